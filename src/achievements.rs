@@ -0,0 +1,230 @@
+//! Optional milestones, unlocked once and kept with the preferences.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use chrono::NaiveDate;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::history::{EntryStatus, History};
+use crate::locale::Locale;
+
+/// Task intervals needed in a day for it to count as a zero-skip day.
+const ZERO_SKIP_MIN_TASKS: u32 = 4;
+/// Days in a row with at least one task for a streak.
+const STREAK_DAYS: usize = 7;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum Achievement {
+    FirstTask,
+    Hundred,
+    WeekStreak,
+    ZeroSkipDay,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 4] = [
+        Achievement::FirstTask,
+        Achievement::Hundred,
+        Achievement::WeekStreak,
+        Achievement::ZeroSkipDay,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::FirstTask => "Complete a task interval",
+            Achievement::Hundred => "Complete 100 task intervals",
+            Achievement::WeekStreak => "Complete a task 7 days in a row",
+            Achievement::ZeroSkipDay => {
+                "Complete at least 4 tasks in a day without skipping or \
+                 voiding any"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Achievement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Achievement::FirstTask => "First steps",
+            Achievement::Hundred => "Centurion",
+            Achievement::WeekStreak => "Week streak",
+            Achievement::ZeroSkipDay => "No skipping",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The achievements the history qualifies for, judging whole days only once
+/// they are over.
+pub fn earned(history: &History, today: NaiveDate) -> Vec<Achievement> {
+    let completed: Vec<_> = history
+        .entries()
+        .iter()
+        .filter(|e| e.status != EntryStatus::Voided)
+        .collect();
+    let days: BTreeSet<NaiveDate> = completed
+        .iter()
+        .map(|e| e.end.date().naive_local())
+        .collect();
+
+    let mut streak = 0;
+    let mut longest_streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in &days {
+        streak = match previous {
+            Some(previous) if previous.succ() == day => streak + 1,
+            _ => 1,
+        };
+        longest_streak = longest_streak.max(streak);
+        previous = Some(day);
+    }
+
+    let zero_skip_day = days.iter().filter(|&&day| day < today).any(|&day| {
+        let stats = history.day_stats(day);
+        stats.completed >= ZERO_SKIP_MIN_TASKS
+            && stats.skipped == 0
+            && stats.voided == 0
+    });
+
+    Achievement::ALL
+        .iter()
+        .copied()
+        .filter(|achievement| match achievement {
+            Achievement::FirstTask => !completed.is_empty(),
+            Achievement::Hundred => completed.len() >= 100,
+            Achievement::WeekStreak => longest_streak >= STREAK_DAYS,
+            Achievement::ZeroSkipDay => zero_skip_day,
+        })
+        .collect()
+}
+
+/// An achievement, and the day it was unlocked.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Unlocked {
+    pub achievement: Achievement,
+    pub on: NaiveDate,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Achievements {
+    pub enabled: bool,
+    pub unlocked: Vec<Unlocked>,
+}
+
+impl Achievements {
+    /// The day the given achievement was unlocked, if it has been.
+    pub fn unlocked_on(&self, achievement: Achievement) -> Option<NaiveDate> {
+        self.unlocked
+            .iter()
+            .find(|unlocked| unlocked.achievement == achievement)
+            .map(|unlocked| unlocked.on)
+    }
+
+    /// Unlock whatever the history now qualifies for, returning the
+    /// achievements newly unlocked.
+    pub fn unlock(
+        &mut self,
+        history: &History,
+        today: NaiveDate,
+    ) -> Vec<Achievement> {
+        let new: Vec<_> = earned(history, today)
+            .into_iter()
+            .filter(|&achievement| self.unlocked_on(achievement).is_none())
+            .collect();
+        self.unlocked
+            .extend(new.iter().map(|&achievement| Unlocked {
+                achievement,
+                on: today,
+            }));
+        new
+    }
+}
+
+/// Every achievement, with the day it was unlocked or what it takes.
+pub fn trophies(
+    ui: &mut egui::Ui,
+    achievements: &Achievements,
+    locale: Locale,
+) {
+    egui::Grid::new("trophies").show(ui, |ui| {
+        for achievement in Achievement::ALL {
+            match achievements.unlocked_on(achievement) {
+                Some(on) => {
+                    ui.label(format!("\u{1F3C6} {}", achievement));
+                    ui.add(
+                        egui::Label::new(format!(
+                            "{} on {}",
+                            achievement.description(),
+                            locale.format_date(on)
+                        ))
+                        .small(),
+                    );
+                }
+                None => {
+                    ui.add(
+                        egui::Label::new(format!("\u{1F512} {}", achievement))
+                            .weak(),
+                    );
+                    ui.add(
+                        egui::Label::new(achievement.description())
+                            .small()
+                            .weak(),
+                    );
+                }
+            }
+            ui.end_row();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EntryDraft;
+
+    #[test]
+    fn test_unlock() {
+        let start = NaiveDate::from_ymd(2021, 11, 1);
+        let day = |days: i64| start + chrono::Duration::days(days);
+        let tasks_on = |history: &mut History, date: NaiveDate| {
+            for hour in 9..13 {
+                let entry = EntryDraft {
+                    date: date.format("%Y-%m-%d").to_string(),
+                    start: format!("{:02}:00", hour),
+                    end: format!("{:02}:25", hour),
+                    task: "Write report".into(),
+                }
+                .parse()
+                .unwrap();
+                history.add(entry);
+            }
+        };
+
+        // days are only judged once they are over
+        let mut history = History::default();
+        let mut achievements = Achievements::default();
+        tasks_on(&mut history, day(0));
+        assert_eq!(
+            vec![Achievement::FirstTask],
+            achievements.unlock(&history, day(0))
+        );
+
+        for days in 1..7 {
+            tasks_on(&mut history, day(days));
+        }
+        assert_eq!(
+            vec![Achievement::WeekStreak, Achievement::ZeroSkipDay],
+            achievements.unlock(&history, day(6))
+        );
+        assert!(achievements.unlock(&history, day(7)).is_empty());
+        assert_eq!(
+            Some(day(0)),
+            achievements.unlocked_on(Achievement::FirstTask)
+        );
+        assert_eq!(None, achievements.unlocked_on(Achievement::Hundred));
+    }
+}
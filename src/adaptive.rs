@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::State;
+use crate::time_of_day::TimeOfDay;
+
+/// Adjusts the duration of an interval starting at the given time of day.
+pub trait DurationModifier {
+    fn modify(
+        &self,
+        state: State,
+        duration: Duration,
+        at: NaiveTime,
+    ) -> Duration;
+}
+
+/// Lengthens breaks later in the day, increasing steadily from no change at
+/// `start` to the full extension at `end`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdaptiveBreaks {
+    pub enabled: bool,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+    /// How much longer breaks are from `end` onwards, in percent.
+    pub max_extension: f32,
+}
+
+impl Default for AdaptiveBreaks {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: TimeOfDay::new(12, 0),
+            end: TimeOfDay::new(17, 0),
+            max_extension: 50.,
+        }
+    }
+}
+
+impl AdaptiveBreaks {
+    /// How far along the curve the given time is, from 0 to 1.
+    fn progress(&self, at: NaiveTime) -> f32 {
+        let minutes = |hour: u32, minute: u32| (hour * 60 + minute) as f32;
+        let start = minutes(self.start.hour, self.start.minute);
+        let end = minutes(self.end.hour, self.end.minute);
+        let at = minutes(at.hour(), at.minute());
+
+        if end <= start {
+            return if at >= start { 1. } else { 0. };
+        }
+
+        ((at - start) / (end - start)).clamp(0., 1.)
+    }
+}
+
+impl DurationModifier for AdaptiveBreaks {
+    fn modify(
+        &self,
+        state: State,
+        duration: Duration,
+        at: NaiveTime,
+    ) -> Duration {
+        if !self.enabled || !state.is_break() {
+            return duration;
+        }
+
+        let factor = 1. + self.progress(at) * self.max_extension / 100.;
+        let millis = duration.as_millis() as f32 * factor.max(0.);
+        Duration::from_millis(millis.round() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_breaks() {
+        let adaptive = AdaptiveBreaks {
+            enabled: true,
+            ..Default::default()
+        };
+        let five = Duration::from_secs(5 * 60);
+        let at = |hour| NaiveTime::from_hms(hour, 0, 0);
+
+        assert_eq!(five, adaptive.modify(State::ShortBreak, five, at(9)));
+        assert_eq!(five, adaptive.modify(State::ShortBreak, five, at(12)));
+        assert_eq!(
+            Duration::from_secs(6 * 60),
+            adaptive.modify(State::ShortBreak, five, at(14))
+        );
+        assert_eq!(
+            Duration::from_secs(450),
+            adaptive.modify(State::LongBreak, five, at(20))
+        );
+
+        // tasks are left alone
+        assert_eq!(five, adaptive.modify(State::Task, five, at(20)));
+
+        let disabled = AdaptiveBreaks::default();
+        assert_eq!(five, disabled.modify(State::ShortBreak, five, at(20)));
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::time_of_day::{self, TimeOfDay};
+
+/// How many minutes late an alarm may still go off, e.g. after a suspend.
+const GRACE_MINUTES: u32 = 5;
+
+/// An alarm which goes off at a time of day, independent of the intervals.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Alarm {
+    pub label: String,
+    pub time: TimeOfDay,
+    /// Days on which the alarm repeats, starting from Monday. If there are
+    /// none, the alarm goes off once and then disables itself.
+    pub weekdays: [bool; 7],
+    pub enabled: bool,
+    /// The day the alarm last went off, so it only does so once a day.
+    pub last_fired: Option<NaiveDate>,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self {
+            label: "Alarm".to_owned(),
+            time: TimeOfDay::new(10, 0),
+            weekdays: [false; 7],
+            enabled: true,
+            last_fired: None,
+        }
+    }
+}
+
+impl Alarm {
+    pub fn is_recurring(&self) -> bool {
+        self.weekdays.iter().any(|day| *day)
+    }
+
+    /// Whether the alarm should go off at the given time.
+    pub fn is_due(&self, now: &DateTime<Local>) -> bool {
+        let weekday = now.weekday().num_days_from_monday() as usize;
+        let scheduled_today = !self.is_recurring() || self.weekdays[weekday];
+
+        let time = TimeOfDay::from(now.time());
+        let minutes = |t: TimeOfDay| t.hour * 60 + t.minute;
+        let late = minutes(time).checked_sub(minutes(self.time));
+
+        self.enabled
+            && scheduled_today
+            && self.last_fired != Some(now.date().naive_local())
+            && matches!(late, Some(late) if late < GRACE_MINUTES)
+    }
+
+    /// Record that the alarm went off, disabling it if it is a one-off.
+    pub fn fire(&mut self, now: &DateTime<Local>) {
+        self.last_fired = Some(now.date().naive_local());
+        if !self.is_recurring() {
+            self.enabled = false;
+        }
+    }
+}
+
+pub fn editor(ui: &mut egui::Ui, alarms: &mut Vec<Alarm>) {
+    const WEEKDAYS: [&str; 7] = ["M", "T", "W", "T", "F", "S", "S"];
+
+    let mut removed = None;
+
+    for (i, alarm) in alarms.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut alarm.enabled, "");
+                ui.text_edit_singleline(&mut alarm.label);
+
+                if ui.button("\u{1F5D9}").clicked() {
+                    removed = Some(i);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                time_of_day::editor(ui, &mut alarm.time);
+
+                for (day, enabled) in WEEKDAYS.iter().zip(&mut alarm.weekdays) {
+                    if ui.selectable_label(*enabled, *day).clicked() {
+                        *enabled = !*enabled;
+                    }
+                }
+            });
+        });
+    }
+
+    if let Some(i) = removed {
+        alarms.remove(i);
+    }
+
+    if ui.button("Add alarm").clicked() {
+        alarms.push(Alarm::default());
+    }
+    ui.add(egui::Label::new("Alarms with no days go off once.").small());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_alarm() {
+        // a Monday
+        let at = |h, m| Local.ymd(2021, 11, 1).and_hms(h, m, 0);
+
+        let mut standup = Alarm {
+            label: "Standup".to_owned(),
+            weekdays: [true, true, true, true, true, false, false],
+            ..Default::default()
+        };
+        assert!(!standup.is_due(&at(9, 59)));
+        assert!(standup.is_due(&at(10, 0)));
+        assert!(standup.is_due(&at(10, 4)));
+        assert!(!standup.is_due(&at(10, 5)));
+
+        standup.fire(&at(10, 0));
+        assert!(standup.enabled);
+        assert!(!standup.is_due(&at(10, 1)));
+        assert!(standup.is_due(&Local.ymd(2021, 11, 2).and_hms(10, 0, 0)));
+        assert!(!standup.is_due(&Local.ymd(2021, 11, 6).and_hms(10, 0, 0)));
+
+        let mut once = Alarm::default();
+        assert!(once.is_due(&Local.ymd(2021, 11, 6).and_hms(10, 0, 0)));
+        once.fire(&at(10, 0));
+        assert!(!once.enabled);
+        assert!(!once.is_due(&Local.ymd(2021, 11, 2).and_hms(10, 0, 0)));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+
+/// A way of getting the user's attention.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum AlertChannel {
+    Notification,
+    Sound,
+    Flash,
+    /// A sequence of strong flashes while the window's contents shake, for
+    /// those who can't rely on sound.
+    Strobe,
+    /// Messages sent to remote services, such as a chat room.
+    Push,
+}
+
+impl AlertChannel {
+    pub const ALL: [AlertChannel; 5] = [
+        AlertChannel::Notification,
+        AlertChannel::Sound,
+        AlertChannel::Flash,
+        AlertChannel::Strobe,
+        AlertChannel::Push,
+    ];
+}
+
+impl fmt::Display for AlertChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AlertChannel::Notification => "Notification",
+            AlertChannel::Sound => "Sound",
+            AlertChannel::Flash => "Flash",
+            AlertChannel::Strobe => "Strobe and shake",
+            AlertChannel::Push => "Push",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A single step in an escalation chain.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub struct AlertStep {
+    pub channel: AlertChannel,
+    /// Delay after the event in seconds before this step fires.
+    pub delay_secs: u32,
+}
+
+impl AlertStep {
+    pub fn new(channel: AlertChannel, delay_secs: u32) -> Self {
+        Self {
+            channel,
+            delay_secs,
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        Duration::from_secs(self.delay_secs as u64)
+    }
+}
+
+/// The default chain, which alerts on the desktop immediately, and pushes to
+/// any configured remote services if the alert is missed for two minutes.
+pub fn default_chain() -> Vec<AlertStep> {
+    vec![
+        AlertStep::new(AlertChannel::Notification, 0),
+        AlertStep::new(AlertChannel::Sound, 0),
+        AlertStep::new(AlertChannel::Push, 120),
+    ]
+}
+
+/// Number of flashes in a strobe alert.
+const STROBE_FLASHES: u32 = 6;
+
+/// Time between the starts of strobe flashes. This keeps to fewer than three
+/// flashes a second, below the rate considered a risk for photosensitive
+/// seizures.
+const STROBE_PERIOD: Duration = Duration::from_millis(400);
+
+/// How far the window's contents move either way while shaking, in points.
+const SHAKE_DISTANCE: f32 = 12.;
+
+/// What to show at some point during a strobe alert.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct StrobeFrame {
+    /// Opacity of the flash, from 0 to 1.
+    pub intensity: f32,
+    /// Whether this flash uses the alternate color.
+    pub alternate: bool,
+    /// Horizontal offset of the window's contents.
+    pub shake: f32,
+}
+
+/// The frame of a strobe alert some time after it started, or `None` once it
+/// is over.
+pub fn strobe_frame(elapsed: Duration) -> Option<StrobeFrame> {
+    let period = STROBE_PERIOD.as_secs_f32();
+    let flash = (elapsed.as_secs_f32() / period) as u32;
+    if flash >= STROBE_FLASHES {
+        return None;
+    }
+
+    // each flash is on for the first half of its period
+    let phase = elapsed.as_secs_f32() / period - flash as f32;
+    let intensity = if phase < 0.5 { 1. } else { 0. };
+
+    // shake back and forth four times per flash, settling towards the end
+    let settle = 1. - flash as f32 / STROBE_FLASHES as f32;
+    let shake =
+        (phase * 4. * std::f32::consts::TAU).sin() * SHAKE_DISTANCE * settle;
+
+    Some(StrobeFrame {
+        intensity,
+        alternate: flash % 2 == 1,
+        shake,
+    })
+}
+
+/// An escalating alert in progress, which fires each step of its chain in
+/// turn until it is acknowledged.
+pub struct Escalation {
+    /// Steps to fire, ordered by delay.
+    chain: Vec<AlertStep>,
+    /// Message to show in notifications.
+    message: String,
+    /// The time of the event which triggered this alert.
+    started: Instant,
+    /// Index of the next step to fire.
+    next_step: usize,
+}
+
+impl Escalation {
+    pub fn new(chain: &[AlertStep], message: impl Into<String>) -> Self {
+        let mut chain = chain.to_vec();
+        chain.sort_by_key(|step| step.delay_secs);
+
+        Self {
+            chain,
+            message: message.into(),
+            started: clock::now(),
+            next_step: 0,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Take all channels whose steps are due to fire.
+    pub fn take_due(&mut self) -> Vec<AlertChannel> {
+        let elapsed = clock::elapsed(self.started);
+        let due: Vec<AlertChannel> = self.chain[self.next_step..]
+            .iter()
+            .take_while(|step| step.delay() <= elapsed)
+            .map(|step| step.channel)
+            .collect();
+
+        self.next_step += due.len();
+        due
+    }
+
+    /// Time until the next step is due, or `None` if the chain is done.
+    pub fn until_next(&self) -> Option<Duration> {
+        self.chain.get(self.next_step).map(|step| {
+            step.delay().saturating_sub(clock::elapsed(self.started))
+        })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next_step >= self.chain.len()
+    }
+}
+
+/// Edit an escalation chain, one step per row.
+pub fn chain_editor(ui: &mut egui::Ui, id: &str, chain: &mut Vec<AlertStep>) {
+    let mut removed = None;
+
+    for (i, step) in chain.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source((id, i))
+                .selected_text(step.channel.to_string())
+                .show_ui(ui, |ui| {
+                    for channel in AlertChannel::ALL {
+                        ui.selectable_value(
+                            &mut step.channel,
+                            channel,
+                            channel.to_string(),
+                        );
+                    }
+                });
+
+            ui.add(
+                egui::DragValue::new(&mut step.delay_secs)
+                    .clamp_range(0..=3600)
+                    .prefix("after ")
+                    .suffix(" s"),
+            );
+
+            if ui.button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = removed {
+        chain.remove(i);
+    }
+
+    if ui.button("Add step").clicked() {
+        chain.push(AlertStep::new(AlertChannel::Notification, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalation() {
+        let chain = [
+            AlertStep::new(AlertChannel::Flash, 120),
+            AlertStep::new(AlertChannel::Notification, 0),
+            AlertStep::new(AlertChannel::Sound, 30),
+        ];
+
+        let mut escalation = Escalation::new(&chain, "Time's up");
+
+        assert_eq!("Time's up", escalation.message());
+        assert_eq!(vec![AlertChannel::Notification], escalation.take_due());
+        assert!(escalation.take_due().is_empty());
+        assert!(!escalation.is_done());
+
+        let until_next = escalation.until_next().unwrap();
+        assert!(until_next <= Duration::from_secs(30));
+        assert!(until_next > Duration::from_secs(29));
+
+        let mut empty = Escalation::new(&[], "");
+        assert!(empty.take_due().is_empty());
+        assert!(empty.is_done());
+        assert_eq!(None, empty.until_next());
+    }
+
+    #[test]
+    fn test_strobe() {
+        let frame = strobe_frame(Duration::from_millis(100)).unwrap();
+        assert_eq!(1., frame.intensity);
+        assert!(!frame.alternate);
+        let frame = strobe_frame(Duration::from_millis(650)).unwrap();
+        assert_eq!(0., frame.intensity);
+        assert!(frame.alternate);
+        assert!(frame.shake.abs() <= SHAKE_DISTANCE);
+        assert_eq!(None, strobe_frame(STROBE_PERIOD * STROBE_FLASHES));
+    }
+}
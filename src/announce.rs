@@ -0,0 +1,410 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::history;
+use crate::State;
+
+/// File types voice clips may be recorded in.
+const CLIP_EXTENSIONS: [&str; 4] = ["ogg", "wav", "flac", "mp3"];
+
+/// How the remaining time is read out during task intervals.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum AnnouncementChannel {
+    Off,
+    Notification,
+    /// Spoken with the system's text-to-speech voice.
+    #[cfg(feature = "speech")]
+    Speech,
+}
+
+impl AnnouncementChannel {
+    pub const ALL: &'static [AnnouncementChannel] = &[
+        AnnouncementChannel::Off,
+        AnnouncementChannel::Notification,
+        #[cfg(feature = "speech")]
+        AnnouncementChannel::Speech,
+    ];
+}
+
+impl Default for AnnouncementChannel {
+    fn default() -> Self {
+        AnnouncementChannel::Off
+    }
+}
+
+impl fmt::Display for AnnouncementChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AnnouncementChannel::Off => "Off",
+            AnnouncementChannel::Notification => "Notification",
+            #[cfg(feature = "speech")]
+            AnnouncementChannel::Speech => "Speech",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Settings for reading out the remaining time.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnnouncementSettings {
+    pub channel: AnnouncementChannel,
+    /// Remaining minutes at which to make an announcement.
+    pub minutes: Vec<u32>,
+    /// Whether to also announce the start of each interval, when spoken.
+    pub transitions: bool,
+    /// ID of the text-to-speech voice, or `None` for the system's default.
+    pub voice: Option<String>,
+    /// Volume of spoken announcements and voice clips, from 0 to 1,
+    /// separate from the alert sound.
+    pub volume: f32,
+}
+
+impl Default for AnnouncementSettings {
+    fn default() -> Self {
+        Self {
+            channel: AnnouncementChannel::default(),
+            minutes: vec![10, 5, 1],
+            transitions: false,
+            voice: None,
+            volume: 1.,
+        }
+    }
+}
+
+/// Something to read out.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Announcement {
+    MinutesLeft(u32),
+    /// The start of an interval in the given state.
+    Transition(State),
+}
+
+impl Announcement {
+    pub fn text(self) -> String {
+        match self {
+            Announcement::MinutesLeft(minutes) => message(minutes),
+            Announcement::Transition(state) => match state {
+                State::Task => "Back to work".to_owned(),
+                State::ShortBreak => "Time for a short break".to_owned(),
+                State::LongBreak => "Time for a long break".to_owned(),
+                State::Idle => String::new(),
+            },
+        }
+    }
+
+    /// Name of the custom voice clip to play instead of speaking, without
+    /// its extension, such as `5-minutes-left` or `short-break`.
+    pub fn clip_name(self) -> String {
+        match self {
+            Announcement::MinutesLeft(minutes) => {
+                message(minutes).replace(' ', "-")
+            }
+            Announcement::Transition(state) => match state {
+                State::Task => "task".to_owned(),
+                State::ShortBreak => "short-break".to_owned(),
+                State::LongBreak => "long-break".to_owned(),
+                State::Idle => "idle".to_owned(),
+            },
+        }
+    }
+}
+
+/// The directory custom voice clips are dropped into.
+pub fn clips_dir() -> Option<PathBuf> {
+    history::data_dir().map(|dir| dir.join("voice"))
+}
+
+/// The custom voice clip for an announcement in the given directory, if one
+/// has been recorded.
+pub fn clip(dir: &Path, announcement: Announcement) -> Option<PathBuf> {
+    let name = announcement.clip_name();
+    CLIP_EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", name, extension)))
+        .find(|path| path.is_file())
+}
+
+/// Works out when the remaining time passes one of the announced minutes.
+#[derive(Default)]
+pub struct Countdown {
+    /// The remaining time when last checked.
+    last_remaining: Option<Duration>,
+}
+
+impl Countdown {
+    /// Start over for a new interval.
+    pub fn reset(&mut self) {
+        self.last_remaining = None;
+    }
+
+    /// The announced minute passed since the last check, if any. Only the
+    /// lowest is returned if several were passed at once.
+    pub fn take_due(
+        &mut self,
+        minutes: &[u32],
+        remaining: Duration,
+    ) -> Option<u32> {
+        let last = self.last_remaining.replace(remaining)?;
+
+        minutes
+            .iter()
+            .copied()
+            .filter(|&m| {
+                let mark = Duration::from_secs(m as u64 * 60);
+                remaining <= mark && last > mark
+            })
+            .min()
+    }
+}
+
+/// The announcement for the given number of minutes left.
+pub fn message(minutes: u32) -> String {
+    match minutes {
+        1 => "1 minute left".to_owned(),
+        minutes => format!("{} minutes left", minutes),
+    }
+}
+
+/// A text-to-speech voice which can be chosen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoiceOption {
+    pub id: String,
+    /// The voice's name and language.
+    pub label: String,
+}
+
+/// Speaks announcements with the system's text-to-speech voice.
+#[cfg(feature = "speech")]
+#[derive(Default)]
+pub struct Speaker {
+    /// Connected to on first use.
+    tts: Option<tts::Tts>,
+    /// The voices installed, listed on first use.
+    voices: Option<Vec<VoiceOption>>,
+    /// The voice and volume last set.
+    applied: Option<(Option<String>, f32)>,
+}
+
+#[cfg(feature = "speech")]
+impl Speaker {
+    fn tts(&mut self) -> Result<&mut tts::Tts, tts::Error> {
+        match &mut self.tts {
+            Some(tts) => Ok(tts),
+            None => Ok(self.tts.insert(tts::Tts::default()?)),
+        }
+    }
+
+    /// The voices to choose from, which may be none if the platform does not
+    /// support choosing.
+    pub fn voices(&mut self) -> &[VoiceOption] {
+        if self.voices.is_none() {
+            let voices = self
+                .tts()
+                .and_then(|tts| tts.voices())
+                .map(|voices| {
+                    voices
+                        .iter()
+                        .map(|voice| VoiceOption {
+                            id: voice.id(),
+                            label: format!(
+                                "{} ({})",
+                                voice.name(),
+                                voice.language()
+                            ),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            self.voices = Some(voices);
+        }
+
+        self.voices.as_deref().unwrap_or_default()
+    }
+
+    /// Use the chosen voice and volume, if they have changed.
+    fn apply(
+        &mut self,
+        settings: &AnnouncementSettings,
+    ) -> Result<(), tts::Error> {
+        let wanted = (settings.voice.clone(), settings.volume);
+        if self.applied.as_ref() == Some(&wanted) {
+            return Ok(());
+        }
+
+        let tts = self.tts()?;
+        if let Some(id) = &settings.voice {
+            if let Some(voice) =
+                tts.voices()?.iter().find(|voice| voice.id() == *id)
+            {
+                tts.set_voice(voice)?;
+            }
+        }
+        let (min, max) = (tts.min_volume(), tts.max_volume());
+        tts.set_volume(min + (max - min) * settings.volume.clamp(0., 1.))?;
+
+        self.applied = Some(wanted);
+        Ok(())
+    }
+
+    pub fn speak(
+        &mut self,
+        text: &str,
+        settings: &AnnouncementSettings,
+    ) -> Result<(), tts::Error> {
+        self.apply(settings)?;
+        self.tts()?.speak(text, true)?;
+        Ok(())
+    }
+}
+
+/// Choose the voice which speaks announcements, and how loudly.
+#[cfg(feature = "speech")]
+pub fn speech_settings_editor(
+    ui: &mut egui::Ui,
+    settings: &mut AnnouncementSettings,
+    voices: &[VoiceOption],
+) {
+    if settings.channel != AnnouncementChannel::Speech {
+        return;
+    }
+
+    ui.checkbox(
+        &mut settings.transitions,
+        "Announce the start of each interval",
+    );
+
+    if !voices.is_empty() {
+        let selected = settings
+            .voice
+            .as_ref()
+            .and_then(|id| voices.iter().find(|voice| voice.id == *id))
+            .map_or("System default", |voice| voice.label.as_str());
+        egui::ComboBox::from_label("Voice")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut settings.voice,
+                    None,
+                    "System default",
+                );
+                for voice in voices {
+                    ui.selectable_value(
+                        &mut settings.voice,
+                        Some(voice.id.clone()),
+                        &voice.label,
+                    );
+                }
+            });
+    }
+
+    ui.add(
+        egui::Slider::new(&mut settings.volume, 0.0..=1.0).text("Voice volume"),
+    );
+
+    #[cfg(feature = "sound")]
+    if let Some(dir) = clips_dir() {
+        ui.add(
+            egui::Label::new(format!(
+                "Recordings in {} are played instead of speaking, \
+                 such as 5-minutes-left.ogg or short-break.ogg",
+                dir.display()
+            ))
+            .small(),
+        );
+    }
+}
+
+pub fn announcements_editor(
+    ui: &mut egui::Ui,
+    settings: &mut AnnouncementSettings,
+) {
+    egui::ComboBox::from_label("Read out remaining time")
+        .selected_text(settings.channel.to_string())
+        .show_ui(ui, |ui| {
+            for &channel in AnnouncementChannel::ALL {
+                ui.selectable_value(
+                    &mut settings.channel,
+                    channel,
+                    channel.to_string(),
+                );
+            }
+        });
+
+    let mut removed = None;
+
+    for (i, minutes) in settings.minutes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(minutes)
+                    .clamp_range(1..=120)
+                    .suffix(" min left"),
+            );
+
+            if ui.button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = removed {
+        settings.minutes.remove(i);
+    }
+
+    if ui.button("Add read-out").clicked() {
+        settings.minutes.push(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown() {
+        let minutes = [10, 5, 1];
+        let mut countdown = Countdown::default();
+        let mut check =
+            |secs| countdown.take_due(&minutes, Duration::from_secs(secs));
+
+        // nothing is announced when the interval starts
+        assert_eq!(None, check(25 * 60));
+        assert_eq!(None, check(10 * 60 + 1));
+        assert_eq!(Some(10), check(10 * 60));
+        assert_eq!(None, check(9 * 60));
+
+        // only the lowest of several passed minutes is announced
+        assert_eq!(Some(1), check(30));
+        assert_eq!(None, check(0));
+
+        countdown.reset();
+        assert_eq!(None, countdown.take_due(&minutes, Duration::ZERO));
+
+        assert_eq!("1 minute left", message(1));
+        assert_eq!("5 minutes left", message(5));
+    }
+
+    #[test]
+    fn test_voice_clips() {
+        let five = Announcement::MinutesLeft(5);
+        let long_break = Announcement::Transition(State::LongBreak);
+        assert_eq!("5 minutes left", five.text());
+        assert_eq!("5-minutes-left", five.clip_name());
+        assert_eq!("1-minute-left", Announcement::MinutesLeft(1).clip_name());
+        assert_eq!("Time for a long break", long_break.text());
+        assert_eq!("long-break", long_break.clip_name());
+
+        let dir = std::env::temp_dir()
+            .join(format!("time-flo-voice-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("long-break.wav"), "").unwrap();
+        assert_eq!(Some(dir.join("long-break.wav")), clip(&dir, long_break));
+        assert_eq!(None, clip(&dir, five));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
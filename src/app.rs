@@ -1,65 +1,117 @@
-use std::fmt;
-use std::time::Duration;
+use std::cell::Cell;
+use std::path::Path;
+#[cfg(all(feature = "tray", target_os = "linux"))]
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Timelike};
 use eframe::{
     egui::{self, Color32},
     epi,
 };
-use log::warn;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "notifications")]
-use notify_rust::Notification;
-
 #[cfg(feature = "sound")]
 use rodio::Source;
 
-use crate::timer::Timer;
-
-macro_rules! slider {
-    ($ui:ident, $val:expr, $name:expr, $range:expr) => {
-        $ui.add(::eframe::egui::Slider::new(&mut $val, $range).text($name));
-    };
-    ($ui:ident, $val:expr, $name:expr, $range:expr, $suffix:expr) => {
-        $ui.add(
-            ::eframe::egui::Slider::new(&mut $val, $range)
-                .text($name)
-                .suffix($suffix),
-        );
-    };
-}
+use crate::achievements::{self, Achievement, Achievements};
+use crate::adaptive::{AdaptiveBreaks, DurationModifier};
+use crate::alarm::{self, Alarm};
+use crate::alert::{self, AlertChannel, AlertStep, Escalation};
+#[cfg(feature = "speech")]
+use crate::announce::Speaker;
+use crate::announce::{
+    self, Announcement, AnnouncementChannel, AnnouncementSettings, Countdown,
+};
+#[cfg(feature = "sound")]
+use crate::audio::{self, Audio};
+use crate::busylight::{self, Busylight, BusylightSettings};
+use crate::capacity::{self, Capacity};
+use crate::clock;
+use crate::completion::{Completion, CompletionWatcher};
+use crate::devices::DeviceMonitor;
+use crate::enforcement::{self, Enforcement};
+use crate::experiment::{self, Experiment};
+use crate::flow::{self, Flow};
+use crate::flowtime::{self, Flowtime};
+#[cfg(feature = "sound")]
+use crate::focus_sound::{self, FocusSoundPlayer, FocusSoundSettings};
+use crate::history::{
+    self, EntryDraft, EntryStatus, History, InterruptionKind, Interruptions,
+    LoggedInterruption,
+};
+use crate::hooks::{self, HookContext, HookEvent, Hooks};
+#[cfg(unix)]
+use crate::http::{self, HttpServer, HttpSettings};
+use crate::import::{self, CsvImport};
+#[cfg(feature = "push")]
+use crate::influx::{self, InfluxExporter, InfluxSettings};
+use crate::invoice::{self, InvoiceSettings};
+#[cfg(unix)]
+use crate::ipc::{self, IpcServer, Snapshot};
+#[cfg(feature = "push")]
+use crate::lights::{self, LightGroup};
+use crate::locale::Locale;
+use crate::lock::{self, PreferencesLock};
+#[cfg(unix)]
+use crate::metrics::Metrics;
+#[cfg(feature = "notifications")]
+use crate::notifier::Notifier;
+use crate::plan::{
+    self, AdminBlocks, CarryOver, QueuedTask, TaskDuration, TaskQueue, Template,
+};
+use crate::power::PowerMonitor;
+use crate::profile::{self, Profile, ProfileRule, ProfileSelection};
+#[cfg(feature = "push")]
+use crate::push::{self, PushSettings};
+use crate::repaint::RepaintScheduler;
+use crate::report::MonthReport;
+use crate::scheduler::{
+    self, CycleRules, LongBreakTrigger, Schedule, Scheduler, State,
+};
+use crate::status_file::{self, StatusFile};
+use crate::store::{HistoryStore, StoreBackend};
+use crate::suspend::SuspendMonitor;
+use crate::theme::{self, ThemeManager};
+use crate::time_of_day::{self, HourFormat, TimeOfDay};
+#[cfg(all(feature = "tray", target_os = "linux"))]
+use crate::timer::TimerEvent;
+use crate::timer::{Timer, TimerEvents, TimerLabel};
+use crate::toast::Toasts;
+#[cfg(all(feature = "tray", target_os = "linux"))]
+use crate::tray::{TrayIcon, TrayStatus};
+use crate::window_title::{self, TitleDetection, TitleRule, TitleSampler};
 
-#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
-pub enum State {
-    Idle,
-    Task,
-    ShortBreak,
-    LongBreak,
-}
+/// How long to wait past a change in the displayed time before repainting.
+const REPAINT_MARGIN: Duration = Duration::from_millis(5);
+/// When running on battery, the displayed time is only updated at multiples
+/// of this many seconds, except during the final minute.
+const BATTERY_REPAINT_STEP: u64 = 5;
 
-impl State {
-    pub fn is_break(&self) -> bool {
-        matches!(self, State::ShortBreak | State::LongBreak)
-    }
-}
+/// Duration of the transition flash in seconds.
+const FLASH_DURATION: f32 = 0.6;
+/// Peak opacity of the transition flash.
+const FLASH_OPACITY: f32 = 0.5;
+/// Opacity of each flash of a strobe alert.
+const STROBE_OPACITY: f32 = 0.85;
 
-impl Default for State {
-    fn default() -> Self {
-        State::Idle
-    }
-}
+/// How long before the end of a task the window starts to dim.
+const DIM_DURATION: Duration = Duration::from_secs(60);
+/// Opacity of the dimming overlay right before a break.
+const DIM_OPACITY: f32 = 0.6;
 
-impl fmt::Display for State {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name = match self {
-            State::Idle => "Idle",
-            State::Task => "Task period",
-            State::ShortBreak => "Short break",
-            State::LongBreak => "Long break",
-        };
+/// Minutes a gap between tasks may run past a long break and still count as
+/// a break in the timeline, for getting back to work.
+const TIMELINE_BREAK_SLACK_MINUTES: i64 = 10;
 
-        write!(f, "{}", name)
-    }
+/// Time the rest of the enclosing block with the `profiling` feature enabled.
+macro_rules! profile_scope {
+    ($name:expr) => {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!($name);
+    };
 }
 
 /// Preferences set by the user.
@@ -74,17 +126,180 @@ struct Preferences {
     pub long_break_minutes: f32,
     /// Number of short breaks before a long break.
     pub num_short_breaks: u32,
+    /// Whether to move on to the next interval as soon as the timer runs
+    /// out, rather than counting overtime until the transition is
+    /// acknowledged.
+    pub auto_transition: bool,
+    /// Whether to start the next task as soon as a break ends, rather than
+    /// waiting for it to be begun by hand.
+    pub auto_start_tasks: bool,
+    /// Whether to start breaks as soon as they are due, rather than waiting
+    /// for them to be begun by hand.
+    pub auto_start_breaks: bool,
+    /// Whether time cut from skipped breaks is added to the next long break.
+    pub break_debt: bool,
+    /// Whether tasks under way can only be aborted, behind a confirmation,
+    /// rather than paused or skipped.
+    pub strict_mode: bool,
+    /// Whether jumping straight to a state moves the break cycle along, as
+    /// if it had been reached in turn.
+    pub jumps_count: bool,
+    /// The most a long break is extended by to pay back skipped breaks, in
+    /// minutes.
+    pub max_break_debt_minutes: f32,
+    /// How much longer to keep going for when snoozing an interval which has
+    /// run out, in minutes.
+    pub snooze_minutes: f32,
+    /// Whether to ask what was accomplished when a task interval completes.
+    pub journal_prompts: bool,
+    /// Whether to ask before moving on to the next task in the plan once
+    /// the current one reaches its estimate.
+    pub suggest_task_switch: bool,
+    /// Counting tasks up instead, with breaks based on the time focused.
+    pub flowtime: Flowtime,
+    /// Tasks with their own task interval durations.
+    pub task_durations: Vec<TaskDuration>,
+    /// Time-boxed admin blocks planned every day.
+    pub admin_blocks: AdminBlocks,
+    /// Contexts intervals can be labelled with.
+    pub contexts: Vec<String>,
+    /// The order intervals follow.
+    pub schedule: Schedule,
+    /// What decides when it is time for a long break.
+    pub long_break_trigger: LongBreakTrigger,
+    /// Minutes spent on tasks before a long break, when triggered by
+    /// focused minutes.
+    pub long_break_focus_minutes: f32,
+    /// Whether to limit how long a task interval may be paused for.
+    pub pause_budget: bool,
+    /// Total pause allowed per task interval in minutes.
+    pub pause_budget_minutes: f32,
+    /// Whether to void task intervals paused for longer than the budget,
+    /// rather than only marking them as interrupted.
+    pub void_over_pause_budget: bool,
+    /// Whether to void task intervals which are skipped early on, rather
+    /// than recording a skip.
+    pub void_abandoned: bool,
+    /// How far through a skipped task interval must be, in percent, to be
+    /// counted as completed rather than voided.
+    pub void_below_percent: f32,
+    /// Lengthening of breaks later in the day.
+    pub adaptive_breaks: AdaptiveBreaks,
+    /// Number of task intervals to complete each day.
+    pub daily_goal: u32,
+    /// Whether to send a notification when the daily goal is reached.
+    pub celebrate_goal: bool,
+    /// Milestones unlocked along the way.
+    pub achievements: Achievements,
+    /// Two profiles being compared on alternating days.
+    pub experiment: Experiment,
+    /// When the working day starts, for checking the daily goal fits.
+    pub work_start: TimeOfDay,
+    /// When the working day ends.
+    pub work_end: TimeOfDay,
+    /// Whether to review the day once it has ended.
+    pub end_of_day_review: bool,
+    /// An iCalendar file with the day's events, or empty for none.
+    pub calendar_file: String,
+    /// Whether to offer to resume a task paused for a calendar event once
+    /// the event is over.
+    pub resume_after_meetings: bool,
+    /// Whether to pause tasks while the microphone or camera is in use, and
+    /// resume once they are released.
+    pub pause_for_calls: bool,
+    /// What to do with the task guessed from the focused window's title.
+    pub title_detection: TitleDetection,
+    /// Which tasks window titles point to.
+    pub title_rules: Vec<TitleRule>,
+    /// Whether to record which applications are focused during task
+    /// intervals.
+    pub track_applications: bool,
+    /// Whether to flash the window when transitioning between states.
+    pub transition_flash: bool,
+    /// Whether to strobe the window in place of playing alert sounds.
+    pub visual_alerts: bool,
+    /// Whether to gradually dim the window in the last minute of a task.
+    pub dim_before_break: bool,
+    /// Name of the user theme to use, or `None` for the default theme.
+    pub theme: Option<String>,
+    /// Whether to switch to the low-power profile when on battery power.
+    pub low_power_on_battery: bool,
+    /// Whether time spent suspended counts toward the running interval, as
+    /// it would on a wall clock.
+    pub count_suspended_time: bool,
+    /// Whether to remind the user when a task is not started after a break.
+    pub start_reminder: bool,
+    /// Grace period before the first reminder in minutes.
+    pub start_reminder_minutes: f32,
+    /// Escalation chain for alerts at the end of a task period.
+    pub task_end_alerts: Vec<AlertStep>,
+    /// Escalation chain for alerts at the end of a break.
+    pub break_end_alerts: Vec<AlertStep>,
+    /// Read-outs of the remaining time during task intervals.
+    pub announcements: AnnouncementSettings,
+    /// Remote services to push alerts to.
+    #[cfg(feature = "push")]
+    pub push: PushSettings,
+    /// Where to export intervals to as time series.
+    #[cfg(feature = "push")]
+    pub influx: InfluxSettings,
+    /// Smart lights to color by state.
+    #[cfg(feature = "push")]
+    pub lights: Vec<LightGroup>,
+    /// Background sound to play during tasks.
+    #[cfg(feature = "sound")]
+    pub focus_sound: FocusSoundSettings,
+    /// Lock on interval durations and program flow.
+    pub lock: PreferencesLock,
+    /// Named alternatives to the default interval durations.
+    pub profiles: Vec<Profile>,
+    /// Rules for activating profiles automatically.
+    pub profile_rules: Vec<ProfileRule>,
+    /// Whether to show the current time under the countdown.
+    pub show_clock: bool,
+    /// Whether to show tenths of a second in the countdown.
+    pub show_tenths: bool,
+    /// How to display hours in the current time.
+    pub hour_format: HourFormat,
+    /// How to write durations, dates, and numbers.
+    pub locale: Locale,
+    /// How strongly long breaks are enforced.
+    pub long_break_enforcement: Enforcement,
+    /// Alarms at times of day, separate from the intervals.
+    pub alarms: Vec<Alarm>,
+    /// Saved day plans.
+    pub templates: Vec<Template>,
+    /// Unfinished tasks set aside for another day.
+    pub carry_over: CarryOver,
+    /// Where the history is kept.
+    pub history_backend: StoreBackend,
+    /// How focused time is rounded when exported for invoicing.
+    pub invoice: InvoiceSettings,
+    /// Whether to keep a status file up to date for shell prompts.
+    pub status_file: bool,
+    /// Shell commands to run on state transitions.
+    pub hooks: Hooks,
+    /// Which bytes to send to a busy light on a serial port.
+    pub busylight: BusylightSettings,
+    /// Whether to serve the timer over HTTP, for share links and the API.
+    #[cfg(unix)]
+    #[serde(flatten)]
+    pub http: HttpSettings,
 }
 
 impl Preferences {
-    pub fn preferred_duration(&self, state: State) -> Duration {
-        let minutes = match state {
-            State::Idle => 0.,
-            State::Task => self.task_minutes,
-            State::ShortBreak => self.short_break_minutes,
-            State::LongBreak => self.long_break_minutes,
-        };
-        Duration::from_secs_f32(minutes * 60.)
+    /// The profile with the given name, or the default profile made up of the
+    /// top-level durations.
+    pub fn profile(&self, name: Option<&str>) -> Profile {
+        name.and_then(|name| self.profiles.iter().find(|p| p.name == name))
+            .cloned()
+            .unwrap_or_else(|| Profile {
+                task_minutes: self.task_minutes,
+                short_break_minutes: self.short_break_minutes,
+                long_break_minutes: self.long_break_minutes,
+                num_short_breaks: self.num_short_breaks,
+                ..Default::default()
+            })
     }
 }
 
@@ -95,287 +310,4873 @@ impl Default for Preferences {
             short_break_minutes: 5.,
             long_break_minutes: 15.,
             num_short_breaks: 3,
+            auto_transition: true,
+            auto_start_tasks: false,
+            auto_start_breaks: true,
+            break_debt: false,
+            strict_mode: false,
+            jumps_count: true,
+            max_break_debt_minutes: 15.,
+            snooze_minutes: 5.,
+            journal_prompts: false,
+            suggest_task_switch: true,
+            flowtime: Flowtime::default(),
+            task_durations: Vec::new(),
+            admin_blocks: AdminBlocks::default(),
+            contexts: vec![
+                "Coding".to_owned(),
+                "Meetings prep".to_owned(),
+                "Writing".to_owned(),
+            ],
+            schedule: Schedule::default(),
+            long_break_trigger: LongBreakTrigger::default(),
+            long_break_focus_minutes: 100.,
+            pause_budget: false,
+            pause_budget_minutes: 2.,
+            void_over_pause_budget: false,
+            void_abandoned: false,
+            void_below_percent: 50.,
+            adaptive_breaks: AdaptiveBreaks::default(),
+            daily_goal: 8,
+            celebrate_goal: true,
+            achievements: Achievements::default(),
+            experiment: Experiment::default(),
+            work_start: TimeOfDay::new(9, 0),
+            work_end: TimeOfDay::new(17, 0),
+            end_of_day_review: true,
+            calendar_file: String::new(),
+            resume_after_meetings: true,
+            pause_for_calls: false,
+            title_detection: TitleDetection::default(),
+            title_rules: Vec::new(),
+            track_applications: false,
+            transition_flash: true,
+            visual_alerts: false,
+            dim_before_break: false,
+            theme: None,
+            low_power_on_battery: false,
+            count_suspended_time: true,
+            start_reminder: true,
+            start_reminder_minutes: 5.,
+            task_end_alerts: alert::default_chain(),
+            break_end_alerts: alert::default_chain(),
+            announcements: AnnouncementSettings::default(),
+            #[cfg(feature = "push")]
+            push: PushSettings::default(),
+            #[cfg(feature = "push")]
+            influx: InfluxSettings::default(),
+            #[cfg(feature = "push")]
+            lights: Vec::new(),
+            #[cfg(feature = "sound")]
+            focus_sound: FocusSoundSettings::default(),
+            lock: PreferencesLock::default(),
+            profiles: Vec::new(),
+            profile_rules: Vec::new(),
+            show_clock: false,
+            show_tenths: false,
+            hour_format: HourFormat::default(),
+            locale: Locale::default(),
+            long_break_enforcement: Enforcement::default(),
+            alarms: Vec::new(),
+            templates: Vec::new(),
+            carry_over: CarryOver::default(),
+            history_backend: StoreBackend::default(),
+            invoice: InvoiceSettings::default(),
+            status_file: false,
+            hooks: Hooks::default(),
+            busylight: BusylightSettings::default(),
+            #[cfg(unix)]
+            http: HttpSettings::default(),
         }
     }
 }
 
+/// Storage key for the cycle in progress.
+const SESSION_KEY: &str = "session";
+
+/// Progress through the current cycle, saved so that it can be resumed after
+/// a restart.
+#[derive(Deserialize, Serialize)]
+struct Session {
+    #[serde(flatten)]
+    scheduler: Scheduler,
+    /// Saved against the wall clock, so that a running timer keeps counting
+    /// while the program is closed.
+    timer: Timer,
+    task: String,
+    #[serde(default)]
+    queue: TaskQueue,
+    #[serde(default, with = "serde_millis")]
+    break_debt: Duration,
+}
+
+impl Session {
+    /// Whether there is any progress worth resuming.
+    fn is_resumable(&self) -> bool {
+        self.scheduler.current() != State::Idle
+            && (self.timer.has_started()
+                || self.scheduler.cycle_position() > 0
+                || !self.task.trim().is_empty()
+                || !self.queue.is_empty())
+    }
+}
+
+/// Text laid out for painting, kept while its text and look are unchanged so
+/// that labels painted every frame do not allocate.
+#[derive(Default)]
+struct CachedLabel {
+    layout: Option<(egui::TextStyle, Color32, Arc<egui::epaint::Galley>)>,
+}
+
+impl CachedLabel {
+    /// Paint the text like a label, laying it out again only if it changed.
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        text: &str,
+        text_style: egui::TextStyle,
+        color: Color32,
+    ) -> egui::Response {
+        let galley = match &self.layout {
+            Some((style, cached_color, galley))
+                if *style == text_style
+                    && *cached_color == color
+                    && galley.text() == text =>
+            {
+                Arc::clone(galley)
+            }
+            _ => {
+                let galley = ui.fonts().layout_no_wrap(
+                    text.to_owned(),
+                    text_style,
+                    color,
+                );
+                self.layout = Some((text_style, color, Arc::clone(&galley)));
+                galley
+            }
+        };
+
+        let (rect, response) =
+            ui.allocate_exact_size(galley.size(), egui::Sense::hover());
+        ui.painter().galley(rect.min, galley);
+        response
+    }
+}
+
 /// State of the TimeFlo program.
 #[derive(Default)]
 pub struct TimeFloApp {
     /// User-defined preferences.
     preferences: Preferences,
-    /// The current state of the program.
-    state: State,
+    /// Where the program is in the cycle of tasks and breaks.
+    scheduler: Scheduler,
+    /// The flow followed with the flow schedule, if it could be loaded.
+    flow: Option<Flow>,
     /// The underlying timer.
     timer: Timer,
-    /// Number of short breaks which have occurred since the last long break, or
-    /// the start of the program.
-    short_break_counter: u32,
+    /// Cached text of the countdown.
+    timer_label: TimerLabel,
+    /// The countdown, laid out.
+    timer_layout: CachedLabel,
+    /// The heading of the main view, laid out.
+    heading_layout: CachedLabel,
+    /// What the user is currently working on.
+    task: String,
+    /// The context of the current work, or empty if unlabelled.
+    context: String,
+    /// The tasks planned for today.
+    queue: TaskQueue,
+    /// The next task in the plan, offered once the current one has reached
+    /// its estimate.
+    task_switch: Option<String>,
+    /// Whether or not the plan view is visible.
+    plan_visible: bool,
+    /// Whether or not the end of day review is visible.
+    review_visible: bool,
+    /// The last day reviewed.
+    reviewed_on: Option<NaiveDate>,
+    /// The day the admin blocks were last planned for.
+    admin_scheduled_on: Option<NaiveDate>,
+    /// Name of the task being added to the queue.
+    new_task: String,
+    /// Name to save the current plan under.
+    new_template: String,
     /// Whether or not the preferences dialog is visible
     preferences_visible: bool,
+    /// Whether the profiler window is visible.
+    #[cfg(feature = "profiling")]
+    profiler_visible: bool,
+    /// Whether the user is being asked to confirm skipping a break.
+    confirm_skip: bool,
+    /// Whether the user is being asked to confirm aborting a task in strict
+    /// mode.
+    confirm_abort: bool,
+    /// Times the current task has been paused.
+    interruptions: Interruptions,
+    /// Whether the user is being asked what interrupted them.
+    classifying_interruption: bool,
+    /// When the task the user is being asked about was abandoned, if it was.
+    abandoned_at: Option<DateTime<Local>>,
+    /// What the user wrote down about the interruption so far.
+    interruption_note: String,
+    /// When the timer was last paused, if it still is.
+    paused_at: Option<Instant>,
+    /// The pauses of the current task which are over.
+    pauses: Vec<history::Pause>,
+    /// Time the current task has spent paused, not counting the current
+    /// pause.
+    paused_for: Duration,
+    /// Whether the current task has gone over the pause budget.
+    over_pause_budget: bool,
+    /// When the calendar event the task was paused for ends.
+    meeting_ends_at: Option<DateTime<Local>>,
+    /// Whether the event the task was paused for is over, so the user is
+    /// offered to resume.
+    meeting_over: bool,
+    /// Time focused on the last task interval, which Flowtime breaks are
+    /// based on.
+    last_focus: Duration,
+    /// Sends out changes to the timer, once per update.
+    timer_events: TimerEvents,
+    /// Whether the interval has run out and is counting overtime until the
+    /// transition is acknowledged.
+    overtime: bool,
+    /// Time the current interval has been snoozed for past its end.
+    snoozed: Duration,
+    /// Time added to the current interval before it ran out.
+    extended: Duration,
+    /// A session from the last run, which the user may choose to resume.
+    saved_session: Option<Session>,
+    /// Today's calendar events, and the task intervals which fit around them.
+    capacity: Capacity,
+    /// Completed task intervals.
+    history: History,
+    /// Today's focus score, and the day it was worked out for, until the
+    /// history or preferences change.
+    cached_focus_score: Cell<Option<(NaiveDate, u32)>>,
+    /// Where the history is kept, and the backend it belongs to, or `None`
+    /// to keep it in memory only.
+    history_store: Option<(StoreBackend, Box<dyn HistoryStore>)>,
+    /// Passphrase for an encrypted history.
+    history_passphrase: String,
+    /// Whether the history is waiting for its passphrase.
+    history_locked: bool,
+    /// Whether or not the history view is visible.
+    history_visible: bool,
+    /// The day shown in the timeline, or `None` for today.
+    timeline_date: Option<NaiveDate>,
+    /// Whether task names and history are hidden, for sharing the screen.
+    presenting: bool,
+    /// The history entry being added or edited, and the index of the entry
+    /// it replaces.
+    history_draft: Option<(EntryDraft, Option<usize>)>,
+    /// The history entry whose details are shown.
+    history_details: Option<usize>,
+    /// The start of the task interval which just completed, and the note
+    /// being written about it.
+    journal_draft: Option<(DateTime<Local>, String)>,
+    /// Feedback about the last action in the history view.
+    history_message: Option<String>,
+    /// Path of the CSV file to import, and the file once loaded.
+    history_import: Option<(String, Option<CsvImport>)>,
+    /// The time at which the current transition flash began, if any.
+    flash_start: Option<Instant>,
+    /// The time at which the current strobe alert began, if any.
+    strobe_start: Option<Instant>,
+    /// The active user theme.
+    theme: ThemeManager,
+    /// Schedules repaints for when the displayed time changes.
+    repaint_scheduler: Option<RepaintScheduler>,
+    /// Keeps track of whether the machine is running on battery.
+    power: PowerMonitor,
+    /// The day and history length achievements were last checked against.
+    achievements_checked: Option<(NaiveDate, usize)>,
+    /// Time cut from skipped breaks, still owed to a long break.
+    break_debt: Duration,
+    /// Keeps track of whether the microphone or camera is in use.
+    devices: DeviceMonitor,
+    /// Whether the task was paused for a call, to be resumed after it.
+    paused_for_call: bool,
+    /// Whether the task was resumed by hand during the current call.
+    ignoring_call: bool,
+    /// Notices when the machine has been suspended.
+    suspend: Option<SuspendMonitor>,
+    /// Delivers the end of the running interval, even while the window is
+    /// not repainting.
+    completion: Option<CompletionWatcher>,
+    /// Guesses the task from window titles during the current interval.
+    title_sampler: TitleSampler,
+    /// The time at which the last break ended, if the following task has not
+    /// yet been started.
+    break_ended_at: Option<Instant>,
+    /// Number of reminders sent since the last break ended.
+    reminders_sent: u32,
+    /// Total time spent waiting to start a task after a break has ended.
+    idle_drift: Duration,
+    /// The alert currently escalating, if it has not been acknowledged.
+    escalation: Option<Escalation>,
+    /// Keeps track of read-outs of the remaining time.
+    countdown: Countdown,
+    /// Reads out the remaining time.
+    #[cfg(feature = "speech")]
+    speaker: Speaker,
+    /// Passphrase being entered to release the preferences lock.
+    unlock_passphrase: String,
+    /// Name of the active profile, or `None` for the default profile.
+    active_profile: Option<String>,
+    /// The profile to activate at the next interval boundary.
+    profile_selection: ProfileSelection,
+    /// Error messages shown to the user.
+    toasts: Toasts,
+    /// The status file, once written.
+    status_file: Option<StatusFile>,
+    /// The busy light, once opened.
+    busylight: Busylight,
+    /// Intervals waiting to be exported.
+    #[cfg(feature = "push")]
+    influx: InfluxExporter,
+    /// Answers status requests from `time-flo ctl`.
+    #[cfg(unix)]
+    ipc: Option<IpcServer>,
+    /// Serves the timer to share links and the API.
+    #[cfg(unix)]
+    http: Option<HttpServer>,
+    /// Whether the HTTP server has the metrics of the current history.
+    #[cfg(unix)]
+    metrics_current: bool,
+    /// Wakes the application up when a command arrives over HTTP.
+    #[cfg(unix)]
+    repaint_signal: Option<Arc<dyn epi::RepaintSignal>>,
+    /// System tray icon showing the remaining minutes.
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    tray: Option<TrayIcon>,
+    /// Timer events the tray icon has yet to catch up on.
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    tray_events: Option<Receiver<TimerEvent>>,
+    /// Desktop notification delivery.
+    #[cfg(feature = "notifications")]
+    notifier: Notifier,
     /// Audio output stream
     #[cfg(feature = "sound")]
-    audio_handle: Option<rodio::OutputStreamHandle>,
+    audio: Audio,
     #[cfg(feature = "sound")]
-    audio_stream: Option<rodio::OutputStream>,
+    focus_sound: FocusSoundPlayer,
 }
 
 impl TimeFloApp {
     fn change_state(&mut self, state: State) {
-        self.state = state;
-        self.timer = Timer::from_duration(
-            self.preferences.preferred_duration(self.state),
-        );
+        self.enter_state(state, true);
+    }
 
-        // if a break, start the timer immediately
-        if state.is_break() {
-            self.timer.start();
+    /// Move to the given state, moving the break cycle along only if
+    /// `counts`.
+    fn enter_state(&mut self, state: State, counts: bool) {
+        // keep track of how long it takes to get back to work after a break
+        self.break_ended_at =
+            if self.scheduler.current().is_break() && state == State::Task {
+                Some(clock::now())
+            } else {
+                None
+            };
+        self.reminders_sent = 0;
+
+        // announce the new interval, but not on startup
+        #[cfg(feature = "speech")]
+        let announce_transition = self.scheduler.current() != State::Idle;
+
+        if self.scheduler.current() == State::Task {
+            self.last_focus = self.timer.elapsed();
         }
 
-        // update break counter
-        match state {
-            State::ShortBreak => self.short_break_counter += 1,
-            State::LongBreak => self.short_break_counter = 0,
-            _ => {}
+        if self.scheduler.current() != State::Idle && self.timer.has_started() {
+            self.run_hook(HookEvent::End);
         }
-    }
 
-    fn next_state(&self) -> State {
-        match self.state {
-            State::Task => {
-                // is it time for a long break?
-                if self.short_break_counter < self.preferences.num_short_breaks
-                {
-                    State::ShortBreak
-                } else {
-                    State::LongBreak
-                }
+        #[cfg(feature = "push")]
+        self.export_interval();
+        #[cfg(feature = "push")]
+        let interval_ended =
+            self.scheduler.current() != State::Idle && self.timer.is_over();
+
+        // flash on transitions between states, but not on startup
+        if self.preferences.transition_flash
+            && self.scheduler.current() != State::Idle
+            && !self.low_power()
+        {
+            self.flash_start = Some(Instant::now());
+        }
+
+        // profiles only switch at interval boundaries
+        self.active_profile = self.selected_profile();
+
+        let rules = self.cycle_rules();
+        let from_idle = self.scheduler.current() == State::Idle;
+        if counts {
+            self.scheduler.enter(&rules, state, self.timer.elapsed());
+        } else {
+            self.scheduler.jump(state);
+        }
+        // the flow may say whether to start, but not on startup
+        let flow_auto_start = self
+            .scheduler
+            .flow_step(&rules)
+            .and_then(|step| step.auto_start)
+            .filter(|_| !from_idle);
+        self.confirm_skip = false;
+        self.confirm_abort = false;
+        self.interruptions = Interruptions::default();
+        self.classifying_interruption = false;
+        self.abandoned_at = None;
+        self.interruption_note.clear();
+        self.paused_at = None;
+        self.pauses.clear();
+        self.paused_for = Duration::ZERO;
+        self.over_pause_budget = false;
+        self.meeting_ends_at = None;
+        self.meeting_over = false;
+        self.paused_for_call = false;
+        self.overtime = false;
+        self.snoozed = Duration::ZERO;
+        self.extended = Duration::ZERO;
+        self.countdown.reset();
+        self.title_sampler.reset();
+
+        if self.enforcement() == Enforcement::LockScreen {
+            if let Err(err) = enforcement::lock_screen() {
+                warn!("Could not lock screen: {:?}", err);
+                self.toasts
+                    .error_once(format!("Could not lock screen: {}", err));
+            }
+        }
+
+        self.timer =
+            if state == State::Task && self.preferences.flowtime.enabled {
+                Timer::count_up()
+            } else {
+                Timer::from_duration(self.preferred_duration())
+            };
+        self.timer_events.reset();
+
+        // pay back time cut from earlier breaks
+        if state == State::LongBreak && self.preferences.break_debt {
+            let cap = Duration::from_secs_f32(
+                self.preferences.max_break_debt_minutes.max(0.) * 60.,
+            );
+            let repaid = self.break_debt.min(cap);
+            self.break_debt -= repaid;
+            self.extend(repaid);
+        }
+
+        // if a break, start the timer immediately, unless asked to wait for
+        // it to be begun by hand and it is not enforced
+        if state.is_break()
+            && (flow_auto_start.unwrap_or(self.preferences.auto_start_breaks)
+                || self.enforcement() != Enforcement::Off)
+        {
+            self.timer.start();
+            self.run_hook(HookEvent::Start);
+        }
+        // and tasks following a break if asked to, unless there is a switch
+        // to another task to answer first
+        let start_task = flow_auto_start.unwrap_or(
+            self.preferences.auto_start_tasks && self.break_ended_at.is_some(),
+        );
+        if state == State::Task && start_task && self.task_switch.is_none() {
+            self.start_timer();
+        }
+
+        #[cfg(feature = "push")]
+        lights::show_state(&self.preferences.lights, state, interval_ended);
+
+        #[cfg(feature = "speech")]
+        {
+            let settings = &self.preferences.announcements;
+            if announce_transition
+                && settings.transitions
+                && settings.channel == AnnouncementChannel::Speech
+            {
+                self.announce(Announcement::Transition(state));
             }
-            _ => State::Task,
         }
     }
 
-    // called when preferences have changed
-    fn update_preferences(&mut self) {
-        // update timer duration according to preferences
-        self.timer
-            .set_duration(self.preferences.preferred_duration(self.state));
+    /// The alerts for the end of the current interval.
+    fn end_alerts(&self) -> &[AlertStep] {
+        if self.scheduler.current().is_break() {
+            &self.preferences.break_end_alerts
+        } else {
+            &self.preferences.task_end_alerts
+        }
     }
 
-    fn main_view(&mut self, ui: &mut egui::Ui) {
-        ui.heading(format!("{}", self.state));
+    /// The notification for the end of the current interval.
+    fn end_message(&self) -> &'static str {
+        match self.scheduler.current() {
+            State::Task => "Time to take a break! \u{1F389}",
+            State::ShortBreak => "Your short break is over.",
+            State::LongBreak => "Your long break is over.",
+            _ => "",
+        }
+    }
 
-        let timer_color = if self.timer.remaining_time().as_secs() <= 5 {
-            Color32::RED
+    /// Have the end of the running interval delivered in the background, in
+    /// case the window is not being repainted by then.
+    fn arm_completion(&mut self) {
+        let low_power = self.low_power();
+        let completion = if self.timer.is_running()
+            && !self.timer.is_over()
+            && !self.timer.is_counting_up()
+        {
+            let chain = self.end_alerts();
+            let immediate = |channel| {
+                chain
+                    .iter()
+                    .any(|step| step.channel == channel && step.delay_secs == 0)
+            };
+            Some(Completion {
+                at: clock::now() + self.timer.remaining_time(),
+                message: Some(self.end_message().to_owned())
+                    .filter(|_| immediate(AlertChannel::Notification)),
+                sound: immediate(AlertChannel::Sound)
+                    && !self.preferences.visual_alerts
+                    && !low_power,
+            })
         } else {
-            ui.visuals().text_color()
+            None
         };
 
-        ui.add(
-            egui::Label::new(format!("{}", self.timer))
-                .monospace()
-                .text_color(timer_color),
-        );
+        if let Some(watcher) = &mut self.completion {
+            watcher.arm(completion);
+        }
+    }
 
-        ui.separator();
+    /// Catch the timer up on time spent suspended, which the monotonic clock
+    /// leaves out, if it should count.
+    fn check_suspend(&mut self) {
+        let suspended = match &self.suspend {
+            Some(suspend) => suspend.take_suspended(),
+            None => return,
+        };
+        if suspended.is_zero() {
+            return;
+        }
 
-        ui.horizontal(|ui| {
-            let timer = &mut self.timer;
+        info!("Resumed after being suspended for {:?}", suspended);
+        if self.preferences.count_suspended_time && self.timer.is_running() {
+            self.timer.add_elapsed(suspended);
+        }
+    }
 
-            if !self.state.is_break() && !timer.has_started() {
-                // waiting for user to begin task
+    /// Move on to the next interval if the timer has just completed, or
+    /// start counting overtime.
+    fn check_timer(&mut self) {
+        profile_scope!("check_timer");
 
-                let begin_button = ui.add(
-                    egui::Button::new("Begin task")
-                        .fill(Color32::BLUE)
-                        .stroke((1., Color32::DARK_BLUE)),
-                );
+        if !self.timer.is_over() || self.overtime {
+            return;
+        }
 
-                if begin_button.clicked() {
-                    timer.start();
-                }
-            } else if timer.is_paused() {
-                // the timer is paused
-                if ui.button("Resume").clicked() {
-                    timer.start();
-                }
-            } else {
-                // the timer is currently running
-                if ui.button("Pause").clicked() {
-                    timer.pause();
+        // notify the user, escalating until acknowledged, apart from what was
+        // already delivered in the background
+        let delivered = self
+            .completion
+            .as_mut()
+            .and_then(CompletionWatcher::take_delivered);
+        let chain: Vec<AlertStep> = self
+            .end_alerts()
+            .iter()
+            .copied()
+            .filter(|step| {
+                let delivered = match &delivered {
+                    Some(delivered) if step.delay_secs == 0 => delivered,
+                    _ => return true,
+                };
+                match step.channel {
+                    AlertChannel::Notification => {
+                        delivered.completion.message.is_none()
+                    }
+                    AlertChannel::Sound => !delivered.completion.sound,
+                    _ => true,
                 }
-            }
+            })
+            .collect();
 
-            // show a skip button for breaks, or if the timer is running
-            if (self.state.is_break() || timer.has_started())
-                && ui.button("Skip").clicked()
-            {
-                self.change_state(self.next_state());
+        // keep a sound played in the background audible over any focus sound,
+        // as when it is played here
+        #[cfg(feature = "sound")]
+        if let Some(delivered) = &delivered {
+            if delivered.completion.sound {
+                self.focus_sound.duck(delivered.sound_duration);
             }
-        });
+            if let Some(err) = &delivered.sound_error {
+                self.toasts.error_once(format!(
+                    "Could not load alert sound, using a beep instead: {}",
+                    err
+                ));
+            }
+        }
+        self.escalation = Some(Escalation::new(&chain, self.end_message()));
 
-        ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), |ui| {
-            // gear icon
-            if ui.button("\u{2699}").clicked() {
-                self.preferences_visible = true;
+        // change to the next, or wait for the user to, recording the task
+        // once it is over for good. Enforced breaks always move on, since
+        // there may be no way out of them by hand
+        if self.preferences.auto_transition
+            || self.enforcement() != Enforcement::Off
+        {
+            if self.scheduler.current() == State::Task {
+                self.record_task();
             }
-        });
+            self.change_state(self.next_state());
+        } else {
+            self.overtime = true;
+        }
     }
 
-    fn preferences_view(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Preferences");
-
-        ui.separator();
+    /// Keep going for a while longer after the interval has run out.
+    fn snooze(&mut self) {
+        let snooze =
+            Duration::from_secs_f32(self.preferences.snooze_minutes * 60.);
 
-        let prefs = &mut self.preferences;
+        // count the overtime before snoozing too
+        self.snoozed += self.timer.overtime() + snooze;
+        self.timer.set_duration(self.interval_duration());
+        self.overtime = false;
+        self.escalation = None;
+    }
 
-        ui.collapsing("Interval durations", |ui| {
-            slider!(ui, prefs.task_minutes, "Task period", 0.5..=120.0);
-            slider!(ui, prefs.short_break_minutes, "Short break", 0.5..=120.0);
-            slider!(ui, prefs.long_break_minutes, "Long break", 0.5..=120.0);
-        });
+    /// Whether the current interval may be lengthened by hand, which a
+    /// commitment lock rules out for breaks.
+    fn can_lengthen(&self) -> bool {
+        !self.scheduler.current().is_break()
+            || !self.preferences.lock.is_locked(Local::now())
+    }
 
-        ui.collapsing("Program flow", |ui| {
-            slider!(ui, prefs.num_short_breaks, "Short breaks", 1..=16);
-        });
+    /// Add some time to the current interval only, leaving the preferred
+    /// duration as it is.
+    fn extend(&mut self, duration: Duration) {
+        self.extended += duration;
+        self.timer.extend(duration);
+    }
 
-        ui.separator();
+    /// The preferred duration of the current interval, plus any time it has
+    /// been snoozed or extended by.
+    fn interval_duration(&self) -> Duration {
+        self.preferred_duration() + self.snoozed + self.extended
+    }
 
-        ui.horizontal(|ui| {
-            if ui.button("Reset to default").clicked() {
-                self.preferences = Preferences::default();
-            }
+    /// Add the current task interval to the history, as far as it got.
+    fn record_interval(&mut self, status: EntryStatus) {
+        let end = Local::now();
+        let elapsed = chrono::Duration::from_std(self.timer.elapsed())
+            .unwrap_or_else(|_| chrono::Duration::zero());
+        let pauses = self.take_pauses(end);
 
-            if ui.button("Close").clicked() {
-                self.update_preferences();
-                self.preferences_visible = false;
-            }
+        self.history.add(history::Entry {
+            start: end - elapsed,
+            end,
+            task: self.task().unwrap_or_default(),
+            manual: false,
+            interruptions: self.interruptions,
+            status,
+            applications: self.title_sampler.take_applications(),
+            snoozed_minutes: (self.snoozed.as_secs_f32() / 60.).round() as u32,
+            note: String::new(),
+            context: self.context.trim().to_owned(),
+            pauses,
         });
+        self.save_history();
     }
 
-    #[cfg(feature = "notifications")]
-    fn show_notification(&self, body: &str) -> crate::Result<()> {
-        Notification::new()
-            .summary("TimeFlo")
-            .body(body)
-            .timeout(10000)
-            .show()?;
-        Ok(())
+    /// The pauses of the current task, including one still going on at `end`.
+    fn take_pauses(&mut self, end: DateTime<Local>) -> Vec<history::Pause> {
+        let mut pauses = std::mem::take(&mut self.pauses);
+        if let Some(paused_at) = self.paused_at {
+            pauses.push(history::Pause::until(end, clock::elapsed(paused_at)));
+        }
+        pauses
     }
 
-    #[cfg(feature = "sound")]
-    fn play_alert_sound(&self) -> crate::Result<()> {
-        use std::io::BufReader;
+    /// Add the task interval which just completed to the history.
+    fn record_task(&mut self) {
+        let today = Local::today().naive_local();
+        let completed_before = self.history.day_stats(today).completed;
+        self.record_interval(if self.over_pause_budget {
+            EntryStatus::Interrupted
+        } else {
+            EntryStatus::Completed
+        });
+
+        if self.preferences.celebrate_goal
+            && self.goal_reached(completed_before)
+        {
+            info!("Reached the daily goal");
 
-        if let Some(audio_handle) = &self.audio_handle {
-            let file = std::fs::File::open("resources/alert.ogg")?;
-            let source = rodio::Decoder::new(BufReader::new(file))?;
-            audio_handle.play_raw(source.convert_samples())?;
+            #[cfg(feature = "notifications")]
+            self.notifier.send(&format!(
+                "Daily goal of {} tasks reached, well done! \u{1F389}",
+                self.preferences.daily_goal
+            ));
         }
 
-        Ok(())
+        if self.preferences.journal_prompts {
+            self.journal_draft = self
+                .history
+                .entries()
+                .last()
+                .map(|entry| (entry.start, String::new()));
+        }
+
+        // move on through the plan, or offer to at the next interval
+        let estimate_reached = self.queue.count_interval();
+        let next = self.queue.next().map(|task| task.name.clone());
+        if estimate_reached
+            && next.is_some()
+            && self.preferences.suggest_task_switch
+        {
+            self.task_switch = next;
+            return;
+        }
+        if estimate_reached {
+            self.queue.advance();
+        }
+        if let Some(next) = self.queue.current() {
+            self.task = next.name.clone();
+        }
     }
-}
 
-impl epi::App for TimeFloApp {
-    fn name(&self) -> &str {
-        "TimeFlo"
+    /// Whether the task just recorded reached the daily goal, given the tasks
+    /// completed today before it.
+    fn goal_reached(&self, completed_before: u32) -> bool {
+        let goal = self.preferences.daily_goal;
+        let completed = self
+            .history
+            .day_stats(Local::today().naive_local())
+            .completed;
+        completed_before < goal && completed >= goal
     }
 
-    fn setup(
-        &mut self,
-        _ctx: &egui::CtxRef,
-        _frame: &mut epi::Frame<'_>,
-        storage: Option<&dyn epi::Storage>,
-    ) {
-        // Load previous app state (if any).
-        if let Some(storage) = storage {
-            self.preferences =
-                epi::get_value(storage, epi::APP_KEY).unwrap_or_default();
+    /// Move on to the next task in the plan, as suggested.
+    fn accept_task_switch(&mut self) {
+        if self.task_switch.take().is_none() {
+            return;
         }
 
-        self.change_state(State::Task);
+        self.queue.advance();
+        if let Some(next) = self.queue.current() {
+            self.task = next.name.clone();
+        }
+    }
 
-        // initialize audio
-        #[cfg(feature = "sound")]
-        match rodio::OutputStream::try_default() {
-            Ok((audio_stream, handle)) => {
-                self.audio_stream = Some(audio_stream);
-                self.audio_handle = Some(handle);
+    /// Replace today's plan with a saved one. A commitment lock keeps to the
+    /// profile and schedule in use.
+    fn apply_template(&mut self, template: &Template) {
+        self.queue = template.queue();
+        self.admin_scheduled_on = None;
+        if !self.preferences.lock.is_locked(Local::now()) {
+            if let Some(profile) = &template.profile {
+                self.profile_selection =
+                    ProfileSelection::Named(profile.clone());
             }
-            Err(err) => {
-                warn!("Could not acquire audio output stream: {:?}", err);
+            if let Some(schedule) = &template.schedule {
+                self.preferences.schedule = schedule.clone();
             }
         }
+        if let Some(task) = self.queue.current() {
+            self.task = task.name.clone();
+        }
     }
 
-    fn save(&mut self, storage: &mut dyn epi::Storage) {
-        epi::set_value(storage, epi::APP_KEY, &self.preferences);
+    /// Start the current interval over from its full preferred duration,
+    /// without moving on through the cycle.
+    fn restart(&mut self) {
+        let was_started = self.timer.has_started();
+
+        self.timer = if self.timer.is_counting_up() {
+            Timer::count_up()
+        } else {
+            Timer::from_duration(self.preferred_duration())
+        };
+        self.timer_events.reset();
+        self.interruptions = Interruptions::default();
+        self.classifying_interruption = false;
+        self.paused_at = None;
+        self.pauses.clear();
+        self.paused_for = Duration::ZERO;
+        self.over_pause_budget = false;
+        self.meeting_ends_at = None;
+        self.meeting_over = false;
+        self.paused_for_call = false;
+        self.overtime = false;
+        self.snoozed = Duration::ZERO;
+        self.extended = Duration::ZERO;
+        self.escalation = None;
+        self.countdown.reset();
+
+        if was_started {
+            self.timer.start();
+        }
     }
 
-    fn update(&mut self, ctx: &egui::CtxRef, _frame: &mut epi::Frame<'_>) {
+    /// Begin the task, or resume after a pause.
+    fn start_timer(&mut self) {
         if self.timer.is_running() {
-            ctx.request_repaint();
+            return;
         }
 
-        // has the timer just complete?
-        if self.timer.is_over() {
-            // notify the user
-            #[cfg(feature = "notifications")]
-            {
-                let message = match self.state {
-                    State::Task => "Time to take a break! \u{1F389}",
-                    State::ShortBreak => "Your short break is over.",
-                    State::LongBreak => "Your long break is over.",
-                    _ => "",
-                };
-
-                if let Err(err) = self.show_notification(message) {
-                    warn!("Could not show notification: {:?}", err);
-                }
+        let first_start = !self.timer.has_started();
+        if first_start {
+            if let Some(ended_at) = self.break_ended_at.take() {
+                self.idle_drift += clock::elapsed(ended_at);
             }
+        }
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused = clock::elapsed(paused_at);
+            self.paused_for += paused;
+            self.pauses
+                .push(history::Pause::until(Local::now(), paused));
+        }
 
-            #[cfg(feature = "sound")]
-            if let Err(err) = self.play_alert_sound() {
-                warn!("Could not play sound: {:?}", err);
-            }
+        self.timer.start();
+        self.classifying_interruption = false;
+        self.abandoned_at = None;
+        self.meeting_ends_at = None;
+        self.meeting_over = false;
 
-            // change to the next
-            self.change_state(self.next_state());
+        if first_start {
+            self.run_hook(HookEvent::Start);
         }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if self.preferences_visible {
-                self.preferences_view(ui);
-            } else {
-                self.main_view(ui);
+        // starting a task without answering keeps to the current one
+        if self.scheduler.current() == State::Task {
+            self.task_switch = None;
+        }
+    }
+
+    fn pause_timer(&mut self) {
+        if !self.timer.is_running() {
+            return;
+        }
+
+        self.timer.pause();
+        self.paused_at = Some(clock::now());
+
+        if self.scheduler.current() == State::Task {
+            self.interruptions.total += 1;
+            self.classifying_interruption = true;
+
+            // paused for a meeting which is on now, or about to start
+            if self.preferences.resume_after_meetings {
+                let lead = chrono::Duration::minutes(5);
+                self.meeting_ends_at = capacity::event_at(
+                    &self.capacity.calendar,
+                    Local::now(),
+                    lead,
+                )
+                .map(|event| event.end);
+            }
+        }
+    }
+
+    /// Whether strict mode rules out pausing and skipping right now, which is
+    /// while a timed task is under way.
+    fn is_strict(&self) -> bool {
+        self.preferences.strict_mode
+            && self.scheduler.current() == State::Task
+            && self.timer.has_started()
+            && !self.timer.is_counting_up()
+            && !self.overtime
+    }
+
+    /// Give up on the task under way in strict mode, voiding it and starting
+    /// over.
+    fn abort_task(&mut self) {
+        self.record_interval(EntryStatus::Voided);
+        self.change_state(State::Task);
+        self.abandoned_at = Some(Local::now());
+    }
+
+    /// Log why the task was paused or abandoned, as the user answered.
+    fn log_interruption(&mut self, kind: InterruptionKind) {
+        let abandoned_at = self.abandoned_at.take();
+        if abandoned_at.is_none() {
+            match kind {
+                InterruptionKind::Internal => self.interruptions.internal += 1,
+                InterruptionKind::External => self.interruptions.external += 1,
             }
+        }
+        self.classifying_interruption = false;
+
+        self.history.log_interruption(LoggedInterruption {
+            at: abandoned_at.unwrap_or_else(Local::now),
+            kind,
+            abandoned: abandoned_at.is_some(),
+            note: self.interruption_note.trim().to_owned(),
         });
+        self.interruption_note.clear();
+        self.save_history();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Skip the current interval, asking first if breaks are enforced.
+    fn request_skip(&mut self) {
+        if !self.scheduler.current().is_break() && !self.timer.has_started() {
+            return;
+        }
+        if self.is_strict() {
+            return;
+        }
 
-    #[test]
-    fn test_app() {
-        // test an aspect state changing
-        // TODO more!
+        match self.enforcement() {
+            Enforcement::Nag => self.confirm_skip = true,
+            // there is no way past the overlay
+            Enforcement::Overlay => {}
+            _ => self.skip(),
+        }
+    }
 
-        let mut app = TimeFloApp {
-            state: State::Task,
-            short_break_counter: 3,
-            preferences: Preferences {
-                num_short_breaks: 3,
-                ..Default::default()
-            },
-            ..Default::default()
-        };
+    /// Carry out commands sent by other processes, such as editors, or over
+    /// HTTP.
+    #[cfg(unix)]
+    fn handle_commands(&mut self) {
+        let mut commands = Vec::new();
+        if let Some(ipc) = &self.ipc {
+            commands.extend(ipc.take_commands());
+        }
+        if let Some(http) = &self.http {
+            commands.extend(http.take_commands());
+        }
 
-        app.change_state(app.next_state());
+        for command in commands {
+            match command {
+                ipc::Command::Start => self.start_timer(),
+                // there is no pausing in strict mode, or during the overlay
+                ipc::Command::Pause | ipc::Command::Toggle
+                    if (self.is_strict()
+                        || self.enforcement() == Enforcement::Overlay)
+                        && self.timer.is_running() => {}
+                ipc::Command::Pause => self.pause_timer(),
+                ipc::Command::Toggle if self.timer.is_running() => {
+                    self.pause_timer()
+                }
+                ipc::Command::Toggle => self.start_timer(),
+                ipc::Command::Skip => self.request_skip(),
+            }
+        }
+    }
 
-        assert_eq!(State::LongBreak, app.state);
-        assert!(app.timer.has_started());
+    fn skip(&mut self) {
+        let abandoned = self.leave_interval();
+        self.change_state(self.next_state());
+        // ask why, now that the task has been left behind
+        if abandoned {
+            self.abandoned_at = Some(Local::now());
+        }
+    }
+
+    /// Jump straight to the given state, whatever would come next.
+    fn jump_to(&mut self, state: State) {
+        let abandoned = self.leave_interval();
+        self.enter_state(state, self.preferences.jumps_count);
+        if abandoned {
+            self.abandoned_at = Some(Local::now());
+        }
+    }
+
+    /// Wrap up the current interval before moving on from it, returning
+    /// whether a task under way was abandoned.
+    fn leave_interval(&mut self) -> bool {
+        let mut abandoned = false;
+        if self.scheduler.current() == State::Task && self.overtime {
+            // acknowledging the end of the task, however long it ran over
+            self.record_task();
+        } else if self.scheduler.current() == State::Task
+            && self.timer.is_counting_up()
+        {
+            // Flowtime tasks end whenever the focus does
+            if self.timer.has_started() {
+                self.record_task();
+            }
+        } else if self.scheduler.current() == State::Task
+            && self.timer.has_started()
+        {
+            abandoned = true;
+            let prefs = &self.preferences;
+            if !prefs.void_abandoned {
+                self.history.record_skip(Local::now());
+                self.save_history();
+            } else if self.timer.progress() * 100. < prefs.void_below_percent {
+                self.record_interval(EntryStatus::Voided);
+            } else {
+                // far enough along to count
+                self.record_task();
+            }
+        } else if self.scheduler.current().is_break()
+            && self.preferences.break_debt
+        {
+            // time cut from a break is owed to the next long break
+            self.break_debt += self.timer.remaining_time();
+        }
+
+        abandoned
+    }
+
+    /// Today's focus score, leaving out admin blocks.
+    fn focus_score(&self) -> u32 {
+        let today = Local::today().naive_local();
+        if let Some((day, score)) = self.cached_focus_score.get() {
+            if day == today {
+                return score;
+            }
+        }
+
+        let admin = &self.preferences.admin_blocks;
+        let score = self
+            .history
+            .day_stats_where(today, |entry| !admin.is_admin(&entry.task))
+            .focus_score(self.preferences.daily_goal);
+        self.cached_focus_score.set(Some((today, score)));
+        score
+    }
+
+    /// Whether the current task is an admin block.
+    fn is_admin_block(&self) -> bool {
+        self.scheduler.current() == State::Task
+            && self.preferences.admin_blocks.is_admin(&self.task)
+    }
+
+    /// Forget what was worked out from the history, now that it changed.
+    fn history_changed(&mut self) {
+        self.cached_focus_score.set(None);
+        #[cfg(unix)]
+        {
+            self.metrics_current = false;
+        }
+    }
+
+    fn save_history(&mut self) {
+        // the history is saved whenever it changes
+        self.history_changed();
+
+        let store = match &self.history_store {
+            Some((_, store)) => store,
+            None => return,
+        };
+
+        if let Err(err) = store.save(&self.history) {
+            warn!("Could not save history: {:?}", err);
+            self.toasts
+                .error(format!("Could not save history: {}", err));
+        }
+    }
+
+    fn next_state(&self) -> State {
+        self.scheduler
+            .next(&self.cycle_rules(), self.timer.elapsed())
+    }
+
+    /// When the plan is expected to be done, if there is anything left in it.
+    fn queue_finish(&self) -> Option<NaiveDateTime> {
+        let state = self.scheduler.current();
+        let under_way = Some(self.timer.remaining_time()).filter(|_| {
+            !self.timer.is_counting_up()
+                && (state.is_break() || self.timer.has_started())
+        });
+        self.queue.finish_time(
+            self.scheduler,
+            &self.cycle_rules(),
+            under_way,
+            (self.preferences.work_start, self.preferences.work_end),
+            |scheduler, task| self.preferred_duration_for(scheduler, task),
+            Local::now().naive_local(),
+        )
+    }
+
+    /// A label such as "Queue done at ~17:40", if there is anything left in
+    /// the plan.
+    fn queue_finish_label(&self) -> Option<String> {
+        let finish = self.queue_finish()?;
+        let time = self.preferences.hour_format.format(finish.time());
+        let date = finish.date();
+        Some(if date == Local::today().naive_local() {
+            format!("Queue done at ~{}", time)
+        } else {
+            format!(
+                "Queue done at ~{} on {}",
+                time,
+                self.preferences.locale.format_date(date)
+            )
+        })
+    }
+
+    /// The rules deciding which interval comes next.
+    fn cycle_rules(&self) -> CycleRules {
+        CycleRules {
+            schedule: self.preferences.schedule.clone(),
+            long_break_trigger: self.preferences.long_break_trigger,
+            num_short_breaks: self.profile().num_short_breaks,
+            long_break_focus_minutes: self.preferences.long_break_focus_minutes,
+            flow: self.flow.clone(),
+        }
+    }
+
+    /// Load the flow file, if the flow schedule is chosen.
+    fn load_flow(&mut self) {
+        self.flow = None;
+        if self.preferences.schedule != Schedule::Flow {
+            return;
+        }
+
+        let path = match flow::flow_path() {
+            Some(path) => path,
+            None => {
+                self.toasts.error_once(
+                    "There is no flow file, so the usual cycle is followed",
+                );
+                return;
+            }
+        };
+        match Flow::load(&path) {
+            Ok(flow) => self.flow = Some(flow),
+            Err(err) => {
+                warn!("Could not load flow: {:?}", err);
+                self.toasts
+                    .error_once(format!("Could not load flow: {}", err));
+            }
+        }
+    }
+
+    /// How strongly the current interval is enforced.
+    fn enforcement(&self) -> Enforcement {
+        if self.scheduler.current() == State::LongBreak {
+            self.preferences.long_break_enforcement
+        } else {
+            Enforcement::Off
+        }
+    }
+
+    /// Run the hook for the given transition of the current interval, if
+    /// there is one.
+    fn run_hook(&mut self, event: HookEvent) {
+        let state = self.scheduler.current();
+        let command = self.preferences.hooks.command(event, state);
+        if command.is_empty() {
+            return;
+        }
+
+        let duration = match event {
+            HookEvent::Start if self.timer.is_counting_up() => None,
+            HookEvent::Start => Some(self.timer.remaining_time()),
+            HookEvent::End => Some(self.timer.elapsed()),
+        };
+        let context = HookContext {
+            event,
+            state,
+            duration,
+            task: self.task().filter(|_| state == State::Task),
+            // Flowtime tasks end whenever the focus does
+            completed: self.timer.is_over() || self.timer.is_counting_up(),
+        };
+
+        if let Err(err) = hooks::run(command, &context) {
+            warn!("Could not run hook {:?}: {:?}", command, err);
+            self.toasts
+                .error_once(format!("Could not run hook: {}", err));
+        }
+    }
+
+    /// The current task, if the user has entered one.
+    fn task(&self) -> Option<String> {
+        let task = self.task.trim();
+        if task.is_empty() {
+            None
+        } else {
+            Some(task.to_owned())
+        }
+    }
+
+    /// The active profile.
+    fn profile(&self) -> Profile {
+        self.preferences.profile(self.active_profile.as_deref())
+    }
+
+    /// The duration of the current interval if it started now, after any
+    /// adjustments.
+    fn preferred_duration(&self) -> Duration {
+        self.preferred_duration_for(&self.scheduler, &self.task)
+    }
+
+    /// The duration of the interval the given scheduler is in if it started
+    /// now, while working on the given task.
+    fn preferred_duration_for(
+        &self,
+        scheduler: &Scheduler,
+        task: &str,
+    ) -> Duration {
+        let modifiers: [&dyn DurationModifier; 1] =
+            [&self.preferences.adaptive_breaks];
+
+        let state = scheduler.current();
+        let flow_duration = scheduler
+            .flow_step(&self.cycle_rules())
+            .and_then(|step| step.minutes)
+            .map(|minutes| Duration::from_secs_f32(minutes * 60.));
+
+        let flowtime = &self.preferences.flowtime;
+        let task_duration = match state {
+            State::Task if self.preferences.admin_blocks.is_admin(task) => {
+                Some(self.preferences.admin_blocks.duration())
+            }
+            State::Task => {
+                plan::duration_for(&self.preferences.task_durations, task)
+            }
+            _ => None,
+        };
+        let duration = match state {
+            State::ShortBreak if flowtime.enabled => {
+                flowtime.break_for(self.last_focus)
+            }
+            _ => flow_duration
+                .or(task_duration)
+                .unwrap_or_else(|| self.profile().preferred_duration(state)),
+        };
+
+        let now = Local::now().time();
+        modifiers.iter().fold(duration, |duration, modifier| {
+            modifier.modify(state, duration, now)
+        })
+    }
+
+    /// Request a repaint after the given amount of time, even if the timer is
+    /// not running.
+    fn schedule_wakeup(&self, wait: Duration) {
+        if let Some(scheduler) = &self.repaint_scheduler {
+            scheduler.schedule(wait);
+        }
+    }
+
+    /// Fire any due steps of the current escalating alert.
+    fn run_escalation(&mut self, ctx: &egui::CtxRef) {
+        profile_scope!("run_escalation");
+
+        // any click on the window acknowledges the alert
+        if ctx.input().pointer.any_pressed() {
+            self.escalation = None;
+        }
+
+        self.run_escalation_steps();
+    }
+
+    /// Fire the steps of the current escalating alert which are due.
+    fn run_escalation_steps(&mut self) {
+        let (due, message) = match &mut self.escalation {
+            Some(escalation) => {
+                (escalation.take_due(), escalation.message().to_owned())
+            }
+            None => return,
+        };
+
+        for channel in due {
+            self.fire_alert(channel, &message);
+        }
+
+        match self.escalation.as_ref().and_then(Escalation::until_next) {
+            Some(wait) => self.schedule_wakeup(wait),
+            None => self.escalation = None,
+        }
+    }
+
+    #[cfg_attr(
+        not(any(feature = "notifications", feature = "push")),
+        allow(unused_variables)
+    )]
+    fn fire_alert(&mut self, channel: AlertChannel, message: &str) {
+        match channel {
+            AlertChannel::Notification => {
+                #[cfg(feature = "notifications")]
+                self.notifier.send(message);
+            }
+            AlertChannel::Sound if self.preferences.visual_alerts => {
+                self.fire_alert(AlertChannel::Strobe, message)
+            }
+            AlertChannel::Sound =>
+            {
+                #[cfg(feature = "sound")]
+                if !self.low_power() {
+                    if let Err(err) = self.play_alert_sound() {
+                        warn!("Could not play sound: {:?}", err);
+                        self.toasts.error_once(format!(
+                            "Could not play sound: {}",
+                            err
+                        ));
+                    }
+                }
+            }
+            AlertChannel::Flash => {
+                if !self.low_power() {
+                    self.flash_start = Some(Instant::now());
+                }
+            }
+            // shown even in low power, since it may be the only alert noticed
+            AlertChannel::Strobe => self.strobe_start = Some(Instant::now()),
+            AlertChannel::Push => {
+                #[cfg(feature = "push")]
+                self.preferences.push.send(message);
+            }
+        }
+    }
+
+    /// Set off any alarms which are due, through the usual alert channels.
+    fn check_alarms(&mut self) {
+        if !self.preferences.alarms.iter().any(|alarm| alarm.enabled) {
+            return;
+        }
+
+        let now = Local::now();
+        for i in 0..self.preferences.alarms.len() {
+            let alarm = &mut self.preferences.alarms[i];
+            if alarm.is_due(&now) {
+                alarm.fire(&now);
+                let message = if self.presenting {
+                    "Alarm".to_owned()
+                } else {
+                    alarm.label.clone()
+                };
+
+                // alarms go off alongside any alert still waiting to be
+                // acknowledged, rather than replacing it
+                for channel in [AlertChannel::Notification, AlertChannel::Sound]
+                {
+                    self.fire_alert(channel, &message);
+                }
+            }
+        }
+
+        // check again when the minute changes
+        self.schedule_wakeup(Duration::from_secs(60 - now.second() as u64));
+    }
+
+    /// Read out the remaining time at the chosen minutes of a task interval.
+    #[cfg_attr(
+        not(any(feature = "notifications", feature = "speech")),
+        allow(unused_variables)
+    )]
+    fn check_countdown(&mut self) {
+        let settings = &self.preferences.announcements;
+        if settings.channel == AnnouncementChannel::Off
+            || self.scheduler.current() != State::Task
+            || !self.timer.is_running()
+        {
+            return;
+        }
+
+        let remaining = self.timer.remaining_time();
+        let minutes =
+            match self.countdown.take_due(&settings.minutes, remaining) {
+                Some(minutes) => minutes,
+                None => return,
+            };
+
+        let announcement = Announcement::MinutesLeft(minutes);
+        match settings.channel {
+            AnnouncementChannel::Off => {}
+            AnnouncementChannel::Notification => {
+                #[cfg(feature = "notifications")]
+                self.notifier.send(&announcement.text());
+            }
+            #[cfg(feature = "speech")]
+            AnnouncementChannel::Speech => self.announce(announcement),
+        }
+    }
+
+    /// Speak an announcement, or play the voice clip recorded for it.
+    #[cfg(feature = "speech")]
+    fn announce(&mut self, announcement: Announcement) {
+        #[cfg(feature = "sound")]
+        if let Some(path) = announce::clips_dir()
+            .and_then(|dir| announce::clip(&dir, announcement))
+        {
+            match self.play_clip(&path) {
+                Ok(()) => return,
+                Err(err) => warn!("Could not play voice clip: {:?}", err),
+            }
+        }
+
+        let settings = &self.preferences.announcements;
+        if let Err(err) = self.speaker.speak(&announcement.text(), settings) {
+            warn!("Could not speak: {:?}", err);
+            self.toasts.error_once(format!("Could not speak: {}", err));
+        }
+    }
+
+    /// Time the current task has spent paused.
+    fn paused_time(&self) -> Duration {
+        self.paused_for + self.paused_at.map_or(Duration::ZERO, clock::elapsed)
+    }
+
+    /// Mark the current task as interrupted once it has been paused for
+    /// longer than the budget allows, voiding it if the user wants.
+    fn check_pause_budget(&mut self) {
+        if !self.preferences.pause_budget
+            || self.scheduler.current() != State::Task
+            || self.over_pause_budget
+        {
+            return;
+        }
+
+        let budget = Duration::from_secs_f32(
+            self.preferences.pause_budget_minutes * 60.,
+        );
+        let paused = self.paused_time();
+        if paused <= budget {
+            if self.paused_at.is_some() {
+                // make sure we are around when the budget runs out
+                self.schedule_wakeup(budget - paused);
+            }
+            return;
+        }
+
+        self.over_pause_budget = true;
+
+        if self.preferences.void_over_pause_budget {
+            self.record_interval(EntryStatus::Voided);
+
+            // start the task over
+            self.change_state(State::Task);
+            self.toasts
+                .error("Paused for too long, so the task interval was voided");
+        }
+    }
+
+    /// Offer to resume the task once the calendar event it was paused for
+    /// is over.
+    fn check_meeting(&mut self) {
+        let ends_at = match self.meeting_ends_at {
+            Some(ends_at) if !self.meeting_over => ends_at,
+            _ => return,
+        };
+
+        let now = Local::now();
+        if now < ends_at {
+            // make sure we are around when the event ends
+            if let Ok(wait) = (ends_at - now).to_std() {
+                self.schedule_wakeup(wait);
+            }
+            return;
+        }
+
+        self.meeting_over = true;
+
+        #[cfg(feature = "notifications")]
+        {
+            let message = format!(
+                "Your meeting is over. Resume your task? {}",
+                self.interruption_summary()
+            );
+            self.notifier.send(&message);
+        }
+    }
+
+    /// Pause the task while the microphone or camera is in use.
+    fn check_call(&mut self) {
+        if !self.preferences.pause_for_calls {
+            return;
+        }
+
+        let in_call = self.devices.in_use();
+        self.handle_call(in_call);
+
+        // make sure we are around when the call starts or ends
+        self.schedule_wakeup(self.devices.until_check());
+    }
+
+    fn handle_call(&mut self, in_call: bool) {
+        if !in_call {
+            self.ignoring_call = false;
+            if self.paused_for_call {
+                self.paused_for_call = false;
+                self.start_timer();
+            }
+            return;
+        }
+
+        if self.paused_for_call && self.timer.is_running() {
+            // resumed by hand, so leave it running until the call is over
+            self.paused_for_call = false;
+            self.ignoring_call = true;
+        }
+
+        if self.scheduler.current() == State::Task
+            && self.timer.is_running()
+            && !self.ignoring_call
+        {
+            self.pause_timer();
+            // clearly someone else
+            self.interruptions.external += 1;
+            self.classifying_interruption = false;
+            self.paused_for_call = true;
+
+            #[cfg(feature = "notifications")]
+            self.notifier.send(
+                "Paused for your call. The task resumes once it is over.",
+            );
+        }
+    }
+
+    /// Unlock any achievements earned since they were last checked.
+    fn check_achievements(&mut self) {
+        let today = Local::today().naive_local();
+        let checked = Some((today, self.history.entries().len()));
+        if !self.preferences.achievements.enabled
+            || self.achievements_checked == checked
+        {
+            return;
+        }
+        self.achievements_checked = checked;
+
+        let unlocked =
+            self.preferences.achievements.unlock(&self.history, today);
+        for achievement in unlocked {
+            info!("Unlocked achievement {:?}", achievement);
+
+            #[cfg(feature = "notifications")]
+            self.notifier.send(&format!(
+                "Achievement unlocked: {} \u{1F3C6}",
+                achievement
+            ));
+        }
+    }
+
+    /// How long the current pause has lasted, in words.
+    fn interruption_summary(&self) -> String {
+        let paused = self.paused_at.map_or(Duration::ZERO, clock::elapsed);
+        format!(
+            "Interrupted for {}.",
+            self.preferences
+                .locale
+                .format_minutes((paused.as_secs() / 60) as i64)
+        )
+    }
+
+    /// Start or stop the HTTP server to match the preferences.
+    #[cfg(unix)]
+    fn update_http_server(&mut self) {
+        let settings = &self.preferences.http;
+        if !settings.enabled {
+            self.http = None;
+            return;
+        }
+
+        let (address, port) = (settings.address.trim(), settings.port);
+        if let Some(http) = &self.http {
+            if http.is_listening_on(address, port) {
+                http.set_tokens(settings.api_tokens.clone());
+                return;
+            }
+        }
+
+        // free the old port first, in case only the address changed
+        self.http = None;
+        let signal = self.repaint_signal.clone();
+        let wake = move || {
+            if let Some(signal) = &signal {
+                signal.request_repaint();
+            }
+        };
+        match HttpServer::start(address, port, wake) {
+            Ok(mut http) => {
+                http.set_tokens(settings.api_tokens.clone());
+                self.metrics_current = false;
+
+                #[cfg(feature = "mdns")]
+                if let Err(err) = http.advertise() {
+                    warn!("Could not advertise HTTP server: {:?}", err);
+                    self.toasts.error_once(format!(
+                        "Could not advertise HTTP server: {}",
+                        err
+                    ));
+                }
+
+                self.http = Some(http);
+            }
+            Err(err) => {
+                warn!("Could not start HTTP server: {:?}", err);
+                self.toasts
+                    .error(format!("Could not start HTTP server: {}", err));
+            }
+        }
+    }
+
+    /// Queue the interval which is ending for export.
+    #[cfg(feature = "push")]
+    fn export_interval(&mut self) {
+        if !self.preferences.influx.enabled || !self.timer.has_started() {
+            return;
+        }
+
+        // task names are kept private along with an encrypted history
+        let task = if self.scheduler.current() == State::Task
+            && !self.preferences.history_backend.is_encrypted()
+        {
+            self.task()
+        } else {
+            None
+        };
+
+        self.influx.record(&influx::Point {
+            state: self.scheduler.current(),
+            task,
+            profile: self.active_profile.clone().unwrap_or_default(),
+            end: Local::now(),
+            elapsed: self.timer.elapsed(),
+            completed: self.timer.is_over() || self.timer.is_counting_up(),
+            interruptions: self.interruptions.total,
+        });
+    }
+
+    /// Send the exported intervals once the batch interval has passed.
+    #[cfg(feature = "push")]
+    fn export_batch(&mut self) {
+        if let Some(wait) = self.influx.flush_if_due(&self.preferences.influx) {
+            // make sure we are around to send the batch
+            self.schedule_wakeup(wait);
+        }
+    }
+
+    /// Sample the focused window while working, and fill in the task it
+    /// suggests if asked to.
+    fn sample_window_title(&mut self) {
+        let prefs = &self.preferences;
+        let detection = prefs.title_detection;
+        if (detection == TitleDetection::Off && !prefs.track_applications)
+            || self.scheduler.current() != State::Task
+            || !self.timer.is_running()
+        {
+            return;
+        }
+
+        let rules: &[TitleRule] = match detection {
+            TitleDetection::Off => &[],
+            _ => prefs.title_rules.as_slice(),
+        };
+        let wait = self.title_sampler.sample(rules, prefs.track_applications);
+        self.schedule_wakeup(wait);
+
+        if detection == TitleDetection::Assign && self.task.trim().is_empty() {
+            if let Some(task) = self.title_sampler.suggestion() {
+                self.task = task.to_owned();
+            }
+        }
+    }
+
+    /// Keep the busy light showing the current state.
+    fn update_busylight(&mut self) {
+        let settings = &self.preferences.busylight;
+        if !settings.enabled || settings.device.trim().is_empty() {
+            self.busylight.close();
+            return;
+        }
+
+        let paused = !self.timer.is_running();
+        if let Err(err) =
+            self.busylight
+                .update(settings, self.scheduler.current(), paused)
+        {
+            warn!("Could not signal busy light: {:?}", err);
+            self.toasts
+                .error_once(format!("Could not signal busy light: {}", err));
+        }
+    }
+
+    /// Keep the status file up to date, or remove it if it is not wanted.
+    fn update_status_file(&mut self) {
+        if !self.preferences.status_file {
+            self.status_file = None;
+            return;
+        }
+
+        let file = match &mut self.status_file {
+            Some(file) => file,
+            None => match status_file::path() {
+                Some(path) => self.status_file.insert(StatusFile::new(path)),
+                None => return,
+            },
+        };
+
+        let contents =
+            status_file::contents(self.scheduler.current(), &self.timer);
+        if let Err(err) = file.update(contents) {
+            warn!("Could not write status file: {:?}", err);
+            self.toasts
+                .error_once(format!("Could not write status file: {}", err));
+        }
+    }
+
+    /// Follow the duration of the task entered, until the task interval is
+    /// started.
+    fn check_task_duration(&mut self) {
+        if self.scheduler.current() != State::Task
+            || self.timer.has_started()
+            || self.timer.is_counting_up()
+        {
+            return;
+        }
+
+        let duration = self.interval_duration();
+        if self.timer.remaining_time() != duration {
+            self.timer.set_duration(duration);
+        }
+    }
+
+    /// Show the review of the day once the working day is over, after any
+    /// task under way.
+    fn check_end_of_day(&mut self) {
+        let now = Local::now();
+        let today = now.date().naive_local();
+        if !self.preferences.end_of_day_review
+            || self.reviewed_on == Some(today)
+            || self.saved_session.is_some()
+        {
+            return;
+        }
+
+        let end = self.preferences.work_end;
+        if TimeOfDay::from(now.time()) < end {
+            // make sure we are around once the day is over
+            let until =
+                today.and_hms(end.hour, end.minute, 0) - now.naive_local();
+            if let Ok(until) = until.to_std() {
+                self.schedule_wakeup(until);
+            }
+            return;
+        }
+        if self.scheduler.current() == State::Task && self.timer.is_running() {
+            return;
+        }
+
+        self.reviewed_on = Some(today);
+        self.review_visible = true;
+    }
+
+    /// Put tasks carried over from an earlier day back into the plan, once
+    /// the last session has been resumed or set aside.
+    fn check_carry_over(&mut self) {
+        if self.saved_session.is_some() {
+            return;
+        }
+
+        let today = Local::today().naive_local();
+        let tasks = self.preferences.carry_over.take_due(today);
+        if tasks.is_empty() {
+            return;
+        }
+
+        self.queue.tasks.extend(tasks);
+        if self.task.trim().is_empty() {
+            if let Some(task) = self.queue.current() {
+                self.task = task.name.clone();
+            }
+        }
+    }
+
+    /// Plan the day's admin blocks, once a day and whenever the plan is
+    /// replaced.
+    fn check_admin_blocks(&mut self) {
+        let today = Local::today().naive_local();
+        if self.saved_session.is_some()
+            || self.admin_scheduled_on == Some(today)
+        {
+            return;
+        }
+        self.admin_scheduled_on = Some(today);
+
+        let first_task = self.queue.current().map(|task| task.name.clone());
+        let admin = &self.preferences.admin_blocks;
+        let done = self
+            .history
+            .tasks_on(today)
+            .into_iter()
+            .filter(|(task, _)| admin.is_admin(task))
+            .map(|(_, count)| count)
+            .sum();
+        admin.schedule(&mut self.queue, done);
+
+        let current = self.queue.current().map(|task| task.name.clone());
+        if current != first_task && !self.timer.has_started() {
+            if let Some(name) = current {
+                self.task = name;
+            }
+        }
+    }
+
+    /// Set the unfinished tasks in the plan aside for tomorrow.
+    fn carry_over_unfinished(&mut self) {
+        let tasks: Vec<QueuedTask> = std::mem::take(&mut self.queue.tasks)
+            .into_iter()
+            .filter(|task| task.remaining() > 0)
+            .collect();
+        let tomorrow = Local::today().naive_local().succ();
+        self.preferences.carry_over.add(tomorrow, tasks);
+    }
+
+    /// Remind the user to start their task if they have not done so within
+    /// the grace period after a break, waiting twice as long each time.
+    fn check_start_reminder(&mut self) {
+        let ended_at = match self.break_ended_at {
+            Some(ended_at) if self.preferences.start_reminder => ended_at,
+            _ => return,
+        };
+
+        let grace = Duration::from_secs_f32(
+            self.preferences.start_reminder_minutes * 60.,
+        );
+        let next_reminder = grace * 2u32.pow(self.reminders_sent.min(10));
+        let since_ended = clock::elapsed(ended_at);
+
+        if since_ended < next_reminder {
+            // make sure we are around to send the reminder
+            self.schedule_wakeup(next_reminder - since_ended);
+            return;
+        }
+
+        self.reminders_sent += 1;
+
+        #[cfg(feature = "notifications")]
+        {
+            let message = format!(
+                "Still on a break? {} minutes since your break ended.",
+                since_ended.as_secs() / 60
+            );
+
+            self.notifier.send(&message);
+        }
+    }
+
+    /// Whether the low-power profile is active: no tenths of a second, no
+    /// animations, and no sound. Repaints are throttled on battery either
+    /// way.
+    fn low_power(&mut self) -> bool {
+        self.preferences.low_power_on_battery && self.power.on_battery()
+    }
+
+    // called when preferences have changed
+    fn update_preferences(&mut self) {
+        // update timer duration according to preferences
+        self.load_flow();
+        self.timer.set_duration(self.interval_duration());
+
+        self.select_theme();
+        self.check_capacity();
+        self.cached_focus_score.set(None);
+
+        #[cfg(unix)]
+        self.update_http_server();
+
+        #[cfg(feature = "push")]
+        lights::show_state(
+            &self.preferences.lights,
+            self.scheduler.current(),
+            false,
+        );
+
+        // move the history over to the newly chosen backend
+        let backend = self.preferences.history_backend;
+        let current = self.history_store.as_ref().map(|(current, _)| *current);
+        if let Some(current) = current.filter(|&current| current != backend) {
+            if backend.is_encrypted() && self.history_passphrase.is_empty() {
+                self.toasts
+                    .error("Enter a passphrase to encrypt the history");
+                self.preferences.history_backend = current;
+                return;
+            }
+
+            self.history_store = backend
+                .open(&self.history_passphrase)
+                .map(|store| (backend, store));
+            self.save_history();
+        }
+    }
+
+    /// Open the chosen history store and load the history from it, keeping
+    /// anything recorded in the meantime.
+    fn open_history(&mut self) {
+        let backend = self.preferences.history_backend;
+        let store = match backend.open(&self.history_passphrase) {
+            Some(store) => store,
+            None => return,
+        };
+
+        match store.load() {
+            Ok(mut history) => {
+                let recorded = !self.history.entries().is_empty()
+                    || !self.history.skipped().is_empty();
+                for entry in self.history.entries() {
+                    history.add(entry.clone());
+                }
+                for &at in self.history.skipped() {
+                    history.record_skip(at);
+                }
+
+                self.history = history;
+                self.history_changed();
+                self.history_store = Some((backend, store));
+                self.history_locked = false;
+                if recorded {
+                    self.save_history();
+                }
+            }
+            Err(err) => {
+                warn!("Could not load history: {:?}", err);
+                self.toasts
+                    .error(format!("Could not load history: {}", err));
+            }
+        }
+    }
+
+    /// Read the calendar and work out how many task intervals fit into the
+    /// rest of the working day, around its events.
+    fn check_capacity(&mut self) {
+        if let Err(err) = self.capacity.load(&self.preferences.calendar_file) {
+            warn!("Could not read calendar: {:?}", err);
+            self.toasts
+                .error_once(format!("Could not read calendar: {}", err));
+            return;
+        }
+
+        self.update_capacity();
+    }
+
+    /// Work out again how many task intervals fit once the minute changes,
+    /// as the rest of the working day shrinks.
+    fn check_capacity_minute(&mut self) {
+        if !self.capacity.is_known() {
+            return;
+        }
+
+        let now = Local::now();
+        if self.capacity.is_stale(now) {
+            self.update_capacity();
+        }
+
+        self.schedule_wakeup(Duration::from_secs(60 - now.second() as u64));
+    }
+
+    /// Work out how many task intervals fit into the rest of the working
+    /// day, around the calendar events already read.
+    fn update_capacity(&mut self) {
+        let profile = self.profile();
+        self.capacity.update(
+            self.preferences.work_start,
+            self.preferences.work_end,
+            &profile,
+            Local::now(),
+        );
+    }
+
+    fn select_theme(&mut self) {
+        if let Err(err) = self.theme.select(self.preferences.theme.clone()) {
+            warn!("Could not load theme: {:?}", err);
+        }
+    }
+
+    fn main_view(&mut self, ui: &mut egui::Ui) {
+        profile_scope!("main_view");
+
+        let admin_block = self.is_admin_block();
+        let heading = if admin_block {
+            "\u{1F4E5} Admin block"
+        } else {
+            self.scheduler.current().name()
+        };
+        let heading_color = ui.visuals().text_color();
+        self.heading_layout.show(
+            ui,
+            heading,
+            egui::TextStyle::Heading,
+            heading_color,
+        );
+
+        let timer_color = if !self.timer.is_counting_up()
+            && self.timer.remaining_time().as_secs() <= 5
+        {
+            self.theme.theme().warning_color()
+        } else if admin_block {
+            plan::ADMIN_COLOR
+        } else {
+            ui.visuals().text_color()
+        };
+
+        let tenths = self.show_tenths();
+        let label = self.timer_label.get(&self.timer, tenths);
+        self.timer_layout.show(
+            ui,
+            label,
+            egui::TextStyle::Monospace,
+            timer_color,
+        );
+
+        if !self.snoozed.is_zero() {
+            let minutes = (self.snoozed.as_secs_f32() / 60.).round() as i64;
+            ui.add(
+                egui::Label::new(format!(
+                    "Snoozed for {}",
+                    self.preferences.locale.format_minutes(minutes)
+                ))
+                .small(),
+            );
+        }
+
+        if !self.break_debt.is_zero() && !self.presenting {
+            let minutes = (self.break_debt.as_secs_f32() / 60.).ceil() as i64;
+            ui.add(
+                egui::Label::new(format!(
+                    "Break debt: {}",
+                    self.preferences.locale.format_minutes(minutes)
+                ))
+                .small(),
+            );
+        }
+
+        if !self.presenting {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.task)
+                    .hint_text("What are you working on?"),
+            );
+            history::context_selector(
+                ui,
+                &mut self.context,
+                &self.preferences.contexts,
+            );
+
+            let suggestion = self
+                .title_sampler
+                .suggestion()
+                .filter(|_| self.task.trim().is_empty())
+                .map(str::to_owned);
+            if let Some(suggestion) = suggestion {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Working on {}?", suggestion));
+                    if ui.small_button("Yes").clicked() {
+                        self.task = suggestion;
+                    }
+                });
+            }
+
+            self.journal_prompt(ui);
+            self.task_switch_prompt(ui);
+        }
+
+        if self.preferences.show_clock {
+            let now = Local::now();
+            ui.label(self.preferences.hour_format.format(now.time()));
+
+            // repaint when the minute changes, even if the timer is stopped
+            self.schedule_wakeup(Duration::from_secs(60 - now.second() as u64));
+        }
+
+        let today = self.history.day_stats(Local::today().naive_local());
+        if !self.presenting {
+            let goal = self.preferences.daily_goal;
+            let mut counter =
+                format!("{} of {} tasks today", today.completed, goal);
+            if today.completed >= goal {
+                counter.push_str(" \u{2714}");
+            }
+            if today.voided > 0 {
+                counter.push_str(&format!(", {} voided", today.voided));
+            }
+            let progress = today.completed as f32 / goal.max(1) as f32;
+            ui.add(
+                egui::ProgressBar::new(progress.min(1.))
+                    .desired_width(160.)
+                    .text(counter),
+            );
+
+            if let Some(finish) = self.queue_finish_label() {
+                ui.add(egui::Label::new(finish).small());
+            }
+        }
+
+        // warn about overcommitment before the day gets going
+        if let Some(capacity) = self.capacity.tasks.filter(|_| !self.presenting)
+        {
+            let remaining_goal =
+                self.preferences.daily_goal.saturating_sub(today.completed);
+            if !self.timer.has_started() && remaining_goal > capacity {
+                ui.add(
+                    egui::Label::new(format!(
+                        "Only {} more tasks fit in today's free time",
+                        capacity
+                    ))
+                    .small()
+                    .text_color(self.theme.theme().warning_color()),
+                );
+            }
+        }
+
+        let idle_minutes = self.idle_drift.as_secs() / 60;
+        if idle_minutes > 0 {
+            ui.add(
+                egui::Label::new(format!("Idle drift: {} min", idle_minutes))
+                    .small(),
+            );
+        }
+
+        ui.separator();
+
+        let accent_color = self.theme.theme().accent_color();
+
+        ui.horizontal(|ui| {
+            if self.overtime {
+                // waiting for the user to acknowledge the transition
+                let label = if self.scheduler.current().is_break() {
+                    "Back to work"
+                } else {
+                    "Take a break"
+                };
+                let next_button = ui.add(
+                    egui::Button::new(label)
+                        .fill(accent_color)
+                        .stroke((1., accent_color.linear_multiply(0.5))),
+                );
+
+                if next_button.clicked() {
+                    self.skip();
+                }
+                let snooze_label =
+                    format!("Snooze {} min", self.preferences.snooze_minutes);
+                if self.can_lengthen() && ui.button(snooze_label).clicked() {
+                    self.snooze();
+                }
+                return;
+            }
+
+            if !self.timer.has_started() {
+                // waiting for user to begin task, or break
+
+                let begin_label = if self.scheduler.current().is_break() {
+                    "Start break"
+                } else {
+                    "Begin task"
+                };
+                let begin_button = ui.add(
+                    egui::Button::new(begin_label)
+                        .fill(accent_color)
+                        .stroke((1., accent_color.linear_multiply(0.5))),
+                );
+
+                if begin_button.clicked() {
+                    self.start_timer();
+                }
+            } else if self.timer.is_paused() {
+                // the timer is paused
+                if ui.button("Resume").clicked() {
+                    self.start_timer();
+                }
+            } else if self.is_strict() {
+                // the only way out of a strict task is to give up on it
+                if ui.small_button("Abort\u{2026}").clicked() {
+                    self.confirm_abort = true;
+                }
+            } else {
+                // the timer is currently running
+                if ui.button("Pause").clicked() {
+                    self.pause_timer();
+                }
+            }
+
+            // show a skip button for breaks, or if the timer is running
+            let counting_up = self.timer.is_counting_up();
+            let skip_label = if counting_up { "Take a break" } else { "Skip" };
+            if (self.scheduler.current().is_break() || self.timer.has_started())
+                && !self.is_strict()
+                && ui.button(skip_label).clicked()
+            {
+                self.request_skip();
+            }
+
+            // start the interval under way over, except in strict mode where
+            // it would be a way out
+            if self.timer.has_started()
+                && !self.is_strict()
+                && ui.small_button("Restart").clicked()
+            {
+                self.restart();
+            }
+
+            // add time to the interval under way
+            if !counting_up
+                && self.can_lengthen()
+                && (self.scheduler.current().is_break()
+                    || self.timer.has_started())
+            {
+                for minutes in [1, 5] {
+                    if ui.small_button(format!("+{} min", minutes)).clicked() {
+                        self.extend(Duration::from_secs(minutes * 60));
+                    }
+                }
+            }
+        });
+
+        if self.meeting_over && self.timer.is_paused() {
+            ui.label(format!(
+                "Your meeting is over. {}",
+                self.interruption_summary()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Resume task").clicked() {
+                    self.start_timer();
+                }
+                if ui.button("Not yet").clicked() {
+                    self.meeting_ends_at = None;
+                    self.meeting_over = false;
+                }
+            });
+        }
+
+        if self.classifying_interruption || self.abandoned_at.is_some() {
+            ui.label(if self.abandoned_at.is_some() {
+                "What made you stop?"
+            } else {
+                "What interrupted you?"
+            });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.interruption_note)
+                    .hint_text("Note (optional)"),
+            );
+            let mut answer = None;
+            ui.horizontal(|ui| {
+                if ui.button("Distraction").clicked() {
+                    answer = Some(InterruptionKind::Internal);
+                }
+                if ui.button("Someone else").clicked() {
+                    answer = Some(InterruptionKind::External);
+                }
+                if self.abandoned_at.is_some()
+                    && ui.button("Don't log").clicked()
+                {
+                    self.abandoned_at = None;
+                    self.interruption_note.clear();
+                }
+            });
+            if let Some(kind) = answer {
+                self.log_interruption(kind);
+            }
+        }
+
+        if self.confirm_skip {
+            ui.label("Are you sure? You need this break.");
+            ui.horizontal(|ui| {
+                if ui.button("Keep resting").clicked() {
+                    self.confirm_skip = false;
+                }
+                if ui.button("Skip anyway").clicked() {
+                    self.skip();
+                }
+            });
+        }
+
+        if self.confirm_abort {
+            ui.label("Abort this task? It will be voided.");
+            ui.horizontal(|ui| {
+                if ui.button("Keep going").clicked() {
+                    self.confirm_abort = false;
+                }
+                if ui.button("Abort task").clicked() {
+                    self.abort_task();
+                }
+            });
+        }
+
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), |ui| {
+            ui.horizontal(|ui| {
+                // gear icon
+                if ui.button("\u{2699}").clicked() {
+                    self.preferences_visible = true;
+                }
+
+                if !self.presenting {
+                    if ui.button("History").clicked() {
+                        self.history_visible = true;
+                    }
+
+                    if ui.button("Plan").clicked() {
+                        self.plan_visible = true;
+                    }
+                }
+
+                if ui
+                    .selectable_label(self.presenting, "Presenting")
+                    .on_hover_text("Hide task names and history")
+                    .clicked()
+                {
+                    self.presenting = !self.presenting;
+                }
+
+                if !self.preferences.profiles.is_empty() {
+                    self.profile_switcher(ui);
+                }
+
+                // jumping away is ruled out like skipping is
+                if !self.is_strict() && self.enforcement() == Enforcement::Off {
+                    self.jump_menu(ui);
+                }
+            });
+        });
+    }
+
+    /// A dropdown to jump straight to any state.
+    fn jump_menu(&mut self, ui: &mut egui::Ui) {
+        let mut jump = None;
+        egui::ComboBox::from_id_source("jump_to")
+            .selected_text("Jump to\u{2026}")
+            .show_ui(ui, |ui| {
+                for state in [State::Task, State::ShortBreak, State::LongBreak]
+                {
+                    if ui.selectable_label(false, state.name()).clicked() {
+                        jump = Some(state);
+                    }
+                }
+            });
+
+        if let Some(state) = jump {
+            self.jump_to(state);
+        }
+    }
+
+    /// Shown instead of the main view during an enforced break, with no way
+    /// to pause or skip it.
+    fn break_overlay_view(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.heading("Time to stand up");
+            let label = self.timer_label.get(&self.timer, false);
+            let color = ui.visuals().text_color();
+            self.timer_layout
+                .show(ui, label, egui::TextStyle::Heading, color);
+            ui.label("Step away from the screen until your break is over.");
+        });
+    }
+
+    /// Recorded task intervals, which can be edited, deleted, and added to by
+    /// hand.
+    fn history_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading("History");
+        ui.label(format!("Focus score today: {}", self.focus_score()));
+
+        let locale = self.preferences.locale;
+        let hour_format = self.preferences.hour_format;
+        let today = Local::today().naive_local();
+        let week: u32 = (0..7)
+            .map(|i| {
+                let day = today - chrono::Duration::days(i);
+                self.history.day_stats(day).completed
+            })
+            .sum();
+        ui.label(format!(
+            "Focused today: {}",
+            locale.format_minutes(self.history.day_stats(today).minutes)
+        ));
+        ui.label(format!(
+            "This week: {} tasks a day",
+            locale.format_decimal(week as f64 / 7., 1)
+        ));
+
+        let timeline_date = self.timeline_date.unwrap_or(today);
+        ui.collapsing("Timeline", |ui| {
+            ui.horizontal(|ui| {
+                if ui.small_button("\u{25C0}").clicked() {
+                    self.timeline_date = Some(timeline_date.pred());
+                }
+                ui.label(locale.format_date(timeline_date));
+                if timeline_date < today
+                    && ui.small_button("\u{25B6}").clicked()
+                {
+                    self.timeline_date = Some(timeline_date.succ());
+                }
+            });
+
+            let max_break = chrono::Duration::minutes(
+                self.preferences.long_break_minutes.round() as i64
+                    + TIMELINE_BREAK_SLACK_MINUTES,
+            );
+            history::timeline(
+                ui,
+                &self.history.timeline(timeline_date, max_break),
+                hour_format,
+            );
+        });
+
+        history::interruption_chart(ui, &self.history);
+        ui.label(format!(
+            "Context switches today: {}",
+            self.history.context_switches(today)
+        ));
+        history::context_switch_chart(ui, &self.history);
+        history::logged_interruptions(ui, &self.history, hour_format);
+
+        let experiment = &self.preferences.experiment;
+        if experiment.start.is_some() {
+            ui.collapsing("Experiment", |ui| {
+                experiment::experiment_report(
+                    ui,
+                    experiment,
+                    &experiment.results(
+                        &self.history,
+                        self.preferences.daily_goal,
+                        today,
+                    ),
+                    today,
+                    locale,
+                );
+            });
+        }
+
+        if self.preferences.achievements.enabled {
+            ui.collapsing("Trophies", |ui| {
+                achievements::trophies(
+                    ui,
+                    &self.preferences.achievements,
+                    locale,
+                );
+            });
+        }
+
+        ui.separator();
+
+        let mut edit = None;
+        let mut delete = None;
+        let mut details = self.history_details;
+
+        egui::ScrollArea::vertical()
+            .max_height(120.)
+            .show(ui, |ui| {
+                egui::Grid::new("history").striped(true).show(ui, |ui| {
+                    for (i, entry) in
+                        self.history.entries().iter().enumerate().rev()
+                    {
+                        ui.label(format!(
+                            "{} {}",
+                            locale
+                                .format_date(entry.start.date().naive_local()),
+                            hour_format.format(entry.start.time())
+                        ));
+                        ui.label(locale.format_minutes(entry.minutes()));
+                        let mut notes = Vec::new();
+                        if entry.manual {
+                            notes.push("manual");
+                        }
+                        if entry.status != EntryStatus::Completed {
+                            notes.push(entry.status.as_str());
+                        }
+                        if entry.snoozed_minutes > 0 {
+                            notes.push("snoozed");
+                        }
+                        if notes.is_empty() {
+                            ui.label(&entry.task);
+                        } else {
+                            ui.label(format!(
+                                "{} ({})",
+                                entry.task,
+                                notes.join(", ")
+                            ));
+                        }
+                        if ui
+                            .selectable_label(details == Some(i), "Details")
+                            .clicked()
+                        {
+                            details =
+                                if details == Some(i) { None } else { Some(i) };
+                        }
+                        if ui.small_button("Edit").clicked() {
+                            edit = Some(i);
+                        }
+                        if ui.small_button("Delete").clicked() {
+                            delete = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+        if let Some(i) = edit {
+            let draft = EntryDraft::from(&self.history.entries()[i]);
+            self.history_draft = Some((draft, Some(i)));
+        }
+        self.history_details = details;
+        if let Some(i) = delete {
+            self.history.remove(i);
+            self.history_draft = None;
+            self.history_details = None;
+            self.save_history();
+        }
+
+        let entry = self
+            .history_details
+            .and_then(|i| self.history.entries().get(i));
+        if let Some(entry) = entry {
+            ui.separator();
+            history::entry_details(ui, entry, locale, hour_format);
+        }
+
+        ui.separator();
+
+        let mut save = false;
+        let mut cancel = false;
+
+        if let Some((draft, _)) = &mut self.history_draft {
+            egui::Grid::new("history_draft").show(ui, |ui| {
+                ui.label("Date");
+                ui.text_edit_singleline(&mut draft.date);
+                ui.end_row();
+
+                ui.label("From");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.start)
+                            .desired_width(50.),
+                    );
+                    ui.label("to");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.end)
+                            .desired_width(50.),
+                    );
+                });
+                ui.end_row();
+
+                ui.label("Task");
+                ui.text_edit_singleline(&mut draft.task);
+                ui.end_row();
+            });
+
+            ui.horizontal(|ui| {
+                save = ui.button("Save").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+        }
+
+        if save {
+            if let Some((draft, replaces)) = self.history_draft.take() {
+                match draft.parse() {
+                    Ok(mut entry) => {
+                        if let Some(i) = replaces {
+                            let old = self.history.remove(i);
+                            entry.interruptions = old.interruptions;
+                            entry.applications = old.applications;
+                            entry.note = old.note;
+                            entry.context = old.context;
+                        }
+                        self.history.add(entry);
+                        self.history_details = None;
+                        self.history_message = None;
+                        self.save_history();
+                    }
+                    Err(message) => {
+                        self.history_message = Some(message);
+                        self.history_draft = Some((draft, replaces));
+                    }
+                }
+            }
+        }
+        if cancel {
+            self.history_draft = None;
+            self.history_message = None;
+        }
+
+        if self.history_import.is_some() {
+            self.import_view(ui);
+        }
+
+        if let Some(message) = &self.history_message {
+            ui.label(message);
+        }
+
+        ui.horizontal(|ui| {
+            if self.history_draft.is_none() && ui.button("Add").clicked() {
+                self.history_draft = Some((EntryDraft::today(), None));
+            }
+
+            if self.history_import.is_none()
+                && ui.button("Import CSV").clicked()
+            {
+                self.history_import = Some((String::new(), None));
+            }
+
+            if ui.button("Export CSV").clicked() {
+                self.history_message = Some(match self.history.export_csv() {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(err) => format!("Could not export: {}", err),
+                });
+            }
+
+            if ui.button("Export today's summary").clicked() {
+                let today = Local::today().naive_local();
+                self.history_message =
+                    Some(match self.history.export_markdown(today) {
+                        Ok(path) => format!("Exported to {}", path.display()),
+                        Err(err) => format!("Could not export: {}", err),
+                    });
+            }
+
+            if ui.button("Export this month's report").clicked() {
+                let report = MonthReport::new(
+                    &self.history,
+                    Local::today().naive_local(),
+                );
+                self.history_message = Some(match report.export_html() {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(err) => format!("Could not export: {}", err),
+                });
+            }
+
+            if ui.button("Export this month's invoice").clicked() {
+                let exported = invoice::export_csv(
+                    &self.history,
+                    &self.preferences.invoice,
+                    Local::today().naive_local(),
+                );
+                self.history_message = Some(match exported {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(err) => format!("Could not export: {}", err),
+                });
+            }
+
+            if ui.button("Close").clicked() {
+                self.history_visible = false;
+                self.history_draft = None;
+                self.history_message = None;
+                self.history_import = None;
+            }
+        });
+    }
+
+    /// Offer to move on to the next task in the plan. Escape keeps to the
+    /// current task.
+    fn task_switch_prompt(&mut self, ui: &mut egui::Ui) {
+        let next = match &self.task_switch {
+            Some(next) => next,
+            None => return,
+        };
+
+        ui.label(format!(
+            "{} has reached its estimate. Move on to {}?",
+            self.task.trim(),
+            next
+        ));
+        let mut accept = false;
+        let mut dismiss = ui.input().key_pressed(egui::Key::Escape);
+        ui.horizontal(|ui| {
+            accept = ui.small_button("Move on").clicked();
+            dismiss |= ui.small_button("Keep going (Esc)").clicked();
+        });
+
+        if accept {
+            self.accept_task_switch();
+        } else if dismiss {
+            self.task_switch = None;
+        }
+    }
+
+    /// Ask what was accomplished in the task interval which just completed.
+    fn journal_prompt(&mut self, ui: &mut egui::Ui) {
+        let (start, note) = match &mut self.journal_draft {
+            Some(draft) => draft,
+            None => return,
+        };
+
+        let mut save = false;
+        let mut skip = false;
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(note)
+                    .hint_text("What did you accomplish?"),
+            );
+            save = ui.small_button("Save").clicked()
+                || (response.lost_focus()
+                    && ui.input().key_pressed(egui::Key::Enter));
+            skip = ui.small_button("Skip").clicked();
+        });
+
+        if save {
+            if !note.trim().is_empty() && self.history.set_note(*start, note) {
+                self.save_history();
+            }
+            self.journal_draft = None;
+        } else if skip {
+            self.journal_draft = None;
+        }
+    }
+
+    /// Load a CSV file and choose which of its columns to import.
+    fn import_view(&mut self, ui: &mut egui::Ui) {
+        let (path, import) = match &mut self.history_import {
+            Some(import) => import,
+            None => return,
+        };
+
+        let mut load = false;
+        let mut merge = false;
+        let mut cancel = false;
+
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(path);
+            load = ui.button("Load").clicked();
+        });
+
+        if let Some(import) = import {
+            let headers = &import.headers;
+            let mapping = &mut import.mapping;
+            egui::Grid::new("import_mapping").show(ui, |ui| {
+                for (label, column) in [
+                    ("Start", &mut mapping.start),
+                    ("End", &mut mapping.end),
+                    ("Minutes", &mut mapping.minutes),
+                    ("Task", &mut mapping.task),
+                ] {
+                    ui.label(label);
+                    import::column_selector(ui, label, headers, column);
+                    ui.end_row();
+                }
+            });
+            ui.label(format!("{} rows", import.rows.len()));
+        }
+
+        ui.horizontal(|ui| {
+            if import.is_some() {
+                merge = ui.button("Import").clicked();
+            }
+            cancel = ui.button("Cancel").clicked();
+        });
+
+        if load {
+            match CsvImport::load(Path::new(path.trim())) {
+                Ok(loaded) => *import = Some(loaded),
+                Err(err) => {
+                    self.history_message =
+                        Some(format!("Could not load file: {}", err));
+                }
+            }
+        }
+
+        if merge {
+            if let Some((_, Some(import))) = self.history_import.take() {
+                let summary = import.merge_into(&mut self.history);
+                self.history_message = Some(format!(
+                    "Imported {} sessions, skipped {} duplicates and {} \
+                     invalid rows",
+                    summary.added, summary.duplicates, summary.invalid
+                ));
+                self.save_history();
+            }
+        }
+        if cancel {
+            self.history_import = None;
+        }
+    }
+
+    /// Today's task queue, and the saved plans it can be filled from.
+    fn plan_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Plan");
+
+        ui.separator();
+
+        let first_task = self.queue.current().map(|task| task.name.clone());
+        plan::task_queue_editor(
+            ui,
+            &mut self.queue.tasks,
+            &self.preferences.admin_blocks,
+        );
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_task);
+            if ui.button("Add").clicked() && !self.new_task.trim().is_empty() {
+                let name = self.new_task.trim().to_owned();
+                self.queue.tasks.push(QueuedTask::new(name, 1));
+                self.new_task.clear();
+            }
+        });
+        if let Some(finish) = self.queue_finish_label() {
+            ui.label(finish);
+        }
+
+        // keep working on whatever is at the front of the queue
+        let current = self.queue.current().map(|task| task.name.clone());
+        if current != first_task {
+            if let Some(name) = current {
+                self.task = name;
+            }
+        }
+
+        ui.separator();
+
+        let mut applied = None;
+        let mut removed = None;
+        for (i, template) in self.preferences.templates.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(&template.name);
+                if ui.button("Apply").clicked() {
+                    applied = Some(template.clone());
+                }
+                if ui.button("\u{1F5D9}").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(template) = applied {
+            self.apply_template(&template);
+        }
+        if let Some(i) = removed {
+            self.preferences.templates.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_template);
+
+            let name = self.new_template.trim();
+            if ui.button("Save as template").clicked() && !name.is_empty() {
+                let profile = match &self.profile_selection {
+                    ProfileSelection::Named(name) => Some(name.clone()),
+                    _ => None,
+                };
+                let template = Template {
+                    name: name.to_owned(),
+                    profile,
+                    schedule: Some(self.preferences.schedule.clone()),
+                    tasks: self.queue.tasks.clone(),
+                };
+
+                let templates = &mut self.preferences.templates;
+                templates.retain(|t| t.name != template.name);
+                templates.push(template);
+                self.new_template.clear();
+            }
+        });
+
+        ui.separator();
+
+        if ui.button("Close").clicked() {
+            self.plan_visible = false;
+        }
+    }
+
+    /// Sum up the day, offering to carry unfinished tasks over to tomorrow.
+    fn review_view(&mut self, ui: &mut egui::Ui) {
+        let today = Local::today().naive_local();
+        let stats = self.history.day_stats(today);
+        let locale = self.preferences.locale;
+
+        ui.heading("End of day");
+        ui.label(locale.format_date(today));
+
+        ui.separator();
+
+        ui.label(format!(
+            "{} of {} tasks completed, {} of focus",
+            stats.completed,
+            self.preferences.daily_goal,
+            locale.format_minutes(stats.minutes)
+        ));
+        ui.label(format!(
+            "Paused {} times ({} distractions, {} by someone else)",
+            stats.interruptions,
+            stats.internal_interruptions,
+            stats.external_interruptions
+        ));
+
+        if !self.presenting {
+            let tasks = self.history.tasks_on(today);
+            if !tasks.is_empty() {
+                ui.label("Tasks advanced:");
+                for (task, intervals) in tasks {
+                    let task = if task.is_empty() { "Untitled" } else { task };
+                    ui.label(format!("  {} ({})", task, intervals));
+                }
+            }
+
+            let unfinished: Vec<&QueuedTask> = self
+                .queue
+                .tasks
+                .iter()
+                .filter(|task| task.remaining() > 0)
+                .collect();
+            if !unfinished.is_empty() {
+                ui.label("Unfinished:");
+                for task in unfinished {
+                    ui.label(format!(
+                        "  {} ({} left)",
+                        task.name,
+                        task.remaining()
+                    ));
+                }
+            }
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if !self.queue.is_empty()
+                && ui.button("Carry over to tomorrow").clicked()
+            {
+                self.carry_over_unfinished();
+                self.review_visible = false;
+            }
+            if ui.button("Close").clicked() {
+                self.review_visible = false;
+            }
+        });
+    }
+
+    /// Ask for the passphrase the history is encrypted with.
+    fn unlock_history_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Unlock history");
+        ui.label("Enter the passphrase the history is encrypted with.");
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.history_passphrase)
+                .password(true),
+        );
+        let entered =
+            response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Unlock").clicked() || entered {
+                self.open_history();
+            }
+            if ui.button("Continue without history").clicked() {
+                self.history_locked = false;
+            }
+        });
+    }
+
+    /// Ask the user whether to resume the session from the last run.
+    fn resume_view(&mut self, ui: &mut egui::Ui) {
+        let session = match &self.saved_session {
+            Some(session) => session,
+            None => return,
+        };
+
+        ui.heading("Welcome back");
+        ui.label(format!(
+            "You were in a {} with {} left.",
+            session.state.name().to_lowercase(),
+            session.timer
+        ));
+        if !session.task.trim().is_empty() {
+            ui.label(format!("Working on: {}", session.task.trim()));
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Resume").clicked() {
+                if let Some(session) = self.saved_session.take() {
+                    self.resume(session);
+                }
+            }
+            if ui.button("Start fresh").clicked() {
+                self.saved_session = None;
+            }
+        });
+    }
+
+    fn resume(&mut self, session: Session) {
+        self.scheduler = session.scheduler;
+        self.timer = session.timer;
+        self.task = session.task;
+        self.queue = session.queue;
+        self.break_debt = session.break_debt;
+    }
+
+    /// The current session, in a form which can be saved.
+    fn session(&self) -> Session {
+        // task names stay out of plain-text storage if the history is
+        // encrypted
+        let private = self.preferences.history_backend.is_encrypted();
+
+        Session {
+            scheduler: self.scheduler,
+            timer: self.timer,
+            task: if private {
+                String::new()
+            } else {
+                self.task.clone()
+            },
+            queue: if private {
+                TaskQueue::default()
+            } else {
+                self.queue.clone()
+            },
+            break_debt: self.break_debt,
+        }
+    }
+
+    /// The name of the profile selected right now, or `None` for the default
+    /// profile. A running experiment takes the place of the profile rules.
+    fn selected_profile(&self) -> Option<String> {
+        let now = Local::now();
+        let experiment = &self.preferences.experiment;
+        match experiment.profile_on(now.date().naive_local()) {
+            Some(profile)
+                if self.profile_selection == ProfileSelection::Scheduled =>
+            {
+                Some(profile.to_owned())
+            }
+            _ => self
+                .profile_selection
+                .resolve(&self.preferences.profile_rules, &now),
+        }
+    }
+
+    /// A dropdown to switch profiles, which applies at the next interval.
+    fn profile_switcher(&mut self, ui: &mut egui::Ui) {
+        let active = self.active_profile.as_deref().unwrap_or("Default");
+        let selected_text = match &self.profile_selection {
+            ProfileSelection::Scheduled => format!("{} (auto)", active),
+            ProfileSelection::Default => "Default".to_owned(),
+            ProfileSelection::Named(name) => name.clone(),
+        };
+
+        // a commitment lock keeps to the profile in use
+        let locked = self.preferences.lock.is_locked(Local::now());
+        let selection = &mut self.profile_selection;
+        let profiles = &self.preferences.profiles;
+        ui.scope(|ui| {
+            ui.set_enabled(!locked);
+            egui::ComboBox::from_id_source("profile_switcher")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        selection,
+                        ProfileSelection::Scheduled,
+                        "Automatic",
+                    );
+                    ui.selectable_value(
+                        selection,
+                        ProfileSelection::Default,
+                        "Default",
+                    );
+                    for profile in profiles {
+                        ui.selectable_value(
+                            selection,
+                            ProfileSelection::Named(profile.name.clone()),
+                            profile.name.clone(),
+                        );
+                    }
+                });
+        });
+
+        if self.selected_profile() != self.active_profile {
+            ui.add(egui::Label::new("from next interval").small());
+        }
+    }
+
+    fn preferences_view(&mut self, ui: &mut egui::Ui) {
+        profile_scope!("preferences_view");
+
+        ui.heading("Preferences");
+
+        ui.separator();
+
+        let on_battery = self.power.on_battery();
+        #[cfg(feature = "speech")]
+        let voices = self.speaker.voices();
+        let locked = self.preferences.lock.is_locked(Local::now());
+        let strict = self.is_strict();
+        let unlock_passphrase = &mut self.unlock_passphrase;
+        let history_passphrase = &mut self.history_passphrase;
+        let admin_scheduled_on = &mut self.admin_scheduled_on;
+        let prefs = &mut self.preferences;
+
+        ui.collapsing("Interval durations", |ui| {
+            ui.set_enabled(!locked);
+            slider!(ui, prefs.task_minutes, "Task period", 0.5..=120.0);
+            slider!(ui, prefs.short_break_minutes, "Short break", 0.5..=120.0);
+            slider!(ui, prefs.long_break_minutes, "Long break", 0.5..=120.0);
+            flowtime::editor(ui, &mut prefs.flowtime);
+            plan::task_durations_editor(ui, &mut prefs.task_durations);
+        });
+
+        ui.collapsing("Program flow", |ui| {
+            ui.set_enabled(!locked);
+            scheduler::schedule_editor(ui, &mut prefs.schedule);
+            if prefs.schedule == Schedule::Cycle {
+                egui::ComboBox::from_label("Long break after")
+                    .selected_text(prefs.long_break_trigger.to_string())
+                    .show_ui(ui, |ui| {
+                        for trigger in [
+                            LongBreakTrigger::ShortBreaks,
+                            LongBreakTrigger::FocusedMinutes,
+                        ] {
+                            ui.selectable_value(
+                                &mut prefs.long_break_trigger,
+                                trigger,
+                                trigger.to_string(),
+                            );
+                        }
+                    });
+                match prefs.long_break_trigger {
+                    LongBreakTrigger::ShortBreaks => {
+                        slider!(
+                            ui,
+                            prefs.num_short_breaks,
+                            "Short breaks",
+                            1..=16
+                        );
+                    }
+                    LongBreakTrigger::FocusedMinutes => {
+                        slider!(
+                            ui,
+                            prefs.long_break_focus_minutes,
+                            "Focused minutes",
+                            10.0..=300.0,
+                            " min"
+                        );
+                    }
+                }
+            }
+            slider!(ui, prefs.daily_goal, "Daily goal", 1..=24, " tasks");
+            ui.checkbox(
+                &mut prefs.celebrate_goal,
+                "Notify when the daily goal is reached",
+            );
+            ui.checkbox(&mut prefs.achievements.enabled, "Unlock achievements");
+
+            ui.checkbox(
+                &mut prefs.auto_transition,
+                "Move on when the timer runs out",
+            );
+            if !prefs.auto_transition {
+                slider!(
+                    ui,
+                    prefs.snooze_minutes,
+                    "Snooze for",
+                    1.0..=30.0,
+                    " min"
+                );
+            }
+            ui.checkbox(
+                &mut prefs.auto_start_breaks,
+                "Start breaks as soon as they are due",
+            );
+            ui.checkbox(
+                &mut prefs.auto_start_tasks,
+                "Start the next task when a break ends",
+            );
+            // strict mode can't be turned off to get out of a task under way
+            ui.scope(|ui| {
+                ui.set_enabled(!strict);
+                ui.checkbox(
+                    &mut prefs.strict_mode,
+                    "Strict mode: no pausing or skipping tasks",
+                );
+            });
+            ui.checkbox(
+                &mut prefs.jumps_count,
+                "Jumps count towards the break cycle",
+            );
+            ui.checkbox(
+                &mut prefs.break_debt,
+                "Add skipped break time to the next long break",
+            );
+            if prefs.break_debt {
+                slider!(
+                    ui,
+                    prefs.max_break_debt_minutes,
+                    "Longest extension",
+                    1.0..=60.0,
+                    " min"
+                );
+            }
+            ui.checkbox(
+                &mut prefs.journal_prompts,
+                "Ask what I accomplished after each task",
+            );
+            ui.checkbox(
+                &mut prefs.suggest_task_switch,
+                "Ask before moving on to the next planned task",
+            );
+
+            ui.checkbox(&mut prefs.pause_budget, "Limit pauses");
+            if prefs.pause_budget {
+                slider!(
+                    ui,
+                    prefs.pause_budget_minutes,
+                    "Pause budget",
+                    0.5..=15.0,
+                    " min"
+                );
+                ui.checkbox(
+                    &mut prefs.void_over_pause_budget,
+                    "Void tasks paused for longer",
+                );
+            }
+
+            ui.checkbox(&mut prefs.void_abandoned, "Void skipped tasks");
+            if prefs.void_abandoned {
+                slider!(
+                    ui,
+                    prefs.void_below_percent,
+                    "Unless done",
+                    0.0..=100.0,
+                    "%"
+                );
+            }
+        });
+
+        ui.collapsing("Planning", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Working hours");
+                time_of_day::editor(ui, &mut prefs.work_start);
+                ui.label("to");
+                time_of_day::editor(ui, &mut prefs.work_end);
+            });
+            ui.checkbox(
+                &mut prefs.end_of_day_review,
+                "Review the day once working hours are over",
+            );
+            ui.label("Contexts");
+            history::contexts_editor(ui, &mut prefs.contexts);
+            if plan::admin_blocks_editor(ui, &mut prefs.admin_blocks) {
+                // plan the blocks again with the new settings
+                *admin_scheduled_on = None;
+            }
+            ui.horizontal(|ui| {
+                ui.label("Calendar file");
+                ui.add(
+                    egui::TextEdit::singleline(&mut prefs.calendar_file)
+                        .hint_text("events.ics"),
+                );
+            });
+            ui.checkbox(
+                &mut prefs.resume_after_meetings,
+                "Offer to resume after meetings",
+            );
+            ui.checkbox(
+                &mut prefs.pause_for_calls,
+                "Pause while the microphone or camera is in use",
+            );
+
+            ui.separator();
+            egui::ComboBox::from_label("Task from window title")
+                .selected_text(prefs.title_detection.to_string())
+                .show_ui(ui, |ui| {
+                    for detection in TitleDetection::ALL {
+                        ui.selectable_value(
+                            &mut prefs.title_detection,
+                            detection,
+                            detection.to_string(),
+                        );
+                    }
+                });
+            if prefs.title_detection != TitleDetection::Off {
+                window_title::rules_editor(ui, &mut prefs.title_rules);
+            }
+            ui.checkbox(
+                &mut prefs.track_applications,
+                "Record focused applications",
+            );
+        });
+
+        ui.collapsing("Storage", |ui| {
+            egui::ComboBox::from_label("History backend")
+                .selected_text(prefs.history_backend.to_string())
+                .show_ui(ui, |ui| {
+                    for &backend in StoreBackend::ALL {
+                        ui.selectable_value(
+                            &mut prefs.history_backend,
+                            backend,
+                            backend.to_string(),
+                        );
+                    }
+                });
+            if prefs.history_backend.is_encrypted() {
+                ui.horizontal(|ui| {
+                    ui.label("Passphrase");
+                    ui.add(
+                        egui::TextEdit::singleline(history_passphrase)
+                            .password(true),
+                    );
+                });
+                ui.add(
+                    egui::Label::new(
+                        "Templates, task durations, title rules, and carried \
+                         over tasks are stored unencrypted",
+                    )
+                    .small(),
+                );
+            }
+            ui.label("The history is copied over when the backend changes.");
+        });
+
+        ui.collapsing("Invoicing", |ui| {
+            invoice::settings_editor(ui, &mut prefs.invoice);
+        });
+
+        ui.collapsing("Profiles", |ui| {
+            ui.set_enabled(!locked);
+            profile::profiles_editor(ui, &mut prefs.profiles);
+
+            ui.separator();
+            ui.label("Schedule");
+            profile::profile_rules_editor(
+                ui,
+                &mut prefs.profile_rules,
+                &prefs.profiles,
+            );
+
+            ui.separator();
+            ui.label("Experiment");
+            experiment::experiment_editor(
+                ui,
+                &mut prefs.experiment,
+                &prefs.profiles,
+            );
+        });
+
+        ui.collapsing("Breaks later in the day", |ui| {
+            ui.set_enabled(!locked);
+            let adaptive = &mut prefs.adaptive_breaks;
+            ui.checkbox(&mut adaptive.enabled, "Lengthen breaks");
+            ui.horizontal(|ui| {
+                ui.label("From");
+                time_of_day::editor(ui, &mut adaptive.start);
+                ui.label("to");
+                time_of_day::editor(ui, &mut adaptive.end);
+            });
+            slider!(
+                ui,
+                adaptive.max_extension,
+                "Up to",
+                0.0..=200.0,
+                "% longer"
+            );
+        });
+
+        ui.collapsing("Long breaks", |ui| {
+            ui.set_enabled(!locked);
+            egui::ComboBox::from_label("Enforcement")
+                .selected_text(prefs.long_break_enforcement.to_string())
+                .show_ui(ui, |ui| {
+                    for enforcement in Enforcement::ALL {
+                        ui.selectable_value(
+                            &mut prefs.long_break_enforcement,
+                            enforcement,
+                            enforcement.to_string(),
+                        );
+                    }
+                });
+        });
+
+        ui.collapsing("Commitment", |ui| {
+            lock::editor(ui, &mut prefs.lock, unlock_passphrase);
+        });
+
+        ui.collapsing("Alarms", |ui| {
+            alarm::editor(ui, &mut prefs.alarms);
+        });
+
+        ui.collapsing("Alerts", |ui| {
+            ui.checkbox(&mut prefs.transition_flash, "Flash on transition");
+            ui.checkbox(
+                &mut prefs.visual_alerts,
+                "Strobe and shake instead of playing sounds",
+            );
+            ui.checkbox(&mut prefs.dim_before_break, "Dim before breaks");
+            ui.checkbox(&mut prefs.start_reminder, "Remind me to start tasks");
+            slider!(
+                ui,
+                prefs.start_reminder_minutes,
+                "Reminder grace period",
+                1.0..=30.0,
+                " min"
+            );
+
+            ui.separator();
+            ui.label("End of task period");
+            alert::chain_editor(
+                ui,
+                "task_end_alerts",
+                &mut prefs.task_end_alerts,
+            );
+            ui.label("End of break");
+            alert::chain_editor(
+                ui,
+                "break_end_alerts",
+                &mut prefs.break_end_alerts,
+            );
+
+            ui.separator();
+            announce::announcements_editor(ui, &mut prefs.announcements);
+            #[cfg(feature = "speech")]
+            announce::speech_settings_editor(
+                ui,
+                &mut prefs.announcements,
+                voices,
+            );
+        });
+
+        #[cfg(feature = "sound")]
+        ui.collapsing("Focus sounds", |ui| {
+            focus_sound::settings_editor(ui, &mut prefs.focus_sound);
+        });
+
+        #[cfg(feature = "push")]
+        ui.collapsing("Push notifications", |ui| {
+            push::settings_editor(ui, &mut prefs.push);
+        });
+
+        #[cfg(feature = "push")]
+        ui.collapsing("Time series export", |ui| {
+            influx::settings_editor(ui, &mut prefs.influx);
+        });
+
+        #[cfg(feature = "push")]
+        ui.collapsing("Lights", |ui| {
+            lights::editor(ui, &mut prefs.lights);
+        });
+
+        #[cfg(unix)]
+        let http = &self.http;
+        ui.collapsing("Integrations", |ui| {
+            ui.checkbox(&mut prefs.status_file, "Write status file");
+            if let Some(path) = status_file::path() {
+                ui.add(egui::Label::new(path.display().to_string()).small());
+            }
+
+            busylight::settings_editor(ui, &mut prefs.busylight);
+            hooks::editor(ui, &mut prefs.hooks);
+
+            #[cfg(unix)]
+            http::settings_editor(ui, &mut prefs.http, http.as_ref());
+        });
+
+        ui.collapsing("Power", |ui| {
+            ui.checkbox(
+                &mut prefs.low_power_on_battery,
+                "Low-power profile on battery",
+            );
+            ui.checkbox(
+                &mut prefs.count_suspended_time,
+                "Count time spent suspended",
+            )
+            .on_hover_text("Otherwise the timer stands still while asleep");
+
+            let status = match (on_battery, prefs.low_power_on_battery) {
+                (false, _) => "On AC power",
+                (true, false) => "On battery power",
+                (true, true) => "On battery power (low-power profile active)",
+            };
+            ui.label(status);
+        });
+
+        ui.collapsing("Appearance", |ui| {
+            ui.checkbox(&mut prefs.show_clock, "Show current time");
+            ui.checkbox(
+                &mut prefs.show_tenths,
+                "Show tenths of a second, for short intervals",
+            );
+            egui::ComboBox::from_label("Hour format")
+                .selected_text(prefs.hour_format.to_string())
+                .show_ui(ui, |ui| {
+                    for format in [
+                        HourFormat::Locale,
+                        HourFormat::TwelveHour,
+                        HourFormat::TwentyFourHour,
+                    ] {
+                        ui.selectable_value(
+                            &mut prefs.hour_format,
+                            format,
+                            format.to_string(),
+                        );
+                    }
+                });
+
+            egui::ComboBox::from_label("Dates and durations")
+                .selected_text(prefs.locale.to_string())
+                .show_ui(ui, |ui| {
+                    for locale in Locale::ALL {
+                        ui.selectable_value(
+                            &mut prefs.locale,
+                            locale,
+                            locale.to_string(),
+                        );
+                    }
+                });
+
+            let selected = prefs.theme.as_deref().unwrap_or("Default");
+            egui::ComboBox::from_label("Theme")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut prefs.theme, None, "Default");
+                    for name in theme::available_themes() {
+                        let label = name.clone();
+                        ui.selectable_value(
+                            &mut prefs.theme,
+                            Some(name),
+                            label,
+                        );
+                    }
+                });
+        });
+
+        #[cfg(feature = "profiling")]
+        ui.collapsing("Profiling", |ui| {
+            ui.checkbox(&mut self.profiler_visible, "Show profiler");
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.scope(|ui| {
+                ui.set_enabled(!locked);
+                if ui.button("Reset to default").clicked() {
+                    self.preferences = Preferences::default();
+                }
+            });
+
+            if ui.button("Close").clicked() {
+                self.update_preferences();
+                self.preferences_visible = false;
+            }
+        });
+    }
+
+    /// Schedule the next repaint for when the displayed time will change.
+    fn schedule_repaint(&mut self, ctx: &egui::CtxRef) {
+        profile_scope!("schedule_repaint");
+
+        // throttled on battery whether or not the low-power profile is on
+        let step = if self.power.on_battery()
+            && self.timer.remaining_time().as_secs() > 60
+        {
+            BATTERY_REPAINT_STEP
+        } else {
+            1
+        };
+
+        let wait = if self.show_tenths() {
+            self.timer.until_tenth_change()
+        } else {
+            self.timer.until_display_change(step)
+        };
+
+        match &self.repaint_scheduler {
+            Some(scheduler) => scheduler.schedule(wait + REPAINT_MARGIN),
+            None => ctx.request_repaint(),
+        }
+    }
+
+    /// Whether the countdown shows tenths of a second, which takes ten times
+    /// as many repaints, so not in the low-power profile.
+    fn show_tenths(&mut self) -> bool {
+        self.preferences.show_tenths && !self.low_power()
+    }
+
+    /// Update the tray icon, which only changes when the remaining minutes
+    /// do.
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    fn update_tray(&mut self) {
+        // nothing has happened to the timer since the last update
+        match &self.tray_events {
+            Some(events) if events.try_iter().count() > 0 => {}
+            _ => return,
+        }
+
+        // Flowtime tasks show the minutes focused instead
+        let remaining = if self.timer.is_counting_up() {
+            self.timer.elapsed()
+        } else {
+            self.timer.remaining_time()
+        };
+        let hour_format = self.preferences.hour_format;
+        let ends_at = if self.timer.is_running() && !self.timer.is_counting_up()
+        {
+            chrono::Duration::from_std(remaining).ok().map(|remaining| {
+                hour_format.format((Local::now() + remaining).time())
+            })
+        } else {
+            None
+        };
+
+        let status = TrayStatus {
+            state: self.scheduler.current(),
+            minutes: (remaining.as_secs() + 59) / 60,
+            paused: self.timer.is_paused(),
+            ends_at,
+        };
+
+        if let Some(tray) = &mut self.tray {
+            tray.update(status);
+        }
+    }
+
+    /// Gradually dim the window as the end of a task approaches, as a gentle
+    /// signal that a break is coming up. Only the window is darkened, not the
+    /// screen, so this goes unnoticed while it is hidden behind others.
+    fn paint_dim(&self, ctx: &egui::CtxRef) {
+        profile_scope!("paint_dim");
+
+        if !self.preferences.dim_before_break
+            || self.scheduler.current() != State::Task
+            || !self.timer.is_running()
+            || self.timer.is_counting_up()
+        {
+            return;
+        }
+
+        let remaining = self.timer.remaining_time();
+        if remaining >= DIM_DURATION {
+            return;
+        }
+
+        let progress =
+            1. - remaining.as_secs_f32() / DIM_DURATION.as_secs_f32();
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("break_dim"),
+        ));
+        painter.rect_filled(
+            ctx.input().screen_rect(),
+            0.,
+            Color32::BLACK.linear_multiply(DIM_OPACITY * progress),
+        );
+    }
+
+    /// Paint the transition flash over the whole window, if one is active.
+    fn paint_flash(&mut self, ctx: &egui::CtxRef) {
+        profile_scope!("paint_flash");
+
+        let start = match self.flash_start {
+            Some(start) => start,
+            None => return,
+        };
+
+        let progress = start.elapsed().as_secs_f32() / FLASH_DURATION;
+        if progress >= 1. {
+            self.flash_start = None;
+            return;
+        }
+
+        // a single pulse which fades in quickly and out slowly
+        let intensity = (progress * std::f32::consts::PI).sin().powf(0.5);
+        let color = if self.scheduler.current().is_break() {
+            Color32::GREEN
+        } else {
+            Color32::RED
+        };
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("transition_flash"),
+        ));
+        painter.rect_filled(
+            ctx.input().screen_rect(),
+            0.,
+            color.linear_multiply(FLASH_OPACITY * intensity),
+        );
+
+        ctx.request_repaint();
+    }
+
+    /// The current frame of the strobe alert, if one is active.
+    fn strobe_frame(&mut self) -> Option<alert::StrobeFrame> {
+        let frame = self
+            .strobe_start
+            .and_then(|start| alert::strobe_frame(start.elapsed()));
+        if frame.is_none() {
+            self.strobe_start = None;
+        }
+        frame
+    }
+
+    /// Paint the current flash of the strobe alert over the whole window.
+    fn paint_strobe(&mut self, ctx: &egui::CtxRef) {
+        profile_scope!("paint_strobe");
+
+        let frame = match self.strobe_frame() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let color = match (frame.alternate, self.scheduler.current().is_break())
+        {
+            (true, _) => Color32::WHITE,
+            (false, true) => Color32::GREEN,
+            (false, false) => Color32::RED,
+        };
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("strobe_alert"),
+        ));
+        painter.rect_filled(
+            ctx.input().screen_rect(),
+            0.,
+            color.linear_multiply(STROBE_OPACITY * frame.intensity),
+        );
+
+        ctx.request_repaint();
+    }
+
+    /// Loop the focus sound while a task is running, and stop it otherwise.
+    #[cfg(feature = "sound")]
+    fn update_focus_sound(&mut self) {
+        let settings = &self.preferences.focus_sound;
+        let sound = settings
+            .sound
+            .filter(|_| {
+                self.scheduler.current() == State::Task
+                    && self.timer.is_running()
+            })
+            .map(|sound| (sound, settings.volume(sound)));
+
+        if let Err(err) = self.focus_sound.update(&mut self.audio, sound) {
+            warn!("Could not play focus sound: {:?}", err);
+            self.toasts
+                .error_once(format!("Could not play focus sound: {}", err));
+        }
+
+        if let Some(wait) = self.focus_sound.until_restore() {
+            self.schedule_wakeup(wait);
+        }
+    }
+
+    /// Play a recorded voice clip at the announcement volume.
+    #[cfg(all(feature = "sound", feature = "speech"))]
+    fn play_clip(&mut self, path: &Path) -> crate::Result<()> {
+        use std::io::BufReader;
+
+        let file = std::fs::File::open(path)?;
+        let source = rodio::Decoder::new(BufReader::new(file))?;
+        let duration = source.total_duration();
+        let volume = self.preferences.announcements.volume;
+        self.audio.play(source.convert_samples().amplify(volume))?;
+
+        self.focus_sound.duck(duration);
+        Ok(())
+    }
+
+    /// Play the alert sound, falling back to a beep if it can't be loaded.
+    #[cfg(feature = "sound")]
+    fn play_alert_sound(&mut self) -> crate::Result<()> {
+        let duration = match audio::alert_sound() {
+            Ok(source) => {
+                let duration = source.total_duration();
+                self.audio.play(source)?;
+                duration
+            }
+            Err(err) => {
+                warn!("Could not load alert sound: {:?}", err);
+                self.toasts.error_once(format!(
+                    "Could not load alert sound, using a beep instead: {}",
+                    err
+                ));
+                let beep = audio::beep();
+                let duration = beep.total_duration();
+                self.audio.play(beep)?;
+                duration
+            }
+        };
+
+        // keep the alert audible over any focus sound
+        self.focus_sound.duck(duration);
+        Ok(())
+    }
+}
+
+impl epi::App for TimeFloApp {
+    fn name(&self) -> &str {
+        "TimeFlo"
+    }
+
+    fn on_exit(&mut self) {
+        #[cfg(feature = "push")]
+        self.influx.finish(&self.preferences.influx);
+    }
+
+    fn setup(
+        &mut self,
+        _ctx: &egui::CtxRef,
+        frame: &mut epi::Frame<'_>,
+        storage: Option<&dyn epi::Storage>,
+    ) {
+        self.repaint_scheduler =
+            Some(RepaintScheduler::new(frame.repaint_signal()));
+        self.suspend = Some(SuspendMonitor::start(frame.repaint_signal()));
+        self.completion =
+            Some(CompletionWatcher::start(frame.repaint_signal()));
+
+        // Load previous app state (if any).
+        if let Some(storage) = storage {
+            self.preferences =
+                epi::get_value(storage, epi::APP_KEY).unwrap_or_default();
+            self.saved_session = epi::get_value(storage, SESSION_KEY)
+                .filter(Session::is_resumable);
+        }
+
+        // an encrypted history waits for the user to enter the passphrase
+        self.history_locked = self.preferences.history_backend.is_encrypted();
+        if !self.history_locked {
+            self.open_history();
+        }
+
+        self.load_flow();
+        self.change_state(State::Task);
+        self.select_theme();
+        self.check_capacity();
+
+        #[cfg(all(feature = "tray", target_os = "linux"))]
+        {
+            self.tray = Some(TrayIcon::start());
+            self.tray_events = Some(self.timer_events.subscribe());
+        }
+
+        #[cfg(unix)]
+        let signal = frame.repaint_signal();
+        #[cfg(unix)]
+        {
+            self.repaint_signal = Some(Arc::clone(&signal));
+        }
+        #[cfg(unix)]
+        match IpcServer::start(move || signal.request_repaint()) {
+            Ok(ipc) => self.ipc = Some(ipc),
+            Err(err) => warn!("Could not start IPC server: {:?}", err),
+        }
+        #[cfg(unix)]
+        self.update_http_server();
+
+        // initialize audio
+        #[cfg(feature = "sound")]
+        if let Err(err) = self.audio.refresh() {
+            warn!("Could not acquire audio output stream: {:?}", err);
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn epi::Storage) {
+        epi::set_value(storage, epi::APP_KEY, &self.preferences);
+
+        // don't lose the last session if the user hasn't decided about it yet
+        match &self.saved_session {
+            Some(session) => epi::set_value(storage, SESSION_KEY, session),
+            None => epi::set_value(storage, SESSION_KEY, &self.session()),
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::CtxRef, _frame: &mut epi::Frame<'_>) {
+        #[cfg(feature = "profiling")]
+        {
+            puffin::GlobalProfiler::lock().new_frame();
+            puffin::set_scopes_on(self.profiler_visible);
+        }
+        profile_scope!("update");
+
+        // apply the theme, reloading it if it has changed on disk
+        if let Err(err) = self.theme.update(ctx) {
+            warn!("Could not reload theme: {:?}", err);
+        }
+
+        if self.timer.is_running() {
+            self.schedule_repaint(ctx);
+        }
+
+        #[cfg(unix)]
+        self.handle_commands();
+
+        self.check_suspend();
+        self.check_timer();
+        self.check_pause_budget();
+        self.check_meeting();
+        self.check_call();
+        self.check_achievements();
+        self.check_countdown();
+        self.run_escalation(ctx);
+        self.check_alarms();
+        self.check_capacity_minute();
+        self.check_start_reminder();
+        self.check_end_of_day();
+        self.check_task_duration();
+        self.check_carry_over();
+        self.check_admin_blocks();
+        self.sample_window_title();
+        self.timer_events.observe(&self.timer);
+        self.arm_completion();
+
+        #[cfg(all(feature = "tray", target_os = "linux"))]
+        self.update_tray();
+
+        self.update_status_file();
+        self.update_busylight();
+
+        #[cfg(feature = "push")]
+        self.export_batch();
+
+        #[cfg(feature = "sound")]
+        self.update_focus_sound();
+
+        #[cfg(unix)]
+        if self.ipc.is_some() || self.http.is_some() {
+            let snapshot = Snapshot {
+                state: self.scheduler.current(),
+                timer: self.timer,
+                short_break_counter: self.scheduler.cycle_position(),
+                // the status is often on screen too, through editors and
+                // share links
+                task: self.task().filter(|_| !self.presenting),
+                focus_score: self.focus_score(),
+            };
+            if let Some(http) = &self.http {
+                http.update(snapshot.clone());
+                if !self.metrics_current {
+                    http.update_metrics(Metrics::from_history(&self.history));
+                    self.metrics_current = true;
+                }
+            }
+            if let Some(ipc) = &self.ipc {
+                ipc.update(snapshot);
+            }
+        }
+
+        // shake the contents during a strobe alert
+        let mut panel_frame = egui::Frame::central_panel(&ctx.style());
+        if let Some(strobe) = self.strobe_frame() {
+            panel_frame.margin.x =
+                (panel_frame.margin.x + strobe.shake).max(0.);
+        }
+
+        egui::CentralPanel::default()
+            .frame(panel_frame)
+            .show(ctx, |ui| {
+                if self.enforcement() == Enforcement::Overlay {
+                    self.break_overlay_view(ui);
+                } else if self.history_locked {
+                    self.unlock_history_view(ui);
+                } else if self.saved_session.is_some() {
+                    self.resume_view(ui);
+                } else if self.preferences_visible {
+                    self.preferences_view(ui);
+                } else if self.history_visible {
+                    self.history_view(ui);
+                } else if self.plan_visible {
+                    self.plan_view(ui);
+                } else if self.review_visible {
+                    self.review_view(ui);
+                } else {
+                    self.main_view(ui);
+                }
+            });
+
+        if let Some(wait) = self.toasts.show(ctx) {
+            self.schedule_wakeup(wait);
+        }
+
+        self.paint_dim(ctx);
+        self.paint_flash(ctx);
+        self.paint_strobe(ctx);
+
+        #[cfg(feature = "profiling")]
+        if self.profiler_visible {
+            self.profiler_visible = puffin_egui::profiler_window(ctx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app() {
+        // test an aspect state changing
+        // TODO more!
+
+        let mut app = TimeFloApp {
+            scheduler: Scheduler::at(State::Task, 3),
+            preferences: Preferences {
+                num_short_breaks: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        app.change_state(app.next_state());
+
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        assert!(app.timer.has_started());
+    }
+
+    #[test]
+    fn test_transition_flash() {
+        // not on startup
+        let mut app = TimeFloApp::default();
+        app.change_state(State::Task);
+        assert!(app.flash_start.is_none());
+
+        app.change_state(State::ShortBreak);
+        assert!(app.flash_start.is_some());
+    }
+
+    #[test]
+    fn test_idle_drift() {
+        let mut app = TimeFloApp {
+            scheduler: Scheduler::at(State::ShortBreak, 1),
+            ..Default::default()
+        };
+
+        // the time until the next task is started counts as drift
+        app.change_state(app.next_state());
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.break_ended_at.is_some());
+        assert!(!app.timer.has_started());
+    }
+
+    // The scenarios below run the whole state machine on the simulated clock.
+    // They live here rather than under `tests/` since they drive private
+    // methods such as `check_timer` and inspect private state, and since
+    // `clock::advance` and the mock clock being the default only exist when
+    // the library itself is built for testing.
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    /// Let the given amount of simulated time pass, then check the timer.
+    fn run_for(app: &mut TimeFloApp, duration: Duration) {
+        clock::advance(duration);
+        app.check_timer();
+    }
+
+    /// An app at the start of its first task.
+    fn new_app() -> TimeFloApp {
+        let mut app = TimeFloApp {
+            preferences: Preferences {
+                task_minutes: 25.,
+                short_break_minutes: 5.,
+                long_break_minutes: 15.,
+                num_short_breaks: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        app.change_state(State::Task);
+        app
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut app = new_app();
+
+        for short_breaks in 1..=2 {
+            app.timer.start();
+            run_for(&mut app, minutes(25));
+            assert_eq!(State::ShortBreak, app.scheduler.current());
+            assert_eq!(short_breaks, app.scheduler.cycle_position());
+            assert!(app.timer.is_running());
+
+            run_for(&mut app, minutes(5));
+            assert_eq!(State::Task, app.scheduler.current());
+            assert!(!app.timer.has_started());
+        }
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        assert_eq!(0, app.scheduler.cycle_position());
+
+        run_for(&mut app, minutes(14));
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        run_for(&mut app, minutes(1));
+        assert_eq!(State::Task, app.scheduler.current());
+
+        // tasks never start on their own
+        run_for(&mut app, minutes(60));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert_eq!(minutes(25), app.timer.remaining_time());
+    }
+
+    #[test]
+    fn test_overtime() {
+        let mut app = new_app();
+        app.preferences.auto_transition = false;
+
+        app.timer.start();
+        run_for(&mut app, minutes(27));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.overtime);
+        assert_eq!(minutes(2), app.timer.overtime());
+        assert!(app.history.entries().is_empty());
+
+        // snoozing counts the overtime so far
+        app.snooze();
+        assert!(!app.overtime);
+        assert_eq!(minutes(7), app.snoozed);
+        run_for(&mut app, minutes(4));
+        assert!(!app.overtime);
+        run_for(&mut app, minutes(1));
+        assert!(app.overtime);
+
+        // the snooze outlasts changes to the preferences
+        app.update_preferences();
+        assert!(app.timer.is_over());
+
+        app.skip();
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert!(!app.overtime);
+        assert_eq!(Duration::ZERO, app.snoozed);
+        assert_eq!(1, app.history.entries().len());
+        assert_eq!(7, app.history.entries()[0].snoozed_minutes);
+        assert_eq!(32, app.history.entries()[0].minutes());
+        assert!(app.history.skipped().is_empty());
+    }
+
+    #[test]
+    fn test_overlay_overtime() {
+        let mut app = new_app();
+        app.preferences.auto_transition = false;
+        app.preferences.long_break_enforcement = Enforcement::Overlay;
+        app.change_state(State::LongBreak);
+        assert!(app.timer.is_running());
+        assert_eq!(Enforcement::Overlay, app.enforcement());
+
+        // there is no way out of the overlay by hand, so it moves on anyway
+        run_for(&mut app, minutes(15));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(!app.overtime);
+        assert_eq!(Enforcement::Off, app.enforcement());
+    }
+
+    #[test]
+    fn test_task_durations() {
+        let mut app = new_app();
+        app.preferences.task_durations = vec![TaskDuration {
+            task: "Email triage".to_owned(),
+            minutes: 15.,
+        }];
+        app.task = "Email triage".to_owned();
+        app.check_task_duration();
+        assert_eq!(minutes(15), app.timer.remaining_time());
+
+        // the duration stays put once the interval has started
+        app.timer.start();
+        app.task = "Write report".to_owned();
+        app.check_task_duration();
+        run_for(&mut app, minutes(15));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+    }
+
+    #[test]
+    fn test_admin_blocks() {
+        let mut app = new_app();
+        app.preferences.admin_blocks.enabled = true;
+        app.queue.tasks = vec![QueuedTask::new("Write report", 2)];
+        app.check_admin_blocks();
+        assert_eq!("Inbox zero", app.task);
+        assert_eq!(3, app.queue.tasks.len());
+
+        // admin blocks are time-boxed, and do not count towards the score
+        app.check_task_duration();
+        assert_eq!(minutes(15), app.timer.remaining_time());
+        app.timer.start();
+        run_for(&mut app, minutes(15));
+        assert_eq!(1, app.history.entries().len());
+        assert_eq!(0, app.focus_score());
+
+        // planning again later in the day leaves out the block done
+        app.admin_scheduled_on = None;
+        app.check_admin_blocks();
+        let admin = app
+            .queue
+            .tasks
+            .iter()
+            .filter(|task| task.name == "Inbox zero")
+            .count();
+        assert_eq!(1, admin);
+    }
+
+    #[test]
+    fn test_flowtime() {
+        let mut app = new_app();
+        app.preferences.flowtime.enabled = true;
+        app.change_state(State::Task);
+        assert!(app.timer.is_counting_up());
+
+        // tasks run for as long as they need
+        app.timer.start();
+        run_for(&mut app, minutes(50));
+        assert_eq!(State::Task, app.scheduler.current());
+
+        app.skip();
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(minutes(10), app.timer.remaining_time());
+        assert_eq!(50, app.history.entries()[0].minutes());
+        assert!(app.history.skipped().is_empty());
+    }
+
+    #[test]
+    fn test_carry_over() {
+        let mut app = new_app();
+        app.queue.tasks = vec![
+            QueuedTask {
+                completed: 2,
+                ..QueuedTask::new("Done", 2)
+            },
+            QueuedTask::new("Write report", 2),
+        ];
+        app.carry_over_unfinished();
+        assert!(app.queue.is_empty());
+
+        // nothing comes back until tomorrow
+        app.check_carry_over();
+        assert!(app.queue.is_empty());
+        let tomorrow = Local::today().naive_local().succ();
+        let tasks = app.preferences.carry_over.take_due(tomorrow);
+        assert_eq!(vec![QueuedTask::new("Write report", 2)], tasks);
+    }
+
+    #[test]
+    fn test_journal_prompts() {
+        let mut app = new_app();
+        app.preferences.journal_prompts = true;
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        let start = app.history.entries()[0].start;
+        assert_eq!(Some((start, String::new())), app.journal_draft);
+        assert!(app.history.set_note(start, "Drafted the intro"));
+        assert_eq!("Drafted the intro", app.history.entries()[0].note);
+    }
+
+    #[test]
+    fn test_visual_alerts() {
+        let mut app = new_app();
+        app.preferences.visual_alerts = true;
+        app.fire_alert(AlertChannel::Sound, "");
+        assert!(app.strobe_start.is_some());
+        assert!(app.strobe_frame().is_some());
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut app = new_app();
+        app.timer.start();
+        run_for(&mut app, minutes(20));
+        app.extend(minutes(5));
+        assert_eq!(minutes(10), app.timer.remaining_time());
+
+        // the extension outlasts changes to the preferences, but not the
+        // interval
+        app.update_preferences();
+        assert_eq!(minutes(10), app.timer.remaining_time());
+        assert_eq!(25., app.preferences.task_minutes);
+        run_for(&mut app, minutes(10));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(minutes(5), app.timer.remaining_time());
+    }
+
+    #[test]
+    fn test_locked_breaks() {
+        let mut app = new_app();
+        app.preferences.lock.lock_for_today(Local::now());
+        assert!(app.can_lengthen());
+
+        // breaks cannot be lengthened while locked
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert!(!app.can_lengthen());
+
+        app.preferences.lock = PreferencesLock::default();
+        assert!(app.can_lengthen());
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut app = new_app();
+        app.timer.start();
+        run_for(&mut app, minutes(20));
+        app.extend(minutes(5));
+        app.pause_timer();
+
+        // the interval starts over, in the same place in the cycle
+        app.restart();
+        assert_eq!(State::Task, app.scheduler.current());
+        assert_eq!(0, app.scheduler.cycle_position());
+        assert!(app.timer.is_running());
+        assert_eq!(minutes(25), app.timer.remaining_time());
+        assert_eq!(0, app.interruptions.total);
+        assert!(app.history.entries().is_empty());
+
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(1, app.scheduler.cycle_position());
+    }
+
+    #[test]
+    fn test_jump() {
+        let mut app = new_app();
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        run_for(&mut app, minutes(5));
+        assert_eq!(1, app.scheduler.cycle_position());
+
+        // jumps may leave the cycle alone
+        app.preferences.jumps_count = false;
+        app.jump_to(State::ShortBreak);
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(1, app.scheduler.cycle_position());
+        assert_eq!(minutes(5), app.timer.remaining_time());
+
+        app.jump_to(State::Task);
+        app.preferences.jumps_count = true;
+        app.jump_to(State::ShortBreak);
+        assert_eq!(2, app.scheduler.cycle_position());
+
+        // leaving a task under way counts as a skip
+        app.jump_to(State::Task);
+        app.start_timer();
+        run_for(&mut app, minutes(5));
+        app.jump_to(State::LongBreak);
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        assert_eq!(0, app.scheduler.cycle_position());
+        assert_eq!(1, app.history.skipped().len());
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut app = new_app();
+
+        app.timer.start();
+        run_for(&mut app, minutes(10));
+        app.timer.pause();
+        run_for(&mut app, minutes(60));
+
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.timer.is_paused());
+        assert_eq!(minutes(15), app.timer.remaining_time());
+
+        app.timer.start();
+        run_for(&mut app, minutes(15));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+
+        // pauses are kept with the task for the timeline
+        app.change_state(State::Task);
+        app.start_timer();
+        run_for(&mut app, minutes(5));
+        app.pause_timer();
+        run_for(&mut app, minutes(3));
+        app.start_timer();
+        run_for(&mut app, minutes(20));
+        let entry = app.history.entries().last().unwrap();
+        assert_eq!(1, entry.pauses.len());
+        assert_eq!(
+            chrono::Duration::minutes(3),
+            entry.pauses[0].end - entry.pauses[0].start
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut app = new_app();
+        app.preferences.schedule = Schedule::Sequence(vec![
+            State::Task,
+            State::Task,
+            State::LongBreak,
+        ]);
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(!app.timer.has_started());
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        run_for(&mut app, minutes(15));
+        assert_eq!(State::Task, app.scheduler.current());
+    }
+
+    #[test]
+    fn test_flow() {
+        let mut app = new_app();
+        app.preferences.schedule = Schedule::Flow;
+        app.flow = Some(Flow {
+            steps: vec![
+                flow::FlowStep {
+                    state: State::Task,
+                    minutes: Some(50.),
+                    auto_start: Some(true),
+                    ..Default::default()
+                },
+                flow::FlowStep {
+                    state: State::ShortBreak,
+                    minutes: Some(10.),
+                    auto_start: Some(false),
+                    ..Default::default()
+                },
+            ],
+        });
+        app.change_state(State::Task);
+        assert_eq!(minutes(50), app.timer.remaining_time());
+
+        // each step has its own duration, and says whether it starts
+        run_for(&mut app, minutes(50));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(minutes(10), app.timer.remaining_time());
+        assert!(!app.timer.has_started());
+        app.start_timer();
+        run_for(&mut app, minutes(10));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.timer.is_running());
+    }
+
+    #[test]
+    fn test_log_interruptions() {
+        let mut app = new_app();
+        app.timer.start();
+        run_for(&mut app, minutes(5));
+
+        app.pause_timer();
+        app.interruption_note = " Slack ".to_owned();
+        app.log_interruption(InterruptionKind::External);
+        assert_eq!(1, app.interruptions.external);
+        assert!(!app.classifying_interruption);
+
+        // abandoning the task asks too, without counting as a pause
+        app.start_timer();
+        app.skip();
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert!(app.abandoned_at.is_some());
+        app.log_interruption(InterruptionKind::Internal);
+        assert_eq!(0, app.interruptions.internal);
+
+        let logged = app.history.interruptions();
+        assert_eq!(2, logged.len());
+        assert_eq!("Slack", logged[0].note);
+        assert!(!logged[0].abandoned);
+        assert_eq!(InterruptionKind::Internal, logged[1].kind);
+        assert!(logged[1].abandoned);
+    }
+
+    #[test]
+    fn test_daily_goal() {
+        let mut app = new_app();
+        app.preferences.daily_goal = 2;
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert!(!app.goal_reached(0));
+        run_for(&mut app, minutes(5));
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert!(app.goal_reached(1));
+        // only the task which reaches the goal counts
+        assert!(!app.goal_reached(2));
+    }
+
+    #[test]
+    fn test_pause_for_calls() {
+        let mut app = new_app();
+        app.timer.start();
+        run_for(&mut app, minutes(5));
+
+        app.handle_call(true);
+        assert!(app.timer.is_paused());
+        assert_eq!(1, app.interruptions.external);
+        run_for(&mut app, minutes(30));
+        app.handle_call(false);
+        assert!(app.timer.is_running());
+        assert_eq!(minutes(20), app.timer.remaining_time());
+
+        // resuming by hand keeps the task going through the call
+        app.handle_call(true);
+        app.start_timer();
+        app.handle_call(true);
+        assert!(app.timer.is_running());
+        app.handle_call(false);
+        app.handle_call(true);
+        assert!(app.timer.is_paused());
+    }
+
+    #[test]
+    fn test_auto_start_tasks() {
+        let mut app = new_app();
+        app.preferences.auto_start_tasks = true;
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        run_for(&mut app, minutes(5));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.timer.is_running());
+        assert_eq!(Duration::ZERO, app.idle_drift);
+
+        // the cycle runs on its own
+        run_for(&mut app, minutes(25));
+        run_for(&mut app, minutes(5));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.timer.is_running());
+    }
+
+    #[test]
+    fn test_break_debt() {
+        let mut app = new_app();
+        app.preferences.break_debt = true;
+        app.preferences.max_break_debt_minutes = 6.;
+
+        for _ in 0..2 {
+            app.timer.start();
+            run_for(&mut app, minutes(25));
+            assert_eq!(State::ShortBreak, app.scheduler.current());
+            run_for(&mut app, minutes(1));
+            app.skip();
+        }
+        assert_eq!(minutes(8), app.break_debt);
+
+        // the next long break is extended, up to the cap
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        assert_eq!(minutes(21), app.timer.remaining_time());
+        assert_eq!(minutes(2), app.break_debt);
+    }
+
+    #[test]
+    fn test_manual_breaks() {
+        let mut app = new_app();
+        app.preferences.auto_start_breaks = false;
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert!(!app.timer.has_started());
+
+        // the break waits to be started
+        run_for(&mut app, minutes(10));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        app.start_timer();
+        run_for(&mut app, minutes(5));
+        assert_eq!(State::Task, app.scheduler.current());
+
+        // enforced breaks start regardless
+        app.preferences.long_break_enforcement = Enforcement::Nag;
+        app.change_state(State::LongBreak);
+        assert!(app.timer.is_running());
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let mut app = new_app();
+        app.preferences.strict_mode = true;
+
+        // nothing is ruled out before the task begins
+        assert!(!app.is_strict());
+        app.start_timer();
+        assert!(app.is_strict());
+
+        run_for(&mut app, minutes(5));
+        app.request_skip();
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(app.timer.is_running());
+
+        // aborting voids the task and starts over
+        app.abort_task();
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(!app.timer.has_started());
+        assert_eq!(EntryStatus::Voided, app.history.entries()[0].status);
+
+        // breaks can still be skipped
+        app.start_timer();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert!(!app.is_strict());
+        app.request_skip();
+        assert_eq!(State::Task, app.scheduler.current());
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut app = new_app();
+
+        app.timer.start();
+        run_for(&mut app, minutes(5));
+        app.change_state(app.next_state());
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(1, app.scheduler.cycle_position());
+
+        // skipping a break goes straight back to work
+        run_for(&mut app, minutes(1));
+        app.change_state(app.next_state());
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(!app.timer.has_started());
+
+        // the skipped task still counts towards the long break
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        run_for(&mut app, minutes(5));
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::LongBreak, app.scheduler.current());
+    }
+
+    #[test]
+    fn test_pause_budget() {
+        let mut app = new_app();
+        app.preferences.pause_budget = true;
+        app.preferences.pause_budget_minutes = 2.;
+
+        let pause = |app: &mut TimeFloApp, duration| {
+            app.timer.pause();
+            app.paused_at = Some(clock::now());
+            clock::advance(duration);
+            app.check_pause_budget();
+        };
+
+        app.timer.start();
+        run_for(&mut app, minutes(5));
+        pause(&mut app, minutes(1));
+        assert!(!app.over_pause_budget);
+
+        app.timer.start();
+        app.paused_for += clock::elapsed(app.paused_at.take().unwrap());
+        run_for(&mut app, minutes(5));
+        pause(&mut app, minutes(2));
+        assert!(app.over_pause_budget);
+
+        app.timer.start();
+        run_for(&mut app, minutes(15));
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(EntryStatus::Interrupted, app.history.entries()[0].status);
+
+        // voiding starts the task over
+        app.preferences.void_over_pause_budget = true;
+        run_for(&mut app, minutes(5));
+        app.timer.start();
+        run_for(&mut app, minutes(5));
+        pause(&mut app, minutes(3));
+        assert_eq!(State::Task, app.scheduler.current());
+        assert!(!app.timer.has_started());
+        assert_eq!(EntryStatus::Voided, app.history.entries()[1].status);
+
+        let today = app.history.day_stats(Local::today().naive_local());
+        assert_eq!(1, today.completed);
+        assert_eq!(1, today.voided);
+    }
+
+    #[test]
+    fn test_resume_after_meeting() {
+        let mut app = new_app();
+        app.preferences.locale = Locale::Iso;
+        let now = Local::now();
+        app.capacity.calendar = vec![capacity::Busy {
+            start: now + chrono::Duration::minutes(2),
+            end: now + chrono::Duration::minutes(30),
+        }];
+
+        app.start_timer();
+        run_for(&mut app, minutes(5));
+        app.pause_timer();
+        assert_eq!(Some(app.capacity.calendar[0].end), app.meeting_ends_at);
+
+        app.check_meeting();
+        assert!(!app.meeting_over);
+
+        // once the event has ended
+        app.meeting_ends_at = Some(now);
+        clock::advance(minutes(28));
+        app.check_meeting();
+        assert!(app.meeting_over);
+        assert_eq!("Interrupted for 28 minutes.", app.interruption_summary());
+
+        app.start_timer();
+        assert!(!app.meeting_over);
+        assert_eq!(None, app.meeting_ends_at);
+
+        // pausing outside of any event
+        app.capacity.calendar.clear();
+        app.pause_timer();
+        assert_eq!(None, app.meeting_ends_at);
+    }
+
+    #[test]
+    fn test_void_abandoned() {
+        let mut app = new_app();
+        app.preferences.void_abandoned = true;
+        app.task = "Write report".to_owned();
+
+        app.timer.start();
+        run_for(&mut app, minutes(10));
+        app.skip();
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        assert_eq!(EntryStatus::Voided, app.history.entries()[0].status);
+
+        run_for(&mut app, minutes(5));
+        app.timer.start();
+        run_for(&mut app, minutes(15));
+        app.skip();
+        assert_eq!(EntryStatus::Completed, app.history.entries()[1].status);
+
+        let today = app.history.day_stats(Local::today().naive_local());
+        assert_eq!(1, today.completed);
+        assert_eq!(1, today.voided);
+        assert_eq!(0, today.skipped);
+    }
+
+    #[test]
+    fn test_focused_minutes_trigger() {
+        let mut app = new_app();
+        app.preferences.long_break_trigger = LongBreakTrigger::FocusedMinutes;
+        app.preferences.long_break_focus_minutes = 75.;
+
+        // a task cut short only counts the time spent on it
+        app.timer.start();
+        run_for(&mut app, minutes(10));
+        app.change_state(app.next_state());
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+        run_for(&mut app, minutes(5));
+
+        for _ in 0..2 {
+            app.timer.start();
+            run_for(&mut app, minutes(25));
+            assert_eq!(State::ShortBreak, app.scheduler.current());
+            run_for(&mut app, minutes(5));
+        }
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(State::LongBreak, app.scheduler.current());
+        assert_eq!(Duration::ZERO, app.scheduler.focused_since_long_break());
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut app = new_app();
+        app.task = "Write report".to_owned();
+        app.timer.start();
+        run_for(&mut app, minutes(10));
+
+        let session = serde_json::to_string(&app.session()).unwrap();
+        let session: Session = serde_json::from_str(&session).unwrap();
+        assert!(session.is_resumable());
+
+        let mut app = new_app();
+        app.resume(session);
+        assert_eq!(State::Task, app.scheduler.current());
+        assert_eq!("Write report", app.task);
+
+        // the timer carries on running from where it was
+        assert!(app.timer.is_running());
+        let drift = minutes(15).as_secs_f32()
+            - app.timer.remaining_time().as_secs_f32();
+        assert!(drift.abs() < 1.);
+
+        // nothing worth resuming in a fresh app
+        assert!(!new_app().session().is_resumable());
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut app = new_app();
+        app.preferences.suggest_task_switch = false;
+        app.apply_template(&Template {
+            name: "Deep Work Friday".to_owned(),
+            profile: None,
+            schedule: None,
+            tasks: vec![
+                QueuedTask::new("Write report", 2),
+                QueuedTask::new("Review", 1),
+            ],
+        });
+        assert_eq!("Write report", app.task);
+
+        for task in ["Write report", "Write report", "Review"] {
+            assert_eq!(task, app.task);
+            app.timer.start();
+            run_for(&mut app, minutes(25));
+            run_for(&mut app, minutes(5));
+        }
+
+        // the last task stays put once the queue is empty
+        assert!(app.queue.is_empty());
+        assert_eq!("Review", app.task);
+        assert_eq!(3, app.history.entries().len());
+
+        // a commitment lock keeps to the profile and schedule in use
+        let sprint = Template {
+            name: "Sprint".to_owned(),
+            profile: Some("Sprint".to_owned()),
+            schedule: Some(Schedule::Sequence(vec![
+                State::Task,
+                State::LongBreak,
+            ])),
+            tasks: vec![QueuedTask::new("Plan", 1)],
+        };
+        app.preferences.lock.lock_for_today(Local::now());
+        app.apply_template(&sprint);
+        assert_eq!("Plan", app.task);
+        assert_eq!(ProfileSelection::Scheduled, app.profile_selection);
+        assert_eq!(Schedule::Cycle, app.preferences.schedule);
+
+        app.preferences.lock = PreferencesLock::default();
+        app.apply_template(&sprint);
+        assert_eq!(
+            ProfileSelection::Named("Sprint".to_owned()),
+            app.profile_selection
+        );
+        assert_eq!(sprint.schedule, Some(app.preferences.schedule.clone()));
+    }
+
+    #[test]
+    fn test_task_switch() {
+        let mut app = new_app();
+        app.apply_template(&Template {
+            name: "Deep Work Friday".to_owned(),
+            profile: None,
+            schedule: None,
+            tasks: vec![
+                QueuedTask::new("Write report", 1),
+                QueuedTask::new("Review", 1),
+            ],
+        });
+
+        app.timer.start();
+        run_for(&mut app, minutes(25));
+        assert_eq!(Some("Review".to_owned()), app.task_switch);
+        assert_eq!("Write report", app.task);
+
+        // keeping going asks again after the next interval
+        run_for(&mut app, minutes(5));
+        app.start_timer();
+        assert_eq!(None, app.task_switch);
+        run_for(&mut app, minutes(25));
+        assert_eq!(Some("Review".to_owned()), app.task_switch);
+
+        app.accept_task_switch();
+        assert_eq!("Review", app.task);
+        assert_eq!(1, app.queue.tasks.len());
+    }
+
+    #[test]
+    fn test_preferences_mid_interval() {
+        let mut app = new_app();
+
+        app.timer.start();
+        run_for(&mut app, minutes(10));
+
+        // lengthening the task extends the running timer
+        app.preferences.task_minutes = 30.;
+        app.update_preferences();
+        assert_eq!(minutes(20), app.timer.remaining_time());
+
+        // shortening it past the elapsed time ends the task
+        app.preferences.task_minutes = 5.;
+        app.update_preferences();
+        run_for(&mut app, Duration::ZERO);
+        assert_eq!(State::ShortBreak, app.scheduler.current());
+
+        // the new duration applies to the break
+        app.preferences.short_break_minutes = 1.;
+        app.update_preferences();
+        run_for(&mut app, minutes(1));
+        assert_eq!(State::Task, app.scheduler.current());
     }
 }
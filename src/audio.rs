@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use log::info;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{OutputStream, OutputStreamHandle, Source};
+
+/// An open audio output stream.
+struct AudioOutput {
+    /// The stream must be kept alive for the handle to remain usable.
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    /// Name of the device the stream was opened on.
+    device_name: Option<String>,
+}
+
+fn default_device_name() -> Option<String> {
+    rodio::cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.name().ok())
+}
+
+/// Keeps an audio output stream open on the default output device,
+/// re-acquiring it if the device changes or disappears.
+#[derive(Default)]
+pub struct Audio {
+    output: Option<AudioOutput>,
+}
+
+impl Audio {
+    /// Make sure the stream is open on the current default output device.
+    pub fn refresh(&mut self) -> crate::Result<&OutputStreamHandle> {
+        let device_name = default_device_name();
+
+        let output = match self.output.take() {
+            Some(output) if output.device_name == device_name => output,
+            _ => {
+                info!("Opening audio output stream on {:?}", device_name);
+
+                let (stream, handle) = OutputStream::try_default()?;
+                AudioOutput {
+                    _stream: stream,
+                    handle,
+                    device_name,
+                }
+            }
+        };
+
+        Ok(&self.output.insert(output).handle)
+    }
+
+    /// Play a sound on the current default output device.
+    pub fn play<S>(&mut self, source: S) -> crate::Result<()>
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let result = self.refresh()?.play_raw(source);
+
+        // the stream may have died without the device changing, so try again
+        // with a fresh one next time
+        if result.is_err() {
+            self.output = None;
+        }
+
+        Ok(result?)
+    }
+}
+
+/// The alert sound played when an interval ends.
+pub fn alert_sound() -> crate::Result<impl Source<Item = f32> + Send> {
+    let file = std::fs::File::open("resources/alert.ogg")?;
+    let source = rodio::Decoder::new(std::io::BufReader::new(file))?;
+    Ok(source.convert_samples())
+}
+
+/// A short sine wave beep, for when no sound file can be played.
+pub fn beep() -> impl Source<Item = f32> + Send {
+    rodio::source::SineWave::new(880)
+        .take_duration(Duration::from_millis(300))
+        .amplify(0.2)
+}
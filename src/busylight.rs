@@ -0,0 +1,181 @@
+//! Signalling the state to a physical busy light, by writing a byte to a
+//! serial port whenever the state changes.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+/// Which byte to send for each state.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BusylightSettings {
+    pub enabled: bool,
+    /// The serial device, e.g. `/dev/ttyACM0` or `COM3`. Its speed is left
+    /// as the system has it configured, e.g. with `stty`.
+    pub device: String,
+    pub task: u8,
+    pub short_break: u8,
+    pub long_break: u8,
+    /// Sent while the timer is paused, in any state.
+    pub paused: u8,
+    /// Sent when idle, and when TimeFlo exits.
+    pub idle: u8,
+}
+
+impl Default for BusylightSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: String::new(),
+            task: b'R',
+            short_break: b'G',
+            long_break: b'G',
+            paused: b'Y',
+            idle: b'O',
+        }
+    }
+}
+
+impl BusylightSettings {
+    /// The byte for the given state.
+    pub fn signal(&self, state: State, paused: bool) -> u8 {
+        match state {
+            State::Idle => self.idle,
+            _ if paused => self.paused,
+            State::Task => self.task,
+            State::ShortBreak => self.short_break,
+            State::LongBreak => self.long_break,
+        }
+    }
+}
+
+/// Keeps a busy light showing the current state.
+#[derive(Default)]
+pub struct Busylight {
+    /// The open device, and the path it was opened from.
+    port: Option<(File, String)>,
+    /// The byte last sent, and the idle byte to send when closing.
+    sent: Option<(u8, u8)>,
+}
+
+impl Busylight {
+    /// Send the byte for the given state, if it has changed.
+    pub fn update(
+        &mut self,
+        settings: &BusylightSettings,
+        state: State,
+        paused: bool,
+    ) -> io::Result<()> {
+        let signal = settings.signal(state, paused);
+
+        let reopen = match &self.port {
+            Some((_, device)) => *device != settings.device,
+            None => true,
+        };
+        if reopen {
+            self.close();
+            let file =
+                OpenOptions::new().append(true).open(&settings.device)?;
+            self.port = Some((file, settings.device.clone()));
+        } else if self.sent.map(|(sent, _)| sent) == Some(signal) {
+            return Ok(());
+        }
+
+        if let Some((file, _)) = &mut self.port {
+            if let Err(err) = file.write_all(&[signal]) {
+                // try again from scratch next time, e.g. after replugging
+                self.port = None;
+                return Err(err);
+            }
+        }
+        self.sent = Some((signal, settings.idle));
+
+        Ok(())
+    }
+
+    /// Turn the light off and let go of the device.
+    pub fn close(&mut self) {
+        if let (Some((mut file, _)), Some((_, idle))) =
+            (self.port.take(), self.sent.take())
+        {
+            let _ = file.write_all(&[idle]);
+        }
+    }
+}
+
+impl Drop for Busylight {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Edit the busy light's device and the byte sent for each state.
+pub fn settings_editor(ui: &mut egui::Ui, settings: &mut BusylightSettings) {
+    ui.checkbox(&mut settings.enabled, "Busy light on serial port");
+    if !settings.enabled {
+        return;
+    }
+
+    egui::Grid::new("busylight_settings").show(ui, |ui| {
+        ui.label("Device");
+        ui.add(
+            egui::TextEdit::singleline(&mut settings.device)
+                .hint_text("/dev/ttyACM0"),
+        );
+        ui.end_row();
+
+        for (label, byte) in [
+            ("Task period", &mut settings.task),
+            ("Short break", &mut settings.short_break),
+            ("Long break", &mut settings.long_break),
+            ("Paused", &mut settings.paused),
+            ("Idle", &mut settings.idle),
+        ] {
+            ui.label(label);
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(byte));
+                // show printable bytes as the character they are
+                if byte.is_ascii_graphic() {
+                    ui.label(format!("'{}'", *byte as char));
+                }
+            });
+            ui.end_row();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_busylight() {
+        let path = std::env::temp_dir()
+            .join(format!("time-flo-busylight-{}", std::process::id()));
+        fs::write(&path, "").unwrap();
+
+        let settings = BusylightSettings {
+            enabled: true,
+            device: path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        assert_eq!(b'G', settings.signal(State::LongBreak, false));
+        assert_eq!(b'Y', settings.signal(State::Task, true));
+        assert_eq!(b'O', settings.signal(State::Idle, true));
+
+        let mut light = Busylight::default();
+        light.update(&settings, State::Task, false).unwrap();
+        light.update(&settings, State::Task, false).unwrap();
+        light.update(&settings, State::Task, true).unwrap();
+        light.update(&settings, State::ShortBreak, false).unwrap();
+        drop(light);
+
+        assert_eq!("RYGO", fs::read_to_string(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+}
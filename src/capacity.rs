@@ -0,0 +1,331 @@
+//! Checking planned task intervals against the time actually free in a day.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{
+    DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+
+use crate::profile::Profile;
+use crate::time_of_day::TimeOfDay;
+
+/// A span of time taken up by a calendar event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Busy {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+fn parse_ics_time(value: &str, utc: bool) -> Option<DateTime<Local>> {
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    if utc {
+        Some(Utc.from_utc_datetime(&time).with_timezone(&Local))
+    } else {
+        Local.from_local_datetime(&time).earliest()
+    }
+}
+
+/// Read the busy times from an iCalendar file.
+///
+/// Only single events with a start and end time are understood; all-day and
+/// recurring events are ignored.
+pub fn parse_ics(text: &str) -> Vec<Busy> {
+    let mut busy = Vec::new();
+    let mut start = None;
+    let mut end = None;
+    let mut recurring = false;
+
+    for line in text.lines() {
+        let (name, value) = match line.trim_end().split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        // drop parameters, e.g. DTSTART;TZID=Europe/Berlin
+        let name = name.split(';').next().unwrap_or_default();
+        let utc = value.ends_with('Z');
+        let value = value.trim_end_matches('Z');
+
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                start = None;
+                end = None;
+                recurring = false;
+            }
+            ("DTSTART", _) => start = parse_ics_time(value, utc),
+            ("DTEND", _) => end = parse_ics_time(value, utc),
+            ("RRULE", _) => recurring = true,
+            ("END", "VEVENT") => {
+                if let (Some(start), Some(end), false) = (start, end, recurring)
+                {
+                    busy.push(Busy { start, end });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    busy
+}
+
+/// Read the busy times from the iCalendar file at the given path.
+pub fn load_ics(path: &Path) -> crate::Result<Vec<Busy>> {
+    Ok(parse_ics(&fs::read_to_string(path)?))
+}
+
+/// The event taking place at the given time, or starting within `lead` of
+/// it. The one ending last is chosen if several overlap.
+pub fn event_at(
+    busy: &[Busy],
+    at: DateTime<Local>,
+    lead: chrono::Duration,
+) -> Option<Busy> {
+    busy.iter()
+        .filter(|b| b.start - lead <= at && at < b.end)
+        .max_by_key(|b| b.end)
+        .copied()
+}
+
+/// Minutes of working time left on the given day after `from`, excluding
+/// any busy times.
+pub fn free_minutes(
+    day: NaiveDate,
+    work_start: TimeOfDay,
+    work_end: TimeOfDay,
+    busy: &[Busy],
+    from: DateTime<Local>,
+) -> i64 {
+    let at = |time: TimeOfDay| {
+        Local
+            .from_local_datetime(&day.and_hms(time.hour, time.minute, 0))
+            .earliest()
+    };
+    let (start, end) = match (at(work_start), at(work_end)) {
+        (Some(start), Some(end)) => (start.max(from), end),
+        _ => return 0,
+    };
+    if end <= start {
+        return 0;
+    }
+
+    // clip busy times to working hours and merge any overlaps
+    let mut spans: Vec<_> = busy
+        .iter()
+        .map(|b| (b.start.max(start), b.end.min(end)))
+        .filter(|(s, e)| s < e)
+        .collect();
+    spans.sort();
+
+    let mut busy_minutes = 0;
+    let mut covered_until = start;
+    for (s, e) in spans {
+        let s = s.max(covered_until);
+        if s < e {
+            busy_minutes += (e - s).num_minutes();
+            covered_until = e;
+        }
+    }
+
+    (end - start).num_minutes() - busy_minutes
+}
+
+/// Average minutes taken by each task interval over a full cycle, including
+/// its share of the breaks.
+pub fn minutes_per_task(
+    task_minutes: f32,
+    short_break_minutes: f32,
+    long_break_minutes: f32,
+    num_short_breaks: u32,
+) -> f32 {
+    let tasks = (num_short_breaks + 1) as f32;
+    let cycle = task_minutes * tasks
+        + short_break_minutes * num_short_breaks as f32
+        + long_break_minutes;
+    cycle / tasks
+}
+
+/// Whole number of task intervals that fit into the given free time.
+pub fn task_capacity(free_minutes: i64, minutes_per_task: f32) -> u32 {
+    if minutes_per_task <= 0. {
+        return 0;
+    }
+    (free_minutes.max(0) as f32 / minutes_per_task) as u32
+}
+
+/// Today's calendar events, and how many task intervals fit around them.
+#[derive(Default)]
+pub struct Capacity {
+    /// Today's calendar events.
+    pub calendar: Vec<Busy>,
+    /// Number of task intervals which still fit into today's free time.
+    pub tasks: Option<u32>,
+    /// When the number of task intervals was last worked out.
+    updated: Option<DateTime<Local>>,
+}
+
+impl Capacity {
+    /// Read the calendar at `path`, which has no events if the path is empty.
+    /// Everything worked out so far is forgotten if it cannot be read.
+    pub fn load(&mut self, path: &str) -> crate::Result<()> {
+        let path = path.trim();
+        if path.is_empty() {
+            self.calendar.clear();
+            return Ok(());
+        }
+
+        match load_ics(Path::new(path)) {
+            Ok(busy) => {
+                self.calendar = busy;
+                Ok(())
+            }
+            Err(err) => {
+                *self = Self::default();
+                Err(err)
+            }
+        }
+    }
+
+    /// Work out how many task intervals of `profile` fit into the rest of the
+    /// working day, around the calendar events already read.
+    pub fn update(
+        &mut self,
+        work_start: TimeOfDay,
+        work_end: TimeOfDay,
+        profile: &Profile,
+        now: DateTime<Local>,
+    ) {
+        let free = free_minutes(
+            now.date().naive_local(),
+            work_start,
+            work_end,
+            &self.calendar,
+            now,
+        );
+        let per_task = minutes_per_task(
+            profile.task_minutes,
+            profile.short_break_minutes,
+            profile.long_break_minutes,
+            profile.num_short_breaks,
+        );
+        self.tasks = Some(task_capacity(free, per_task));
+        self.updated = Some(now);
+    }
+
+    /// Whether the number of task intervals has been worked out at all.
+    pub fn is_known(&self) -> bool {
+        self.updated.is_some()
+    }
+
+    /// Whether the number of task intervals was worked out before the minute
+    /// of `now`, as the rest of the working day shrinks.
+    pub fn is_stale(&self, now: DateTime<Local>) -> bool {
+        match self.updated {
+            Some(updated) => {
+                now.date() != updated.date()
+                    || (now.hour(), now.minute())
+                        != (updated.hour(), updated.minute())
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capacity() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   DTSTART:20211101T100000\r\n\
+                   DTEND:20211101T110000\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   DTSTART;TZID=Somewhere:20211101T103000\r\n\
+                   DTEND;TZID=Somewhere:20211101T120000\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   DTSTART:20211101T130000\r\n\
+                   DTEND:20211101T140000\r\n\
+                   RRULE:FREQ=DAILY\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   DTSTART;VALUE=DATE:20211101\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+        let busy = parse_ics(ics);
+        assert_eq!(2, busy.len());
+
+        let day = NaiveDate::from_ymd(2021, 11, 1);
+        let morning = Local.ymd(2021, 11, 1).and_hms(8, 0, 0);
+
+        // 9 to 5 with 10 to 12 taken
+        let free = free_minutes(
+            day,
+            TimeOfDay::new(9, 0),
+            TimeOfDay::new(17, 0),
+            &busy,
+            morning,
+        );
+        assert_eq!(6 * 60, free);
+
+        // starting later in the day
+        let afternoon = Local.ymd(2021, 11, 1).and_hms(15, 0, 0);
+        assert_eq!(
+            120,
+            free_minutes(
+                day,
+                TimeOfDay::new(9, 0),
+                TimeOfDay::new(17, 0),
+                &busy,
+                afternoon
+            )
+        );
+
+        // the later of two overlapping events, or one about to start
+        let at = |hour, minute| Local.ymd(2021, 11, 1).and_hms(hour, minute, 0);
+        let lead = chrono::Duration::minutes(5);
+        assert_eq!(None, event_at(&busy, at(9, 0), lead));
+        assert_eq!(Some(busy[0]), event_at(&busy, at(9, 55), lead));
+        assert_eq!(Some(busy[1]), event_at(&busy, at(10, 45), lead));
+        assert_eq!(None, event_at(&busy, at(12, 0), lead));
+
+        // 4 tasks of 25 minutes, 3 short breaks of 5, and a long break of 15
+        let per_task = minutes_per_task(25., 5., 15., 3);
+        assert_eq!(32.5, per_task);
+        assert_eq!(11, task_capacity(free, per_task));
+        assert_eq!(0, task_capacity(-10, per_task));
+    }
+    #[test]
+    fn test_capacity_tracking() {
+        let mut capacity = Capacity::default();
+        assert!(capacity.load("").is_ok());
+        assert!(!capacity.is_known());
+
+        let now = Local.ymd(2021, 11, 1).and_hms(15, 0, 0);
+        let profile = Profile {
+            task_minutes: 25.,
+            short_break_minutes: 5.,
+            long_break_minutes: 15.,
+            num_short_breaks: 3,
+            ..Default::default()
+        };
+        capacity.update(
+            TimeOfDay::new(9, 0),
+            TimeOfDay::new(17, 0),
+            &profile,
+            now,
+        );
+        assert_eq!(Some(3), capacity.tasks);
+
+        // worked out again once the minute changes
+        assert!(!capacity.is_stale(now + chrono::Duration::seconds(30)));
+        assert!(capacity.is_stale(now + chrono::Duration::seconds(60)));
+
+        // an unreadable calendar forgets the capacity
+        assert!(capacity.load("/nonexistent/calendar.ics").is_err());
+        assert!(!capacity.is_known());
+        assert_eq!(None, capacity.tasks);
+    }
+}
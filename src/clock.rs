@@ -0,0 +1,94 @@
+//! The source of the current time for timers and state transitions, which can
+//! be replaced with a mock clock so that tests can advance time without
+//! sleeping.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// A source of the current time.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock frozen in time unless advanced. Clones share the same time.
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    now: Rc<Cell<Instant>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            now: Rc::new(Cell::new(Instant::now())),
+        }
+    }
+
+    /// Move the time forward.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The clock unit tests run on, unless they set another.
+    static MOCK: MockClock = MockClock::new();
+}
+
+thread_local! {
+    static CLOCK: RefCell<Rc<dyn Clock>> = RefCell::new(default_clock());
+}
+
+#[cfg(not(test))]
+fn default_clock() -> Rc<dyn Clock> {
+    Rc::new(SystemClock)
+}
+
+#[cfg(test)]
+fn default_clock() -> Rc<dyn Clock> {
+    Rc::new(MOCK.with(MockClock::clone))
+}
+
+/// Use the given clock for everything on the current thread.
+pub fn set(clock: impl Clock + 'static) {
+    CLOCK.with(|current| *current.borrow_mut() = Rc::new(clock));
+}
+
+/// The current time, according to the current thread's clock.
+pub fn now() -> Instant {
+    CLOCK.with(|clock| clock.borrow().now())
+}
+
+/// Move the default mock clock forward.
+#[cfg(test)]
+pub fn advance(duration: Duration) {
+    MOCK.with(|mock| mock.advance(duration));
+}
+
+/// Time elapsed since the given instant.
+pub fn elapsed(since: Instant) -> Duration {
+    now().saturating_duration_since(since)
+}
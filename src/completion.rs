@@ -0,0 +1,211 @@
+//! Delivering the end of the running interval from a background thread, so
+//! that it arrives on time even while the window is minimized or hidden and
+//! not being repainted. The state change itself follows on the next update,
+//! which the thread asks for.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::epi::RepaintSignal;
+#[cfg(feature = "sound")]
+use log::warn;
+
+/// Deadlines closer together than this are taken to be the same, so that
+/// the watcher is not rearmed on every frame.
+const TOLERANCE: Duration = Duration::from_millis(100);
+
+/// What to deliver when the running interval ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub at: Instant,
+    /// Notification to send, if any.
+    pub message: Option<String>,
+    /// Whether to play the alert sound.
+    pub sound: bool,
+}
+
+impl Completion {
+    fn same_as(&self, other: &Completion) -> bool {
+        let gap = if self.at > other.at {
+            self.at - other.at
+        } else {
+            other.at - self.at
+        };
+        gap < TOLERANCE
+            && self.message == other.message
+            && self.sound == other.sound
+    }
+}
+
+/// A completion delivered in the background, with what became of its sound.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delivered {
+    pub completion: Completion,
+    /// How long the alert sound plays for, if known.
+    pub sound_duration: Option<Duration>,
+    /// Why the alert sound could not be loaded, if a beep played instead.
+    pub sound_error: Option<String>,
+}
+
+/// Waits for the running interval to end in the background.
+pub struct CompletionWatcher {
+    sender: Sender<Option<Completion>>,
+    delivered: Receiver<Delivered>,
+    /// The completion last armed, if any.
+    armed: Option<Completion>,
+}
+
+impl CompletionWatcher {
+    pub fn start(signal: Arc<dyn RepaintSignal>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Option<Completion>>();
+        let (delivered_sender, delivered) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut armed: Option<Completion> = None;
+
+            loop {
+                let message = match &armed {
+                    Some(completion) => receiver.recv_timeout(
+                        completion.at.saturating_duration_since(Instant::now()),
+                    ),
+                    None => receiver
+                        .recv()
+                        .map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match message {
+                    Ok(completion) => armed = completion,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Some(completion) = armed.take() {
+                            let delivered = deliver(completion);
+                            if delivered_sender.send(delivered).is_err() {
+                                break;
+                            }
+                        }
+                        signal.request_repaint();
+                    }
+                    // the watcher has been dropped
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        Self {
+            sender,
+            delivered,
+            armed: None,
+        }
+    }
+
+    /// Wait for the given completion, or for none, replacing any before.
+    pub fn arm(&mut self, completion: Option<Completion>) {
+        let unchanged = match (&self.armed, &completion) {
+            (Some(armed), Some(completion)) => armed.same_as(completion),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+
+        self.armed = completion.clone();
+        // the thread only stops once the watcher is dropped
+        let _ = self.sender.send(completion);
+    }
+
+    /// The completion delivered in the background since this was last
+    /// called, if any. Focus sounds are left for the caller to duck.
+    pub fn take_delivered(&mut self) -> Option<Delivered> {
+        let delivered = self.delivered.try_iter().last()?;
+        self.armed = None;
+        Some(delivered)
+    }
+}
+
+fn deliver(completion: Completion) -> Delivered {
+    #[cfg_attr(not(feature = "sound"), allow(unused_mut))]
+    let mut delivered = Delivered {
+        completion,
+        sound_duration: None,
+        sound_error: None,
+    };
+
+    #[cfg(feature = "notifications")]
+    if let Some(message) = &delivered.completion.message {
+        crate::notifier::Notifier::default().send(message);
+    }
+
+    // the sound is loaded here so the window can tell how long to duck for,
+    // and whether to say it fell back to a beep
+    #[cfg(feature = "sound")]
+    if delivered.completion.sound {
+        let source: AlertSource = match crate::audio::alert_sound() {
+            Ok(source) => Box::new(source),
+            Err(err) => {
+                warn!("Could not load alert sound: {:?}", err);
+                delivered.sound_error = Some(err.to_string());
+                Box::new(crate::audio::beep())
+            }
+        };
+        let duration = rodio::Source::total_duration(&source);
+        delivered.sound_duration = duration;
+
+        thread::spawn(move || {
+            if let Err(err) = play_alert_sound(source, duration) {
+                warn!("Could not play sound: {:?}", err);
+            }
+        });
+    }
+
+    delivered
+}
+
+#[cfg(feature = "sound")]
+type AlertSource = Box<dyn rodio::Source<Item = f32> + Send>;
+
+/// Play the alert sound on a stream of its own, kept open until it is done.
+#[cfg(feature = "sound")]
+fn play_alert_sound(
+    source: AlertSource,
+    duration: Option<Duration>,
+) -> crate::Result<()> {
+    let mut audio = crate::audio::Audio::default();
+    audio.play(source)?;
+
+    thread::sleep(duration.unwrap_or(Duration::from_secs(2)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_completion() {
+        let now = Instant::now();
+        let completion = Completion {
+            at: now,
+            message: Some("Time to take a break!".to_owned()),
+            sound: true,
+        };
+        let jittered = Completion {
+            at: now + Duration::from_millis(20),
+            ..completion.clone()
+        };
+        assert!(completion.same_as(&jittered));
+        assert!(jittered.same_as(&completion));
+
+        let later = Completion {
+            at: now + Duration::from_secs(60),
+            ..completion.clone()
+        };
+        assert!(!completion.same_as(&later));
+        let silent = Completion {
+            sound: false,
+            ..completion.clone()
+        };
+        assert!(!completion.same_as(&silent));
+    }
+}
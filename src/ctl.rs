@@ -0,0 +1,261 @@
+//! The `time-flo ctl` command line interface, for querying a running
+//! instance from scripts.
+
+use std::io;
+
+use crate::ipc;
+use crate::State;
+
+const USAGE: &str = "\
+Usage: time-flo ctl <command> [options]
+
+Commands:
+    status [--json]                 Print the state of the running timer
+    watch [--json] [--every SECS]   Print the state whenever it changes,
+                                    with the time left updated every SECS
+                                    seconds (default 1)
+    start | pause | toggle | skip   Control the running timer
+    stdio                           Relay JSON requests from stdin, one per
+                                    line, and print the responses
+    tmux [--interval SECS]          Print the time left for a tmux status
+                                    line, colored by state, in minutes if
+                                    SECS (tmux's status-interval, default
+                                    15) is a minute or more
+    discover [--timeout SECS]       List instances serving over HTTP on the
+                                    local network, waiting SECS seconds
+                                    (default 3; needs the mdns feature)
+
+For example, in ~/.tmux.conf:
+    set -g status-interval 5
+    set -g status-right '#(time-flo ctl tmux --interval 5)'
+";
+
+/// Run the command line interface, returning the exit code.
+pub fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["status"] => print_status(false),
+        ["status", "--json"] => print_status(true),
+        ["watch", options @ ..] => match parse_watch_options(options) {
+            Some((json, every)) => watch(json, every),
+            None => {
+                eprint!("{}", USAGE);
+                2
+            }
+        },
+        ["start"] => send(ipc::Request::Start),
+        ["pause"] => send(ipc::Request::Pause),
+        ["toggle"] => send(ipc::Request::Toggle),
+        ["skip"] => send(ipc::Request::Skip),
+        ["stdio"] => stdio(),
+        ["tmux"] => print_tmux(15),
+        ["tmux", "--interval", interval] => match interval.parse() {
+            Ok(interval) => print_tmux(interval),
+            Err(_) => {
+                eprint!("{}", USAGE);
+                2
+            }
+        },
+        ["discover"] => discover(3),
+        ["discover", "--timeout", timeout] => match timeout.parse() {
+            Ok(timeout) => discover(timeout),
+            Err(_) => {
+                eprint!("{}", USAGE);
+                2
+            }
+        },
+        ["help"] | ["--help"] | ["-h"] => {
+            print!("{}", USAGE);
+            0
+        }
+        _ => {
+            eprint!("{}", USAGE);
+            2
+        }
+    }
+}
+
+fn format_status(status: &ipc::Status) -> String {
+    let mut line = format!(
+        "{} {:02}:{:02}",
+        status.state,
+        status.remaining_secs / 60,
+        status.remaining_secs % 60
+    );
+    if status.paused {
+        line.push_str(" (paused)");
+    }
+    if let Some(task) = &status.task {
+        line.push_str(&format!(" - {}", task));
+    }
+    line
+}
+
+/// The status in tmux's format, such as `#[fg=red]12:34#[default]`, with
+/// the time left in whole minutes if the status line is only updated every
+/// minute or more.
+fn format_tmux(status: &ipc::Status, interval_secs: u64) -> String {
+    let color = match status.state {
+        State::Idle => return String::new(),
+        State::Task => "red",
+        State::ShortBreak => "green",
+        State::LongBreak => "blue",
+    };
+
+    let remaining = status.remaining_secs;
+    let time = if interval_secs >= 60 {
+        format!("{}m", (remaining + 59) / 60)
+    } else {
+        format!("{:02}:{:02}", remaining / 60, remaining % 60)
+    };
+
+    if status.paused {
+        format!("#[fg={},dim]{} paused#[default]", color, time)
+    } else {
+        format!("#[fg={}]{}#[default]", color, time)
+    }
+}
+
+fn print_tmux(interval_secs: u64) -> i32 {
+    // print nothing rather than an error, so as not to clutter the status
+    // line when TimeFlo is not running
+    if let Ok(status) = ipc::status() {
+        println!("{}", format_tmux(&status, interval_secs));
+    }
+    0
+}
+
+#[cfg(feature = "mdns")]
+fn discover(timeout_secs: u64) -> i32 {
+    use std::time::Duration;
+
+    match crate::mdns::discover(Duration::from_secs(timeout_secs)) {
+        Ok(instances) => {
+            for instance in instances {
+                let url = instance.url().unwrap_or_default();
+                println!("{}\t{}", instance.name, url);
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("time-flo: {}", err);
+            1
+        }
+    }
+}
+
+#[cfg(not(feature = "mdns"))]
+fn discover(_timeout_secs: u64) -> i32 {
+    eprintln!("time-flo: discover needs the mdns feature");
+    1
+}
+
+fn send(request: ipc::Request) -> i32 {
+    match ipc::send(&request) {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("time-flo: {}", err);
+            1
+        }
+    }
+}
+
+fn stdio() -> i32 {
+    let stdin = io::stdin();
+    match ipc::relay(stdin.lock(), io::stdout()) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("time-flo: {}", err);
+            1
+        }
+    }
+}
+
+fn print_status(json: bool) -> i32 {
+    let result = if json {
+        ipc::send(&ipc::Request::Status)
+    } else {
+        ipc::status().map(|status| format_status(&status))
+    };
+
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            0
+        }
+        Err(err) => {
+            eprintln!("time-flo: {}", err);
+            1
+        }
+    }
+}
+
+/// Parse `[--json] [--every SECS]` in any order.
+fn parse_watch_options(options: &[&str]) -> Option<(bool, u64)> {
+    let mut json = false;
+    let mut every = 1;
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match *option {
+            "--json" => json = true,
+            "--every" => every = options.next()?.parse().ok()?,
+            _ => return None,
+        }
+    }
+
+    Some((json, every))
+}
+
+fn watch(json: bool, every: u64) -> i32 {
+    let result = ipc::subscribe(every, |line| {
+        if json {
+            println!("{}", line);
+        } else {
+            match serde_json::from_str::<ipc::Status>(line) {
+                Ok(status) => println!("{}", format_status(&status)),
+                Err(err) => eprintln!("time-flo: {}", err),
+            }
+        }
+        true
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("time-flo: {}", err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_tmux() {
+        let mut status = ipc::Status {
+            state: State::Task,
+            remaining_secs: 754,
+            short_break_counter: 0,
+            task: Some("Write report".to_owned()),
+            paused: false,
+            focus_score: 0,
+            running: true,
+        };
+        assert_eq!("#[fg=red]12:34#[default]", format_tmux(&status, 1));
+        assert_eq!("#[fg=red]13m#[default]", format_tmux(&status, 60));
+
+        status.state = State::ShortBreak;
+        status.paused = true;
+        assert_eq!(
+            "#[fg=green,dim]12:34 paused#[default]",
+            format_tmux(&status, 15)
+        );
+
+        status.state = State::Idle;
+        assert_eq!("", format_tmux(&status, 15));
+    }
+}
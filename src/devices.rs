@@ -0,0 +1,141 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+
+/// How often the devices are re-checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How often to look for the result of a check still in progress.
+const PENDING_POLL: Duration = Duration::from_millis(250);
+
+/// Whether a microphone or camera is currently in use by another program,
+/// such as during a call.
+///
+/// This is only implemented on Linux, by looking for ALSA capture streams
+/// which are running and video devices held open, and always returns `false`
+/// elsewhere.
+#[cfg(target_os = "linux")]
+pub fn in_use() -> bool {
+    microphone_in_use() || camera_in_use()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn in_use() -> bool {
+    false
+}
+
+/// Whether any capture stream of any sound card is running.
+#[cfg(target_os = "linux")]
+fn microphone_in_use() -> bool {
+    use std::fs;
+
+    let cards = match fs::read_dir("/proc/asound") {
+        Ok(cards) => cards,
+        Err(_) => return false,
+    };
+
+    cards
+        .filter_map(|card| card.ok())
+        .filter_map(|card| fs::read_dir(card.path()).ok())
+        .flatten()
+        .filter_map(|pcm| pcm.ok())
+        // capture devices are named like `pcm0c`
+        .filter(|pcm| {
+            let name = pcm.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("pcm") && name.ends_with('c')
+        })
+        .filter_map(|pcm| fs::read_dir(pcm.path()).ok())
+        .flatten()
+        .filter_map(|sub| sub.ok())
+        .any(|sub| {
+            fs::read_to_string(sub.path().join("status"))
+                .map_or(false, |status| status.contains("RUNNING"))
+        })
+}
+
+/// Whether another process holds a video device open.
+#[cfg(target_os = "linux")]
+fn camera_in_use() -> bool {
+    use std::fs;
+
+    let own_pid = std::process::id().to_string();
+    let processes = match fs::read_dir("/proc") {
+        Ok(processes) => processes,
+        Err(_) => return false,
+    };
+
+    processes
+        .filter_map(|process| process.ok())
+        .filter(|process| {
+            let name = process.file_name();
+            let name = name.to_string_lossy();
+            name != own_pid.as_str() && name.bytes().all(|b| b.is_ascii_digit())
+        })
+        // only the processes we may look into
+        .filter_map(|process| fs::read_dir(process.path().join("fd")).ok())
+        .flatten()
+        .filter_map(|fd| fd.ok())
+        .any(|fd| {
+            fs::read_link(fd.path()).map_or(false, |target| {
+                target.to_string_lossy().starts_with("/dev/video")
+            })
+        })
+}
+
+/// Caches whether the devices are in use so that they are not queried every
+/// frame, checking them on a background thread since walking `/proc` can
+/// take a while.
+#[derive(Default)]
+pub struct DeviceMonitor {
+    in_use: bool,
+    last_check: Option<Instant>,
+    /// The result of the check in progress, if any.
+    pending: Option<Receiver<bool>>,
+}
+
+impl DeviceMonitor {
+    /// Whether the devices were in use as of the last finished check,
+    /// starting another if it is time to.
+    pub fn in_use(&mut self) -> bool {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(in_use) => {
+                    self.in_use = in_use;
+                    self.pending = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        let should_check = self
+            .last_check
+            .map_or(true, |last| clock::elapsed(last) >= CHECK_INTERVAL);
+
+        if should_check && self.pending.is_none() {
+            self.last_check = Some(clock::now());
+
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = sender.send(in_use());
+            });
+            self.pending = Some(receiver);
+        }
+
+        self.in_use
+    }
+
+    /// How long until the devices are checked again, or until the check in
+    /// progress should be looked at.
+    pub fn until_check(&self) -> Duration {
+        if self.pending.is_some() {
+            return PENDING_POLL;
+        }
+
+        self.last_check.map_or(Duration::ZERO, |last| {
+            CHECK_INTERVAL.saturating_sub(clock::elapsed(last))
+        })
+    }
+}
@@ -0,0 +1,247 @@
+//! A read-only kiosk display of another instance's timer, such as on a
+//! shared screen in an office, fetched from its HTTP API.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Color32, FontDefinitions, FontFamily, TextStyle};
+use eframe::epi;
+
+use crate::ipc::Status;
+use crate::{State, TimeFloError};
+
+/// The port the HTTP server listens on unless configured otherwise.
+pub const DEFAULT_PORT: u16 = 7315;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where to fetch the status from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source {
+    pub host: String,
+    pub port: u16,
+    /// An API token, which may be read-only.
+    pub token: String,
+}
+
+impl Source {
+    /// Parse an address such as `http://desk.local:7315`, `desk:7315`, or
+    /// just `desk`.
+    pub fn parse(address: &str, token: String) -> Option<Self> {
+        let address = address.trim();
+        let address = address.strip_prefix("http://").unwrap_or(address);
+        let address = address.trim_end_matches('/');
+
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (address, DEFAULT_PORT),
+        };
+        if host.is_empty() || host.contains('/') {
+            return None;
+        }
+
+        Some(Self {
+            host: host.to_owned(),
+            port,
+            token,
+        })
+    }
+
+    fn url(&self) -> String {
+        format!("http://{}:{}/api/status", self.host, self.port)
+    }
+
+    fn fetch(&self) -> crate::Result<Status> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        write!(
+            stream,
+            "GET /api/status HTTP/1.1\r\n\
+             Host: {}:{}\r\n\
+             Authorization: Bearer {}\r\n\
+             Connection: close\r\n\r\n",
+            self.host, self.port, self.token
+        )?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        parse_response(&response)
+            .map_err(|err| TimeFloError::integration(self.url(), err))
+    }
+}
+
+/// The status from an HTTP response.
+fn parse_response(response: &str) -> Result<Status, String> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| "incomplete response".to_owned())?;
+    let status_line = head.lines().next().unwrap_or_default();
+
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => {
+            serde_json::from_str(body).map_err(|err| err.to_string())
+        }
+        Some("401") => Err("the token was not accepted".to_owned()),
+        _ => Err(format!("unexpected response: {}", status_line)),
+    }
+}
+
+/// The last status fetched and when, or why fetching it failed.
+type Latest = Arc<Mutex<Option<Result<(Status, Instant), String>>>>;
+
+/// Shows the countdown and state in large digits, filling the window.
+pub struct DisplayApp {
+    source: Source,
+    latest: Latest,
+    /// Size of the digits, which follows the size of the window.
+    font_size: f32,
+}
+
+impl DisplayApp {
+    pub fn new(source: Source) -> Self {
+        Self {
+            source,
+            latest: Latest::default(),
+            font_size: 0.,
+        }
+    }
+
+    /// Size the digits to fill the given space.
+    fn fit_digits(&mut self, ctx: &egui::CtxRef, size: egui::Vec2) {
+        // five monospace characters, each about 0.6 of the font size wide
+        let font_size = (size.x / 3.2).min(size.y * 0.6).max(14.).round();
+        if (font_size - self.font_size).abs() < 1. {
+            return;
+        }
+
+        let mut fonts = FontDefinitions::default();
+        fonts
+            .family_and_size
+            .insert(TextStyle::Heading, (FontFamily::Monospace, font_size));
+        ctx.set_fonts(fonts);
+        self.font_size = font_size;
+    }
+}
+
+impl epi::App for DisplayApp {
+    fn name(&self) -> &str {
+        "TimeFlo Display"
+    }
+
+    fn setup(
+        &mut self,
+        _ctx: &egui::CtxRef,
+        frame: &mut epi::Frame<'_>,
+        _storage: Option<&dyn epi::Storage>,
+    ) {
+        let source = self.source.clone();
+        let latest = Arc::clone(&self.latest);
+        let signal = frame.repaint_signal();
+        thread::spawn(move || loop {
+            let result = source
+                .fetch()
+                .map(|status| (status, Instant::now()))
+                .map_err(|err| err.to_string());
+            if let Ok(mut latest) = latest.lock() {
+                *latest = Some(result);
+            }
+            signal.request_repaint();
+            thread::sleep(POLL_INTERVAL);
+        });
+    }
+
+    fn update(&mut self, ctx: &egui::CtxRef, frame: &mut epi::Frame<'_>) {
+        if ctx.input().key_pressed(egui::Key::Escape) {
+            frame.quit();
+        }
+
+        self.fit_digits(ctx, ctx.available_rect().size());
+
+        let latest = self.latest.lock().ok().and_then(|latest| latest.clone());
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| match latest {
+                Some(Ok((status, fetched_at))) => {
+                    let mut remaining = status.remaining_secs;
+                    if status.running {
+                        // count down smoothly between fetches
+                        remaining = remaining
+                            .saturating_sub(fetched_at.elapsed().as_secs());
+                    }
+
+                    let color = match status.state {
+                        State::Idle => Color32::GRAY,
+                        State::Task => Color32::RED,
+                        State::ShortBreak => Color32::GREEN,
+                        State::LongBreak => Color32::from_rgb(90, 170, 255),
+                    };
+
+                    let mut state = status.state.name().to_owned();
+                    if status.paused {
+                        state.push_str(" (paused)");
+                    }
+                    ui.label(state);
+                    ui.add(
+                        egui::Label::new(format!(
+                            "{:02}:{:02}",
+                            remaining / 60,
+                            remaining % 60
+                        ))
+                        .text_style(TextStyle::Heading)
+                        .text_color(color),
+                    );
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Could not reach TimeFlo: {}", err));
+                }
+                None => {
+                    ui.label("Connecting\u{2026}");
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_source() {
+        let source = |address| Source::parse(address, String::new());
+        let expected = |host: &str, port| {
+            Some(Source {
+                host: host.to_owned(),
+                port,
+                token: String::new(),
+            })
+        };
+        assert_eq!(
+            expected("desk.local", 8000),
+            source("http://desk.local:8000/")
+        );
+        assert_eq!(expected("192.168.1.20", 7315), source("192.168.1.20"));
+        assert_eq!(None, source("desk:port"));
+        assert_eq!(None, source("http://desk/api"));
+        assert_eq!(None, source(""));
+
+        let status = parse_response(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n\
+             {\"state\":\"Task\",\"remaining_secs\":754,\
+             \"short_break_counter\":0,\"task\":null,\"paused\":false}",
+        )
+        .unwrap();
+        assert_eq!(State::Task, status.state);
+        assert_eq!(754, status.remaining_secs);
+        assert!(!status.running);
+
+        assert!(parse_response("HTTP/1.1 401 Unauthorized\r\n\r\n").is_err());
+        assert!(parse_response("HTTP/1.1 200 OK\r\n").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+use std::io;
+use std::process::Command;
+use std::thread;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// How strongly long breaks are enforced.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum Enforcement {
+    Off,
+    /// Skipping the break asks for confirmation.
+    Nag,
+    /// The window shows nothing but the break until it is over.
+    Overlay,
+    /// The screen is locked when the break starts.
+    LockScreen,
+}
+
+impl Default for Enforcement {
+    fn default() -> Self {
+        Enforcement::Off
+    }
+}
+
+impl fmt::Display for Enforcement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Enforcement::Off => "Off",
+            Enforcement::Nag => "Nag",
+            Enforcement::Overlay => "Overlay",
+            Enforcement::LockScreen => "Lock screen",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl Enforcement {
+    pub const ALL: [Enforcement; 4] = [
+        Enforcement::Off,
+        Enforcement::Nag,
+        Enforcement::Overlay,
+        Enforcement::LockScreen,
+    ];
+}
+
+/// The platform command used to lock the screen.
+///
+/// macOS has no command which locks the screen by itself, so the display is
+/// put to sleep instead, which only locks it if a password is required after
+/// sleep in the security settings.
+fn lock_command() -> Option<Command> {
+    if cfg!(target_os = "linux") {
+        let mut command = Command::new("loginctl");
+        command.arg("lock-session");
+        Some(command)
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("pmset");
+        command.arg("displaysleepnow");
+        Some(command)
+    } else if cfg!(windows) {
+        let mut command = Command::new("rundll32.exe");
+        command.arg("user32.dll,LockWorkStation");
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// Lock the screen using the platform's lock command, without waiting for
+/// it. Only failing to start the command is returned; a command which fails
+/// afterwards is logged.
+pub fn lock_screen() -> crate::Result<()> {
+    let mut command = lock_command().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "locking the screen is not supported on this platform",
+        )
+    })?;
+
+    let mut child = command.spawn()?;
+
+    // reap the command once it exits, so a slow one never holds up the timer
+    thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            warn!("{:?} exited with {}", command, status)
+        }
+        Ok(_) => {}
+        Err(err) => warn!("Could not wait for {:?}: {:?}", command, err),
+    });
+
+    Ok(())
+}
@@ -1,18 +1,66 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, TimeFloError>;
 
 #[derive(Error, Debug)]
 pub enum TimeFloError {
-    #[error("i/o error")]
+    #[error("i/o error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("could not parse {}: {message}", path.display())]
+    ConfigParse { path: PathBuf, message: String },
+    #[error("could not communicate with TimeFlo: {0}")]
+    Ipc(String),
+    #[error("request to {url} failed: {message}")]
+    Integration { url: String, message: String },
+    #[error("data in {} is corrupt: {message}", path.display())]
+    DataCorrupt { path: PathBuf, message: String },
+    #[cfg(feature = "mdns")]
+    #[error("mDNS error: {0}")]
+    Mdns(#[from] mdns_sd::Error),
     #[cfg(feature = "notifications")]
-    #[error("notification error")]
+    #[error("notification error: {0}")]
     Notification(#[from] notify_rust::error::Error),
     #[cfg(feature = "sound")]
-    #[error("sound decoder error")]
+    #[error("could not decode sound: {0}")]
     SoundDecoder(#[from] rodio::decoder::DecoderError),
     #[cfg(feature = "sound")]
-    #[error("sound playback error")]
+    #[error("could not play sound: {0}")]
     SoundPlayback(#[from] rodio::PlayError),
+    #[cfg(feature = "sound")]
+    #[error("could not open audio output: {0}")]
+    SoundStream(#[from] rodio::StreamError),
+    #[cfg(feature = "encryption")]
+    #[error("encryption error: {0}")]
+    Encryption(String),
+    #[cfg(feature = "sqlite")]
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+impl TimeFloError {
+    pub fn config_parse(path: &Path, err: impl fmt::Display) -> Self {
+        TimeFloError::ConfigParse {
+            path: path.to_owned(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn integration(url: impl Into<String>, err: impl fmt::Display) -> Self {
+        TimeFloError::Integration {
+            url: url.into(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn data_corrupt(path: &Path, err: impl fmt::Display) -> Self {
+        TimeFloError::DataCorrupt {
+            path: path.to_owned(),
+            message: err.to_string(),
+        }
+    }
 }
@@ -0,0 +1,278 @@
+//! Comparing two profiles by alternating between them from day to day.
+
+use chrono::{Local, NaiveDate};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::history::History;
+use crate::locale::Locale;
+use crate::profile::Profile;
+
+/// An experiment using one profile on even days and the other on odd days.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Experiment {
+    /// Names of the profiles compared.
+    pub profiles: [String; 2],
+    /// Number of days the experiment runs for.
+    pub days: u32,
+    /// The first day of the experiment, or `None` if it is not running.
+    pub start: Option<NaiveDate>,
+}
+
+impl Default for Experiment {
+    fn default() -> Self {
+        Self {
+            profiles: [String::new(), String::new()],
+            days: 14,
+            start: None,
+        }
+    }
+}
+
+impl Experiment {
+    /// The index of the profile the given day is for, if it is part of the
+    /// experiment.
+    fn arm_on(&self, date: NaiveDate) -> Option<usize> {
+        let offset = (date - self.start?).num_days();
+        if offset < 0 || offset >= self.days as i64 {
+            return None;
+        }
+        Some(offset as usize % 2)
+    }
+
+    /// The profile to use on the given day, if it is part of the experiment.
+    pub fn profile_on(&self, date: NaiveDate) -> Option<&str> {
+        self.arm_on(date).map(|arm| self.profiles[arm].as_str())
+    }
+
+    /// The day after the last day of the experiment, if it is running.
+    pub fn end(&self) -> Option<NaiveDate> {
+        self.start
+            .map(|start| start + chrono::Duration::days(self.days as i64))
+    }
+
+    /// Whether every day of the experiment is over.
+    pub fn is_over(&self, today: NaiveDate) -> bool {
+        self.end().map_or(false, |end| today >= end)
+    }
+
+    /// How each profile did over the days of the experiment which are over,
+    /// leaving out days without any task intervals.
+    pub fn results(
+        &self,
+        history: &History,
+        goal: u32,
+        today: NaiveDate,
+    ) -> [ArmResult; 2] {
+        let mut results = [ArmResult::default(), ArmResult::default()];
+        let start = match self.start {
+            Some(start) => start,
+            None => return results,
+        };
+
+        let mut date = start;
+        while date < today {
+            let arm = match self.arm_on(date) {
+                Some(arm) => arm,
+                None => break,
+            };
+
+            let stats = history.day_stats(date);
+            if stats.completed + stats.skipped + stats.voided > 0 {
+                let result = &mut results[arm];
+                result.days += 1;
+                result.completed += stats.completed;
+                result.abandoned += stats.skipped + stats.voided;
+                result.focus_score += stats.focus_score(goal);
+            }
+            date = date.succ();
+        }
+
+        results
+    }
+}
+
+/// Totals for one profile of an experiment.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ArmResult {
+    /// Days with any task intervals.
+    pub days: u32,
+    pub completed: u32,
+    /// Task intervals which were skipped or voided.
+    pub abandoned: u32,
+    /// Sum of the focus scores of each day.
+    pub focus_score: u32,
+}
+
+impl ArmResult {
+    /// Share of task intervals which were completed, in percent.
+    pub fn completion_rate(&self) -> f32 {
+        let total = self.completed + self.abandoned;
+        if total == 0 {
+            return 0.;
+        }
+        self.completed as f32 / total as f32 * 100.
+    }
+
+    /// The mean focus score of a day.
+    pub fn mean_focus_score(&self) -> f32 {
+        self.focus_score as f32 / self.days.max(1) as f32
+    }
+}
+
+/// The index of the profile which did better, by mean focus score and then
+/// completion rate, or `None` if there is nothing to tell them apart.
+pub fn better(results: &[ArmResult; 2]) -> Option<usize> {
+    if results.iter().any(|result| result.days == 0) {
+        return None;
+    }
+
+    let key = |result: &ArmResult| {
+        (result.mean_focus_score(), result.completion_rate())
+    };
+    let (a, b) = (key(&results[0]), key(&results[1]));
+    if a > b {
+        Some(0)
+    } else if b > a {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Choose two profiles to compare on alternating days, and start or stop the
+/// experiment.
+pub fn experiment_editor(
+    ui: &mut egui::Ui,
+    experiment: &mut Experiment,
+    profiles: &[Profile],
+) {
+    if experiment.start.is_some() {
+        ui.label(format!(
+            "Comparing {} and {} over {} days",
+            experiment.profiles[0], experiment.profiles[1], experiment.days
+        ));
+        if ui.button("Stop experiment").clicked() {
+            experiment.start = None;
+        }
+        return;
+    }
+
+    for (i, label) in ["First profile", "Second profile"].iter().enumerate() {
+        egui::ComboBox::from_label(*label)
+            .selected_text(experiment.profiles[i].clone())
+            .show_ui(ui, |ui| {
+                for profile in profiles {
+                    ui.selectable_value(
+                        &mut experiment.profiles[i],
+                        profile.name.clone(),
+                        profile.name.clone(),
+                    );
+                }
+            });
+    }
+    slider!(ui, experiment.days, "Run for", 2..=56, " days");
+
+    let exists = |name: &String| profiles.iter().any(|p| &p.name == name);
+    if !experiment.profiles.iter().all(exists)
+        || experiment.profiles[0] == experiment.profiles[1]
+    {
+        ui.label("Choose two different profiles to compare.");
+    } else if ui.button("Start experiment").clicked() {
+        experiment.start = Some(Local::today().naive_local());
+    }
+}
+
+/// How the profiles of an experiment have done so far, and which did better
+/// once it is over.
+pub fn experiment_report(
+    ui: &mut egui::Ui,
+    experiment: &Experiment,
+    results: &[ArmResult; 2],
+    today: NaiveDate,
+    locale: Locale,
+) {
+    egui::Grid::new("experiment").show(ui, |ui| {
+        ui.label("Profile");
+        ui.label("Days");
+        ui.label("Completed");
+        ui.label("Focus score");
+        ui.end_row();
+
+        for (profile, result) in experiment.profiles.iter().zip(results) {
+            ui.label(profile);
+            ui.label(result.days.to_string());
+            ui.label(format!(
+                "{}%",
+                locale.format_decimal(result.completion_rate() as f64, 0)
+            ));
+            ui.label(
+                locale.format_decimal(result.mean_focus_score() as f64, 0),
+            );
+            ui.end_row();
+        }
+    });
+
+    if !experiment.is_over(today) {
+        if let Some(end) = experiment.end() {
+            ui.label(format!("Runs until {}", locale.format_date(end)));
+        }
+        return;
+    }
+    match better(results) {
+        Some(i) => ui.label(format!("{} did better.", experiment.profiles[i])),
+        None => ui.label("Neither profile did better."),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EntryDraft;
+
+    #[test]
+    fn test_results() {
+        let start = NaiveDate::from_ymd(2021, 11, 1);
+        let day = |days: i64| start + chrono::Duration::days(days);
+        let experiment = Experiment {
+            profiles: ["Short".to_owned(), "Long".to_owned()],
+            days: 4,
+            start: Some(start),
+        };
+        assert_eq!(None, experiment.profile_on(day(-1)));
+        assert_eq!(Some("Short"), experiment.profile_on(day(0)));
+        assert_eq!(Some("Long"), experiment.profile_on(day(3)));
+        assert_eq!(None, experiment.profile_on(day(4)));
+        assert!(!experiment.is_over(day(3)));
+        assert!(experiment.is_over(day(4)));
+
+        let mut history = History::default();
+        for (days, tasks) in [(0, 4), (1, 1), (3, 2)] {
+            for hour in 9..9 + tasks {
+                let entry = EntryDraft {
+                    date: day(days).format("%Y-%m-%d").to_string(),
+                    start: format!("{:02}:00", hour),
+                    end: format!("{:02}:25", hour),
+                    task: "Write report".into(),
+                }
+                .parse()
+                .unwrap();
+                history.add(entry);
+            }
+        }
+
+        // days still under way are left out
+        let results = experiment.results(&history, 4, day(3));
+        assert_eq!(1, results[0].days);
+        assert_eq!(1, results[1].days);
+        assert_eq!(Some(0), better(&results));
+
+        let results = experiment.results(&history, 4, day(4));
+        assert_eq!(2, results[1].days);
+        assert_eq!(3, results[1].completed);
+        assert_eq!(100., results[1].completion_rate());
+        assert_eq!(Some(0), better(&results));
+        assert_eq!(None, better(&[ArmResult::default(); 2]));
+    }
+}
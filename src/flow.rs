@@ -0,0 +1,171 @@
+//! A flow of intervals defined in a file, for workflows which the built-in
+//! schedules cannot express.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::scheduler::State;
+use crate::TimeFloError;
+
+/// One interval of a flow.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FlowStep {
+    /// Name the step is referred to by in transitions.
+    pub name: String,
+    pub state: State,
+    /// How long the interval lasts, or `None` for the preferred duration of
+    /// its state.
+    pub minutes: Option<f32>,
+    /// Whether the interval starts on its own, or `None` to follow the
+    /// preferences.
+    pub auto_start: Option<bool>,
+    /// Name of the step which follows, or `None` for the next one in the
+    /// file.
+    pub next: Option<String>,
+}
+
+impl Default for FlowStep {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            state: State::Task,
+            minutes: None,
+            auto_start: None,
+            next: None,
+        }
+    }
+}
+
+/// A flow loaded from a TOML or JSON file, as a list of steps which is
+/// followed from the top.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Flow {
+    pub steps: Vec<FlowStep>,
+}
+
+impl Flow {
+    /// Load a flow from a file, choosing the format by its extension.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let flow: Flow = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .map_err(|err| TimeFloError::config_parse(path, err))?,
+            _ => toml::from_str(&contents)
+                .map_err(|err| TimeFloError::config_parse(path, err))?,
+        };
+        flow.validate()
+            .map_err(|err| TimeFloError::config_parse(path, err))?;
+        Ok(flow)
+    }
+
+    /// Check that the flow can be followed, describing the first problem
+    /// found otherwise.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.steps.is_empty() {
+            return Err("a flow needs at least one step".to_owned());
+        }
+
+        let mut names = HashSet::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let label = if step.name.is_empty() {
+                format!("step {}", i + 1)
+            } else {
+                format!("step \"{}\"", step.name)
+            };
+
+            if step.state == State::Idle {
+                return Err(format!("{} cannot be idle", label));
+            }
+            if step.minutes.map_or(false, |minutes| minutes <= 0.) {
+                return Err(format!("{} needs a positive duration", label));
+            }
+            if !step.name.is_empty() && !names.insert(step.name.as_str()) {
+                return Err(format!("{} is defined more than once", label));
+            }
+        }
+
+        for step in &self.steps {
+            if let Some(next) = &step.next {
+                if !names.contains(next.as_str()) {
+                    return Err(format!("there is no step \"{}\"", next));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The index of the step following the given one.
+    pub fn successor(&self, step: usize) -> usize {
+        let next = self.steps.get(step).and_then(|step| step.next.as_ref());
+        match next {
+            Some(name) => self
+                .steps
+                .iter()
+                .position(|step| &step.name == name)
+                .unwrap_or(0),
+            None => (step + 1) % self.steps.len().max(1),
+        }
+    }
+}
+
+/// The file the flow is defined in, if there is one.
+pub fn flow_path() -> Option<PathBuf> {
+    let dir = dirs::config_dir()?.join("time-flo");
+    ["toml", "json"]
+        .iter()
+        .map(|ext| dir.join(format!("flow.{}", ext)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let flow: Flow = toml::from_str(
+            r#"
+            [[steps]]
+            name = "warm-up"
+            state = "Task"
+            minutes = 10
+
+            [[steps]]
+            name = "deep"
+            state = "Task"
+            minutes = 50
+            auto_start = true
+
+            [[steps]]
+            state = "LongBreak"
+            minutes = 20
+            next = "deep"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Ok(()), flow.validate());
+        assert_eq!(Some(10.), flow.steps[0].minutes);
+        assert_eq!(Some(true), flow.steps[1].auto_start);
+        assert_eq!(1, flow.successor(0));
+        assert_eq!(2, flow.successor(1));
+        // the warm-up is only done once
+        assert_eq!(1, flow.successor(2));
+
+        let mut invalid = flow.clone();
+        invalid.steps[2].next = Some("cool-down".to_owned());
+        assert!(invalid.validate().is_err());
+        let mut invalid = flow.clone();
+        invalid.steps[1].name = "warm-up".to_owned();
+        assert!(invalid.validate().is_err());
+        let mut invalid = flow;
+        invalid.steps[0].state = State::Idle;
+        assert!(invalid.validate().is_err());
+        assert!(Flow::default().validate().is_err());
+    }
+}
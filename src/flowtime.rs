@@ -0,0 +1,86 @@
+//! Flowtime, where tasks count up for as long as the focus lasts, and short
+//! breaks are a share of the time focused.
+
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Flowtime {
+    pub enabled: bool,
+    /// Length of a short break as a share of the task before it, in percent.
+    pub break_percent: f32,
+    pub min_break_minutes: f32,
+    pub max_break_minutes: f32,
+}
+
+impl Default for Flowtime {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            break_percent: 20.,
+            min_break_minutes: 2.,
+            max_break_minutes: 30.,
+        }
+    }
+}
+
+impl Flowtime {
+    /// The short break earned by focusing for the given time.
+    pub fn break_for(&self, focused: Duration) -> Duration {
+        let minutes = focused.as_secs_f32() / 60. * self.break_percent / 100.;
+        let minutes = minutes
+            .min(self.max_break_minutes)
+            .max(self.min_break_minutes.min(self.max_break_minutes));
+        Duration::from_secs_f32(minutes.max(0.) * 60.)
+    }
+}
+
+/// Switch to Flowtime, and choose how long its breaks are.
+pub fn editor(ui: &mut egui::Ui, flowtime: &mut Flowtime) {
+    ui.checkbox(
+        &mut flowtime.enabled,
+        "Flowtime: count tasks up, with breaks based on the time focused",
+    );
+    if !flowtime.enabled {
+        return;
+    }
+
+    slider!(
+        ui,
+        flowtime.break_percent,
+        "Break length",
+        5.0..=50.0,
+        "% of task"
+    );
+    slider!(
+        ui,
+        flowtime.min_break_minutes,
+        "Shortest break",
+        0.5..=30.0,
+        " min"
+    );
+    slider!(
+        ui,
+        flowtime.max_break_minutes,
+        "Longest break",
+        0.5..=60.0,
+        " min"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_for() {
+        let flowtime = Flowtime::default();
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        assert_eq!(minutes(10), flowtime.break_for(minutes(50)));
+        assert_eq!(minutes(2), flowtime.break_for(minutes(3)));
+        assert_eq!(minutes(30), flowtime.break_for(minutes(400)));
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use rodio::{Sink, Source};
+use serde::{Deserialize, Serialize};
+
+use crate::audio::Audio;
+
+const SAMPLE_RATE: u32 = 44_100;
+/// Fraction of the usual volume while an alert is playing.
+const DUCK_FACTOR: f32 = 0.2;
+/// How long to duck for when the length of the alert is unknown.
+const DEFAULT_DUCK_DURATION: Duration = Duration::from_secs(2);
+
+/// A background sound which loops during task intervals.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum FocusSound {
+    WhiteNoise,
+    BrownNoise,
+    Rain,
+    Cafe,
+}
+
+impl fmt::Display for FocusSound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FocusSound::WhiteNoise => "White noise",
+            FocusSound::BrownNoise => "Brown noise",
+            FocusSound::Rain => "Rain",
+            FocusSound::Cafe => "Café",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FocusSound {
+    pub const ALL: [FocusSound; 4] = [
+        FocusSound::WhiteNoise,
+        FocusSound::BrownNoise,
+        FocusSound::Rain,
+        FocusSound::Cafe,
+    ];
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FocusSoundSettings {
+    /// The sound to play during tasks, if any.
+    pub sound: Option<FocusSound>,
+    /// Volume of each sound, from 0 to 1.
+    pub volumes: HashMap<FocusSound, f32>,
+}
+
+impl FocusSoundSettings {
+    pub fn volume(&self, sound: FocusSound) -> f32 {
+        self.volumes.get(&sound).copied().unwrap_or(0.5)
+    }
+}
+
+/// Endless synthesized noise, shaped to resemble the given sound.
+struct Noise {
+    sound: FocusSound,
+    /// State of the xorshift generator.
+    seed: u32,
+    /// Low-passed signal, used for the darker sounds.
+    low: f32,
+    /// Remaining amplitude of the current transient (drop, clink, etc.).
+    transient: f32,
+    /// Slowly varying loudness, to keep ambience from sounding static.
+    swell: f32,
+}
+
+impl Noise {
+    fn new(sound: FocusSound) -> Self {
+        Self {
+            sound,
+            seed: 0x9e37_79b9,
+            low: 0.,
+            transient: 0.,
+            swell: 0.5,
+        }
+    }
+
+    /// A uniformly distributed sample between -1 and 1.
+    fn white(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 * 2. - 1.
+    }
+
+    /// Returns true with the given probability per sample.
+    fn chance(&mut self, probability: f32) -> bool {
+        (self.white() + 1.) / 2. < probability
+    }
+}
+
+impl Iterator for Noise {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let white = self.white();
+
+        let sample = match self.sound {
+            FocusSound::WhiteNoise => white * 0.3,
+            FocusSound::BrownNoise => {
+                self.low = (self.low + white * 0.02).clamp(-1., 1.) * 0.998;
+                self.low * 1.5
+            }
+            FocusSound::Rain => {
+                self.low += (white - self.low) * 0.3;
+                if self.chance(0.0004) {
+                    self.transient = 0.3 + (self.white() + 1.) * 0.2;
+                }
+                self.transient *= 0.995;
+                self.low * 0.25 + white * self.transient
+            }
+            FocusSound::Cafe => {
+                // murmur: band-limited noise with a wandering loudness
+                self.low += (white - self.low) * 0.05;
+                self.swell =
+                    (self.swell + self.white() * 0.0005).clamp(0.2, 1.);
+                if self.chance(0.00005) {
+                    self.transient = 0.4;
+                }
+                self.transient *= 0.9995;
+                let clink = (self.transient * 40.).sin() * self.transient;
+                self.low * self.swell * 1.5 + clink * 0.1
+            }
+        };
+
+        Some(sample.clamp(-1., 1.))
+    }
+}
+
+impl Source for Noise {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Plays a single focus sound at a time on its own sink.
+#[derive(Default)]
+pub struct FocusSoundPlayer {
+    playing: Option<(FocusSound, Sink)>,
+    /// When to restore the volume after ducking for an alert.
+    ducked_until: Option<Instant>,
+}
+
+impl FocusSoundPlayer {
+    /// Lower the volume while an alert of the given length plays over it.
+    pub fn duck(&mut self, duration: Option<Duration>) {
+        let until = Instant::now() + duration.unwrap_or(DEFAULT_DUCK_DURATION);
+        self.ducked_until =
+            Some(self.ducked_until.map_or(until, |u| u.max(until)));
+    }
+
+    /// Time until the volume should be restored after ducking.
+    pub fn until_restore(&self) -> Option<Duration> {
+        self.ducked_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    /// Play the given sound at the given volume, or stop playback if `None`.
+    pub fn update(
+        &mut self,
+        audio: &mut Audio,
+        sound: Option<(FocusSound, f32)>,
+    ) -> crate::Result<()> {
+        if matches!(self.ducked_until, Some(until) if until <= Instant::now()) {
+            self.ducked_until = None;
+        }
+
+        let (sound, mut volume) = match sound {
+            Some(sound) => sound,
+            None => {
+                self.playing = None;
+                return Ok(());
+            }
+        };
+
+        if self.ducked_until.is_some() {
+            volume *= DUCK_FACTOR;
+        }
+
+        match &self.playing {
+            Some((playing, sink)) if *playing == sound && !sink.empty() => {
+                sink.set_volume(volume);
+            }
+            _ => {
+                let sink = Sink::try_new(audio.refresh()?)?;
+                sink.set_volume(volume);
+                sink.append(Noise::new(sound));
+                self.playing = Some((sound, sink));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Choose the background sound played during tasks, and its volume.
+pub fn settings_editor(ui: &mut egui::Ui, settings: &mut FocusSoundSettings) {
+    let selected = settings
+        .sound
+        .map_or_else(|| "Off".to_string(), |sound| sound.to_string());
+
+    egui::ComboBox::from_label("Sound")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut settings.sound, None, "Off");
+            for sound in FocusSound::ALL {
+                ui.selectable_value(
+                    &mut settings.sound,
+                    Some(sound),
+                    sound.to_string(),
+                );
+            }
+        });
+
+    if let Some(sound) = settings.sound {
+        let mut volume = settings.volume(sound);
+        if ui
+            .add(egui::Slider::new(&mut volume, 0.0..=1.0).text("Volume"))
+            .changed()
+        {
+            settings.volumes.insert(sound, volume);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise() {
+        for sound in FocusSound::ALL {
+            let samples: Vec<f32> = Noise::new(sound).take(44_100).collect();
+            assert!(samples.iter().all(|s| s.is_finite() && s.abs() <= 1.));
+            assert!(samples.iter().any(|s| *s != 0.));
+        }
+    }
+}
@@ -0,0 +1,1057 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Locale;
+use crate::score::DayStats;
+use crate::time_of_day::HourFormat;
+
+/// The directory in which history is stored.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("time-flo"))
+}
+
+/// A completed task interval.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Entry {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub task: String,
+    /// Whether the entry was added or edited by hand, rather than recorded.
+    #[serde(default)]
+    pub manual: bool,
+    #[serde(default)]
+    pub interruptions: Interruptions,
+    #[serde(default)]
+    pub status: EntryStatus,
+    /// Which applications were focused, most sampled first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applications: Vec<AppTime>,
+    /// How long the task was kept going past its end, in minutes.
+    #[serde(default)]
+    pub snoozed_minutes: u32,
+    /// What the user wrote down about the interval once it ended.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    /// The context the interval was labelled with, such as "Writing".
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub context: String,
+    /// When the interval was paused, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pauses: Vec<Pause>,
+}
+
+/// A time the task interval was paused for.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Pause {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+impl Pause {
+    /// A pause which lasted for the given time up to `end`.
+    pub fn until(end: DateTime<Local>, paused: std::time::Duration) -> Self {
+        let paused = chrono::Duration::from_std(paused)
+            .unwrap_or_else(|_| chrono::Duration::zero());
+        Self {
+            start: end - paused,
+            end,
+        }
+    }
+}
+
+/// What a stretch of a day's timeline was spent on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+    Task(EntryStatus),
+    Paused,
+    Break,
+    /// A gap between task intervals longer than a break.
+    Idle,
+}
+
+/// A stretch of a day's timeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub kind: SpanKind,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+/// How often an application was found focused during a task interval.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct AppTime {
+    pub name: String,
+    pub samples: u32,
+}
+
+/// How a task interval went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryStatus {
+    Completed,
+    /// Completed, but paused for longer than the pause budget allows.
+    Interrupted,
+    /// Abandoned, so not counted as completed.
+    Voided,
+}
+
+impl Default for EntryStatus {
+    fn default() -> Self {
+        EntryStatus::Completed
+    }
+}
+
+impl EntryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryStatus::Completed => "completed",
+            EntryStatus::Interrupted => "interrupted",
+            EntryStatus::Voided => "voided",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [
+            EntryStatus::Completed,
+            EntryStatus::Interrupted,
+            EntryStatus::Voided,
+        ]
+        .iter()
+        .copied()
+        .find(|status| status.as_str() == s)
+    }
+}
+
+/// Times a task interval was paused, and why.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Interruptions {
+    pub total: u32,
+    /// Pauses the user put down to their own distraction.
+    pub internal: u32,
+    /// Pauses the user put down to someone or something else.
+    pub external: u32,
+}
+
+/// Who an interruption came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptionKind {
+    /// The user's own distraction.
+    Internal,
+    /// Someone or something else.
+    External,
+}
+
+impl InterruptionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InterruptionKind::Internal => "internal",
+            InterruptionKind::External => "external",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [InterruptionKind::Internal, InterruptionKind::External]
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str() == s)
+    }
+}
+
+/// An interruption the user logged on pausing or abandoning a task interval.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LoggedInterruption {
+    pub at: DateTime<Local>,
+    pub kind: InterruptionKind,
+    /// Whether the task interval was abandoned, rather than paused.
+    #[serde(default)]
+    pub abandoned: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+impl Entry {
+    pub fn minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+
+    /// When the interval began, which is before its start by the time it
+    /// spent paused.
+    pub fn began(&self) -> DateTime<Local> {
+        self.pauses
+            .iter()
+            .fold(self.start, |began, pause| began - (pause.end - pause.start))
+    }
+
+    /// The share of samples each application took up, in percent.
+    pub fn application_shares(&self) -> Vec<(&str, u32)> {
+        let total: u32 = self.applications.iter().map(|app| app.samples).sum();
+        self.applications
+            .iter()
+            .map(|app| {
+                let share = (app.samples as f32 / total as f32 * 100.).round();
+                (app.name.as_str(), share as u32)
+            })
+            .collect()
+    }
+}
+
+/// All completed task intervals, oldest first.
+#[derive(Default, Deserialize, Serialize)]
+pub struct History {
+    entries: Vec<Entry>,
+    /// When task intervals were skipped before they ended.
+    #[serde(default)]
+    skipped: Vec<DateTime<Local>>,
+    /// Interruptions the user logged, oldest first.
+    #[serde(default)]
+    interruptions: Vec<LoggedInterruption>,
+}
+
+impl History {
+    /// Build a history from stored entries, skips, and interruptions, in any
+    /// order.
+    pub fn from_parts(
+        mut entries: Vec<Entry>,
+        skipped: Vec<DateTime<Local>>,
+        mut interruptions: Vec<LoggedInterruption>,
+    ) -> Self {
+        entries.sort_by_key(|entry| entry.start);
+        interruptions.sort_by_key(|interruption| interruption.at);
+        Self {
+            entries,
+            skipped,
+            interruptions,
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Add an entry, keeping entries in order.
+    pub fn add(&mut self, entry: Entry) {
+        let index = self.entries.partition_point(|e| e.start <= entry.start);
+        self.entries.insert(index, entry);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Entry {
+        self.entries.remove(index)
+    }
+
+    pub fn skipped(&self) -> &[DateTime<Local>] {
+        &self.skipped
+    }
+
+    pub fn record_skip(&mut self, at: DateTime<Local>) {
+        self.skipped.push(at);
+    }
+
+    pub fn interruptions(&self) -> &[LoggedInterruption] {
+        &self.interruptions
+    }
+
+    pub fn log_interruption(&mut self, interruption: LoggedInterruption) {
+        self.interruptions.push(interruption);
+    }
+
+    /// The interruptions logged during the day.
+    pub fn interruptions_on(
+        &self,
+        date: NaiveDate,
+    ) -> impl Iterator<Item = &LoggedInterruption> {
+        self.interruptions
+            .iter()
+            .filter(move |i| i.at.date().naive_local() == date)
+    }
+
+    /// The tasks worked on during the day, with the number of task intervals
+    /// completed for each, most first.
+    pub fn tasks_on(&self, date: NaiveDate) -> Vec<(&str, u32)> {
+        let mut tasks: Vec<(&str, u32)> = Vec::new();
+        for entry in self.entries.iter().filter(|e| {
+            e.end.date().naive_local() == date
+                && e.status != EntryStatus::Voided
+        }) {
+            let name = entry.task.trim();
+            match tasks.iter_mut().find(|(task, _)| *task == name) {
+                Some((_, count)) => *count += 1,
+                None => tasks.push((name, 1)),
+            }
+        }
+        tasks.sort_by(|a, b| b.1.cmp(&a.1));
+        tasks
+    }
+
+    /// Write down a note for the entry which started at the given time,
+    /// returning whether there was one.
+    pub fn set_note(&mut self, start: DateTime<Local>, note: &str) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.start == start) {
+            Some(entry) => {
+                entry.note = note.trim().to_owned();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Statistics for the task intervals which ended on the given day.
+    pub fn day_stats(&self, date: NaiveDate) -> DayStats {
+        self.day_stats_where(date, |_| true)
+    }
+
+    /// Statistics for the task intervals which ended on the given day,
+    /// counting only the entries included.
+    pub fn day_stats_where(
+        &self,
+        date: NaiveDate,
+        include: impl Fn(&Entry) -> bool,
+    ) -> DayStats {
+        let mut stats = DayStats::default();
+
+        for entry in self
+            .entries
+            .iter()
+            .filter(|e| e.end.date().naive_local() == date && include(e))
+        {
+            if entry.status == EntryStatus::Voided {
+                stats.voided += 1;
+            } else {
+                stats.completed += 1;
+                stats.minutes += entry.minutes();
+            }
+            stats.interruptions += entry.interruptions.total;
+            stats.internal_interruptions += entry.interruptions.internal;
+            stats.external_interruptions += entry.interruptions.external;
+        }
+        stats.skipped = self
+            .skipped
+            .iter()
+            .filter(|at| at.date().naive_local() == date)
+            .count() as u32;
+
+        stats
+    }
+
+    /// Number of times the context changed between the labelled task
+    /// intervals which ended on the given day.
+    pub fn context_switches(&self, date: NaiveDate) -> u32 {
+        let contexts: Vec<&str> = self
+            .entries
+            .iter()
+            .filter(|e| {
+                e.end.date().naive_local() == date
+                    && e.status != EntryStatus::Voided
+                    && !e.context.is_empty()
+            })
+            .map(|e| e.context.as_str())
+            .collect();
+        contexts
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .count() as u32
+    }
+
+    /// How the day went from the first task interval to the last, with gaps
+    /// of up to `max_break` between them counted as breaks.
+    pub fn timeline(
+        &self,
+        date: NaiveDate,
+        max_break: chrono::Duration,
+    ) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut last_end: Option<DateTime<Local>> = None;
+
+        for entry in self
+            .entries
+            .iter()
+            .filter(|e| e.end.date().naive_local() == date)
+        {
+            let began = entry.began();
+            if let Some(last_end) = last_end.filter(|&end| end < began) {
+                let kind = if began - last_end <= max_break {
+                    SpanKind::Break
+                } else {
+                    SpanKind::Idle
+                };
+                spans.push(Span {
+                    kind,
+                    start: last_end,
+                    end: began,
+                });
+            }
+
+            let mut at = began;
+            for pause in &entry.pauses {
+                if pause.start > at {
+                    spans.push(Span {
+                        kind: SpanKind::Task(entry.status),
+                        start: at,
+                        end: pause.start,
+                    });
+                }
+                spans.push(Span {
+                    kind: SpanKind::Paused,
+                    start: pause.start.max(at),
+                    end: pause.end,
+                });
+                at = at.max(pause.end);
+            }
+            if entry.end > at {
+                spans.push(Span {
+                    kind: SpanKind::Task(entry.status),
+                    start: at,
+                    end: entry.end,
+                });
+            }
+
+            last_end =
+                Some(last_end.map_or(entry.end, |end| end.max(entry.end)));
+        }
+
+        spans
+    }
+
+    /// Write the history as CSV, with manual entries flagged.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "start,end,minutes,task,manual,status")?;
+        for entry in &self.entries {
+            writeln!(
+                writer,
+                "{},{},{},\"{}\",{},{}",
+                entry.start.to_rfc3339(),
+                entry.end.to_rfc3339(),
+                entry.minutes(),
+                entry.task.replace('"', "\"\""),
+                entry.manual,
+                entry.status.as_str()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write a Markdown summary of the task intervals which ended on the given
+    /// day, with their notes.
+    pub fn write_markdown(
+        &self,
+        date: NaiveDate,
+        mut writer: impl Write,
+    ) -> io::Result<()> {
+        let stats = self.day_stats(date);
+        writeln!(writer, "# {}", date.format("%Y-%m-%d"))?;
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{} intervals completed, {} minutes of focus.",
+            stats.completed, stats.minutes
+        )?;
+        writeln!(writer)?;
+
+        for entry in self
+            .entries
+            .iter()
+            .filter(|e| e.end.date().naive_local() == date)
+        {
+            let task = match entry.task.trim() {
+                "" => "Untitled task",
+                task => task,
+            };
+            write!(
+                writer,
+                "- {}–{} **{}**",
+                entry.start.format("%H:%M"),
+                entry.end.format("%H:%M"),
+                task
+            )?;
+            if entry.status == EntryStatus::Voided {
+                write!(writer, " (voided)")?;
+            }
+            if !entry.note.is_empty() {
+                write!(writer, ": {}", entry.note)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Export a Markdown summary of the given day next to the history file.
+    pub fn export_markdown(&self, date: NaiveDate) -> crate::Result<PathBuf> {
+        let path = data_dir()
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no data directory")
+            })?
+            .join("summaries")
+            .join(format!("{}.md", date.format("%Y-%m-%d")));
+
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut writer = io::BufWriter::new(fs::File::create(&path)?);
+        self.write_markdown(date, &mut writer)?;
+        writer.flush()?;
+        Ok(path)
+    }
+
+    /// Export the history as CSV next to the history file.
+    pub fn export_csv(&self) -> crate::Result<PathBuf> {
+        let path = data_dir()
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no data directory")
+            })?
+            .join("history.csv");
+
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut writer = io::BufWriter::new(fs::File::create(&path)?);
+        self.write_csv(&mut writer)?;
+        writer.flush()?;
+        Ok(path)
+    }
+}
+
+/// An entry being added or edited by hand, as entered by the user.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntryDraft {
+    /// The date, as `YYYY-MM-DD`.
+    pub date: String,
+    /// The start time, as `HH:MM`.
+    pub start: String,
+    /// The end time, as `HH:MM`.
+    pub end: String,
+    pub task: String,
+}
+
+impl EntryDraft {
+    /// A draft for an entry today.
+    pub fn today() -> Self {
+        Self {
+            date: Local::today().format("%Y-%m-%d").to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn parse(&self) -> Result<Entry, String> {
+        let date = NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d")
+            .map_err(|_| "Date must be in the form YYYY-MM-DD")?;
+        let time = |s: &str| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M")
+                .map_err(|_| "Times must be in the form HH:MM")
+        };
+        let local = |time| {
+            Local
+                .from_local_datetime(&date.and_time(time))
+                .single()
+                .ok_or("That time does not exist in the local time zone")
+        };
+
+        let start = local(time(&self.start)?)?;
+        let end = local(time(&self.end)?)?;
+        if end <= start {
+            return Err("The end time must be after the start time".into());
+        }
+
+        Ok(Entry {
+            start,
+            end,
+            task: self.task.trim().to_owned(),
+            manual: true,
+            interruptions: Interruptions::default(),
+            status: EntryStatus::default(),
+            applications: Vec::new(),
+            snoozed_minutes: 0,
+            note: String::new(),
+            context: String::new(),
+            pauses: Vec::new(),
+        })
+    }
+}
+
+impl From<&Entry> for EntryDraft {
+    fn from(entry: &Entry) -> Self {
+        Self {
+            date: entry.start.format("%Y-%m-%d").to_string(),
+            start: entry.start.format("%H:%M").to_string(),
+            end: entry.end.format("%H:%M").to_string(),
+            task: entry.task.clone(),
+        }
+    }
+}
+
+/// A recorded task interval in full, including which applications were
+/// focused during it.
+pub fn entry_details(
+    ui: &mut egui::Ui,
+    entry: &Entry,
+    locale: Locale,
+    hour_format: HourFormat,
+) {
+    ui.label(format!(
+        "{} {} to {}",
+        locale.format_date(entry.start.date().naive_local()),
+        hour_format.format(entry.start.time()),
+        hour_format.format(entry.end.time())
+    ));
+    let interruptions = entry.interruptions;
+    ui.label(format!(
+        "Paused {} times ({} distractions, {} by someone else)",
+        interruptions.total, interruptions.internal, interruptions.external
+    ));
+    if entry.snoozed_minutes > 0 {
+        ui.label(format!(
+            "Snoozed for {}",
+            locale.format_minutes(entry.snoozed_minutes as i64)
+        ));
+    }
+    if !entry.note.is_empty() {
+        ui.label(format!("Accomplished: {}", entry.note));
+    }
+    if !entry.context.is_empty() {
+        ui.label(format!("Context: {}", entry.context));
+    }
+
+    if entry.applications.is_empty() {
+        ui.add(egui::Label::new("No applications recorded.").small());
+        return;
+    }
+
+    egui::Grid::new("entry_applications").show(ui, |ui| {
+        for (name, share) in entry.application_shares() {
+            ui.label(name);
+            ui.add(
+                egui::ProgressBar::new(share as f32 / 100.)
+                    .desired_width(120.)
+                    .text(format!("{}%", share)),
+            );
+            ui.end_row();
+        }
+    });
+}
+
+/// Internal and external interruptions over the last week, as stacked bars.
+pub fn interruption_chart(ui: &mut egui::Ui, history: &History) {
+    const DAYS: i64 = 7;
+    const BAR_WIDTH: f32 = 12.;
+    const HEIGHT: f32 = 30.;
+
+    let today = Local::today().naive_local();
+    let days: Vec<_> = (0..DAYS)
+        .rev()
+        .map(|i| history.day_stats(today - chrono::Duration::days(i)))
+        .collect();
+
+    let most = days
+        .iter()
+        .map(|day| day.internal_interruptions + day.external_interruptions)
+        .max()
+        .unwrap_or(0);
+    if most == 0 {
+        return;
+    }
+
+    let internal_color = Color32::from_rgb(0xe0, 0x8a, 0x3c);
+    let external_color = Color32::from_rgb(0x4a, 0x90, 0xd9);
+
+    ui.horizontal(|ui| {
+        let spacing = ui.spacing().item_spacing.x;
+        let size = egui::vec2(DAYS as f32 * (BAR_WIDTH + spacing), HEIGHT);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter();
+        let scale = HEIGHT / most as f32;
+
+        for (i, day) in days.iter().enumerate() {
+            let left = rect.left() + i as f32 * (BAR_WIDTH + spacing);
+            let internal = day.internal_interruptions as f32 * scale;
+            let external = day.external_interruptions as f32 * scale;
+
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - internal),
+                    egui::pos2(left + BAR_WIDTH, rect.bottom()),
+                ),
+                0.,
+                internal_color,
+            );
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - internal - external),
+                    egui::pos2(left + BAR_WIDTH, rect.bottom() - internal),
+                ),
+                0.,
+                external_color,
+            );
+        }
+
+        ui.vertical(|ui| {
+            ui.add(
+                egui::Label::new("\u{25A0} distractions")
+                    .small()
+                    .text_color(internal_color),
+            );
+            ui.add(
+                egui::Label::new("\u{25A0} someone else")
+                    .small()
+                    .text_color(external_color),
+            );
+        });
+    });
+}
+
+/// Context switches a day over the last week, as bars.
+pub fn context_switch_chart(ui: &mut egui::Ui, history: &History) {
+    const DAYS: i64 = 7;
+    const BAR_WIDTH: f32 = 12.;
+    const HEIGHT: f32 = 30.;
+
+    let today = Local::today().naive_local();
+    let days: Vec<_> = (0..DAYS)
+        .rev()
+        .map(|i| history.context_switches(today - chrono::Duration::days(i)))
+        .collect();
+
+    let most = days.iter().copied().max().unwrap_or(0);
+    if most == 0 {
+        return;
+    }
+
+    let color = Color32::from_rgb(0x9a, 0xc8, 0x4a);
+    let spacing = ui.spacing().item_spacing.x;
+    let size = egui::vec2(DAYS as f32 * (BAR_WIDTH + spacing), HEIGHT);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter();
+    let scale = HEIGHT / most as f32;
+
+    for (i, switches) in days.iter().enumerate() {
+        let left = rect.left() + i as f32 * (BAR_WIDTH + spacing);
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(left, rect.bottom() - *switches as f32 * scale),
+                egui::pos2(left + BAR_WIDTH, rect.bottom()),
+            ),
+            0.,
+            color,
+        );
+    }
+}
+
+/// The color a kind of span is shown in on the timeline.
+fn span_color(kind: SpanKind) -> Color32 {
+    match kind {
+        SpanKind::Task(EntryStatus::Completed) => {
+            Color32::from_rgb(0x4a, 0x90, 0xd9)
+        }
+        SpanKind::Task(EntryStatus::Interrupted) => {
+            Color32::from_rgb(0x2e, 0x5c, 0x8a)
+        }
+        SpanKind::Task(EntryStatus::Voided) => Color32::from_gray(0x80),
+        SpanKind::Paused => Color32::from_rgb(0xe0, 0x8a, 0x3c),
+        SpanKind::Break => Color32::from_rgb(0x9a, 0xc8, 0x4a),
+        SpanKind::Idle => Color32::from_gray(0x40),
+    }
+}
+
+/// A day from its first task to its last as one bar, colored by what each
+/// stretch of it was spent on.
+pub fn timeline(ui: &mut egui::Ui, spans: &[Span], hour_format: HourFormat) {
+    const HEIGHT: f32 = 20.;
+
+    let (first, last) = match (
+        spans.iter().map(|span| span.start).min(),
+        spans.iter().map(|span| span.end).max(),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            ui.add(egui::Label::new("Nothing recorded on this day.").small());
+            return;
+        }
+    };
+
+    let total = (last - first).num_seconds().max(1) as f32;
+    let size = egui::vec2(ui.available_width(), HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let x = |at: DateTime<Local>| {
+        rect.left() + (at - first).num_seconds() as f32 / total * rect.width()
+    };
+
+    let painter = ui.painter();
+    for span in spans {
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(x(span.start), rect.top()),
+                egui::pos2(x(span.end), rect.bottom()),
+            ),
+            0.,
+            span_color(span.kind),
+        );
+    }
+
+    // say what the stretch under the pointer was
+    let hovered = response.hover_pos().and_then(|pos| {
+        spans
+            .iter()
+            .find(|span| x(span.start) <= pos.x && pos.x <= x(span.end))
+    });
+    if let Some(span) = hovered {
+        let label = match span.kind {
+            SpanKind::Task(EntryStatus::Voided) => "Voided task",
+            SpanKind::Task(_) => "Task",
+            SpanKind::Paused => "Paused",
+            SpanKind::Break => "Break",
+            SpanKind::Idle => "Idle",
+        };
+        response.on_hover_text(format!(
+            "{} from {} to {}",
+            label,
+            hour_format.format(span.start.time()),
+            hour_format.format(span.end.time())
+        ));
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::Label::new(hour_format.format(first.time()))
+                .small()
+                .weak(),
+        );
+        for (kind, label) in [
+            (SpanKind::Task(EntryStatus::Completed), "Task"),
+            (SpanKind::Paused, "Paused"),
+            (SpanKind::Break, "Break"),
+            (SpanKind::Idle, "Idle"),
+        ] {
+            ui.add(
+                egui::Label::new(format!("\u{25A0} {}", label))
+                    .small()
+                    .text_color(span_color(kind)),
+            );
+        }
+        ui.add(
+            egui::Label::new(hour_format.format(last.time()))
+                .small()
+                .weak(),
+        );
+    });
+}
+
+/// The interruptions logged today, with whatever was noted about them.
+pub fn logged_interruptions(
+    ui: &mut egui::Ui,
+    history: &History,
+    hour_format: HourFormat,
+) {
+    let today = Local::today().naive_local();
+    let logged: Vec<_> = history.interruptions_on(today).collect();
+    if logged.is_empty() {
+        return;
+    }
+
+    let abandoned = logged.iter().filter(|i| i.abandoned).count();
+    ui.collapsing(
+        format!(
+            "Interruptions logged today: {} ({} abandoned)",
+            logged.len(),
+            abandoned
+        ),
+        |ui| {
+            egui::Grid::new("logged_interruptions").show(ui, |ui| {
+                for interruption in logged {
+                    ui.label(hour_format.format(interruption.at.time()));
+                    ui.label(match interruption.kind {
+                        InterruptionKind::Internal => "Distraction",
+                        InterruptionKind::External => "Someone else",
+                    });
+                    ui.label(if interruption.abandoned {
+                        "Abandoned"
+                    } else {
+                        "Paused"
+                    });
+                    ui.label(&interruption.note);
+                    ui.end_row();
+                }
+            });
+        },
+    );
+}
+
+/// Choose the context of the current work from the configured set.
+pub fn context_selector(
+    ui: &mut egui::Ui,
+    context: &mut String,
+    contexts: &[String],
+) {
+    if contexts.is_empty() {
+        return;
+    }
+
+    let selected = if context.is_empty() {
+        "None"
+    } else {
+        context.as_str()
+    };
+    egui::ComboBox::from_label("Context")
+        .selected_text(selected.to_owned())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(context, String::new(), "None");
+            for label in contexts {
+                ui.selectable_value(context, label.clone(), label);
+            }
+        });
+}
+
+/// Edit the contexts intervals can be labelled with.
+pub fn contexts_editor(ui: &mut egui::Ui, contexts: &mut Vec<String>) {
+    let mut removed = None;
+
+    egui::Grid::new("contexts").show(ui, |ui| {
+        for (i, context) in contexts.iter_mut().enumerate() {
+            ui.add(
+                egui::TextEdit::singleline(context)
+                    .hint_text("Context")
+                    .desired_width(120.),
+            );
+            if ui.button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(i) = removed {
+        contexts.remove(i);
+    }
+
+    if ui.button("Add context").clicked() {
+        contexts.push(String::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let draft = EntryDraft {
+            date: "2021-11-02".into(),
+            start: "14:00".into(),
+            end: "14:50".into(),
+            task: " Offline reading ".into(),
+        };
+        let mut entry = draft.parse().unwrap();
+        assert_eq!(50, entry.minutes());
+        assert_eq!("Offline reading", entry.task);
+        assert!(entry.manual);
+        assert_eq!("14:00", EntryDraft::from(&entry).start);
+
+        entry.applications = vec![
+            AppTime {
+                name: "code".into(),
+                samples: 2,
+            },
+            AppTime {
+                name: "firefox".into(),
+                samples: 1,
+            },
+        ];
+        assert_eq!(
+            vec![("code", 67), ("firefox", 33)],
+            entry.application_shares()
+        );
+
+        let backwards = EntryDraft {
+            end: "13:00".into(),
+            ..draft.clone()
+        };
+        assert!(backwards.parse().is_err());
+        assert!(EntryDraft::default().parse().is_err());
+
+        // entries are kept in order
+        let mut history = History::default();
+        let earlier = EntryDraft {
+            start: "09:00".into(),
+            end: "09:25".into(),
+            task: "Say \"hi\"".into(),
+            ..draft
+        };
+        history.add(entry);
+        history.add(earlier.parse().unwrap());
+        assert_eq!(25, history.entries()[0].minutes());
+
+        let mut csv = Vec::new();
+        history.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(3, csv.lines().count());
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",25,\"Say \"\"hi\"\"\",true,completed"));
+
+        let start = history.entries()[1].start;
+        let date = start.date().naive_local();
+        assert_eq!(
+            vec![("Say \"hi\"", 1), ("Offline reading", 1)],
+            history.tasks_on(date)
+        );
+
+        assert_eq!(0, history.context_switches(date));
+        let mut labelled = history.entries()[0].clone();
+        for context in ["Writing", "Coding", "Coding", "", "Writing"] {
+            labelled.context = context.to_owned();
+            history.add(labelled.clone());
+        }
+        assert_eq!(2, history.context_switches(date));
+
+        assert!(history.set_note(start, " Finished the chapter "));
+        let mut markdown = Vec::new();
+        history.write_markdown(date, &mut markdown).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        assert!(markdown.starts_with("# 2021-11-02\n"));
+        assert!(markdown.contains(
+            "- 14:00–14:50 **Offline reading**: Finished the chapter\n"
+        ));
+
+        // the timeline fills the gaps between tasks with breaks or idle time
+        let entry = |start: &str, end: &str| {
+            EntryDraft {
+                date: "2021-11-02".into(),
+                start: start.into(),
+                end: end.into(),
+                task: "Offline reading".into(),
+            }
+            .parse()
+            .unwrap()
+        };
+        let mut paused = entry("09:05", "09:25");
+        let at = |time: &str| entry(time, "23:59").start;
+        paused.pauses = vec![Pause {
+            start: at("09:10"),
+            end: at("09:15"),
+        }];
+        let mut history = History::default();
+        history.add(paused);
+        history.add(entry("09:35", "10:00"));
+        history.add(entry("11:00", "11:25"));
+
+        let timeline = history.timeline(date, chrono::Duration::minutes(20));
+        let kinds: Vec<_> = timeline.iter().map(|span| span.kind).collect();
+        let task = SpanKind::Task(EntryStatus::Completed);
+        assert_eq!(
+            vec![
+                task,
+                SpanKind::Paused,
+                task,
+                SpanKind::Break,
+                task,
+                SpanKind::Idle,
+                task
+            ],
+            kinds
+        );
+        assert_eq!(at("09:00"), timeline[0].start);
+        assert_eq!(at("09:10"), timeline[0].end);
+        assert_eq!(at("11:25"), timeline[6].end);
+    }
+}
@@ -0,0 +1,188 @@
+//! Shell commands run on state transitions, for scripting integrations which
+//! TimeFlo has no settings of its own for.
+
+use std::io;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+/// Commands to run through the shell, left empty to run nothing.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// Run when a task interval starts.
+    pub on_task_start: String,
+    /// Run when a short or long break starts.
+    pub on_break_start: String,
+    /// Run when an interval which was started ends, by running out or being
+    /// skipped.
+    pub on_interval_end: String,
+}
+
+/// A transition hooks can be run on.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum HookEvent {
+    Start,
+    End,
+}
+
+impl Hooks {
+    /// The command to run for the given transition of an interval in the
+    /// given state.
+    pub fn command(&self, event: HookEvent, state: State) -> &str {
+        let command = match event {
+            HookEvent::Start if state == State::Task => {
+                self.on_task_start.as_str()
+            }
+            HookEvent::Start if state.is_break() => {
+                self.on_break_start.as_str()
+            }
+            HookEvent::Start => "",
+            HookEvent::End => self.on_interval_end.as_str(),
+        };
+        command.trim()
+    }
+}
+
+/// What a hook is told about the interval through its environment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HookContext {
+    pub event: HookEvent,
+    pub state: State,
+    /// The planned length of the interval when it starts, or the time spent
+    /// in it when it ends. Planned lengths are unknown when counting up.
+    pub duration: Option<Duration>,
+    pub task: Option<String>,
+    /// Whether the interval ran to the end rather than being skipped.
+    pub completed: bool,
+}
+
+impl HookContext {
+    /// The environment variables to run a hook with.
+    pub fn environment(&self) -> Vec<(&'static str, String)> {
+        let event = match self.event {
+            HookEvent::Start => "start",
+            HookEvent::End => "end",
+        };
+        let state = match self.state {
+            State::Idle => "idle",
+            State::Task => "task",
+            State::ShortBreak => "short_break",
+            State::LongBreak => "long_break",
+        };
+
+        vec![
+            ("TIMEFLO_EVENT", event.to_owned()),
+            ("TIMEFLO_STATE", state.to_owned()),
+            (
+                "TIMEFLO_DURATION",
+                self.duration
+                    .map(|duration| duration.as_secs().to_string())
+                    .unwrap_or_default(),
+            ),
+            ("TIMEFLO_TASK", self.task.clone().unwrap_or_default()),
+            ("TIMEFLO_COMPLETED", self.completed.to_string()),
+        ]
+    }
+}
+
+/// The platform shell, ready to be given a command.
+fn shell() -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    }
+}
+
+/// Run a hook through the shell without waiting for it, warning if it fails.
+pub fn run(command: &str, context: &HookContext) -> io::Result<()> {
+    let mut child = shell().arg(command).envs(context.environment()).spawn()?;
+
+    // reap the hook once it exits, so slow hooks never hold up the timer
+    let command = command.to_owned();
+    thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            warn!("Hook {:?} exited with {}", command, status)
+        }
+        Ok(_) => {}
+        Err(err) => warn!("Could not wait for hook {:?}: {:?}", command, err),
+    });
+
+    Ok(())
+}
+
+/// Edit the shell commands run on state transitions.
+pub fn editor(ui: &mut egui::Ui, hooks: &mut Hooks) {
+    egui::Grid::new("hooks").show(ui, |ui| {
+        for (label, command) in [
+            ("When a task starts", &mut hooks.on_task_start),
+            ("When a break starts", &mut hooks.on_break_start),
+            ("When an interval ends", &mut hooks.on_interval_end),
+        ] {
+            ui.label(label);
+            ui.add(
+                egui::TextEdit::singleline(command).hint_text("Shell command"),
+            );
+            ui.end_row();
+        }
+    });
+    ui.add(
+        egui::Label::new(
+            "Hooks see $TIMEFLO_EVENT, $TIMEFLO_STATE, $TIMEFLO_DURATION, \
+             $TIMEFLO_TASK and $TIMEFLO_COMPLETED",
+        )
+        .small(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment() {
+        let hooks = Hooks {
+            on_task_start: "notify-send start".to_owned(),
+            on_break_start: " ".to_owned(),
+            on_interval_end: "notify-send end".to_owned(),
+        };
+        assert_eq!(
+            "notify-send start",
+            hooks.command(HookEvent::Start, State::Task)
+        );
+        assert_eq!("", hooks.command(HookEvent::Start, State::LongBreak));
+        assert_eq!(
+            "notify-send end",
+            hooks.command(HookEvent::End, State::ShortBreak)
+        );
+
+        let context = HookContext {
+            event: HookEvent::End,
+            state: State::ShortBreak,
+            duration: Some(Duration::from_secs(300)),
+            task: None,
+            completed: true,
+        };
+        assert_eq!(
+            vec![
+                ("TIMEFLO_EVENT", "end".to_owned()),
+                ("TIMEFLO_STATE", "short_break".to_owned()),
+                ("TIMEFLO_DURATION", "300".to_owned()),
+                ("TIMEFLO_TASK", String::new()),
+                ("TIMEFLO_COMPLETED", "true".to_owned()),
+            ],
+            context.environment()
+        );
+    }
+}
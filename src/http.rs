@@ -0,0 +1,869 @@
+//! A small HTTP server for following the timer from other devices, such as
+//! an accountability partner's browser, and for controlling it with an API
+//! token.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::ipc::{Command, Snapshot, Status};
+#[cfg(feature = "mdns")]
+use crate::mdns::Advertisement;
+use crate::metrics::Metrics;
+use crate::timer::Timer;
+use crate::State;
+
+/// How often the listener checks whether the server has been dropped.
+const ACCEPT_POLL: Duration = Duration::from_millis(200);
+/// How long to wait on a client before giving up on its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// The longest request or header line accepted, in bytes.
+const MAX_LINE_BYTES: usize = 8 * 1024;
+/// The most headers accepted in a request.
+const MAX_HEADERS: usize = 64;
+
+/// The remote control page, which talks to the API.
+const REMOTE_PAGE: &str = include_str!("../resources/remote.html");
+
+/// How often the shared page reloads itself, in seconds.
+const PAGE_REFRESH_SECS: u32 = 15;
+
+const OK: &str = "200 OK";
+const BAD_REQUEST: &str = "400 Bad Request";
+const UNAUTHORIZED: &str = "401 Unauthorized";
+const FORBIDDEN: &str = "403 Forbidden";
+const NOT_FOUND: &str = "404 Not Found";
+const SERVER_ERROR: &str = "500 Internal Server Error";
+
+/// What an API token allows.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum Permission {
+    /// Reading the status only.
+    ReadOnly,
+    /// Reading the status, and starting, pausing, and skipping intervals.
+    Control,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 2] =
+        [Permission::ReadOnly, Permission::Control];
+}
+
+impl Default for Permission {
+    fn default() -> Self {
+        Permission::ReadOnly
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Permission::ReadOnly => "Read only",
+            Permission::Control => "Control",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A token for the API, sent as `Authorization: Bearer <token>`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApiToken {
+    /// What the token is used for, such as "Phone".
+    pub name: String,
+    pub token: String,
+    pub permission: Permission,
+}
+
+impl ApiToken {
+    /// A new read-only token.
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            token: new_token(),
+            permission: Permission::default(),
+        }
+    }
+}
+
+/// Whether and where to serve the timer, and who may control it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    #[serde(rename = "http_server")]
+    pub enabled: bool,
+    /// The address the server listens on.
+    #[serde(rename = "http_address")]
+    pub address: String,
+    #[serde(rename = "http_port")]
+    pub port: u16,
+    /// How long share links last, in hours.
+    pub share_hours: f32,
+    /// Tokens for controlling the timer.
+    pub api_tokens: Vec<ApiToken>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1".to_owned(),
+            port: 7315,
+            share_hours: 4.,
+            api_tokens: Vec::new(),
+        }
+    }
+}
+
+/// A temporary, read-only link to the timer.
+#[derive(Clone, Debug)]
+pub struct ShareLink {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+impl ShareLink {
+    fn is_valid(&self, token: &str) -> bool {
+        tokens_match(&self.token, token) && clock::now() < self.expires_at
+    }
+}
+
+/// State shared between the application and the connection threads.
+struct Shared {
+    snapshot: Mutex<Snapshot>,
+    /// Totals from the history, for the metrics endpoint.
+    metrics: Mutex<Metrics>,
+    share: Mutex<Option<ShareLink>>,
+    tokens: Mutex<Vec<ApiToken>>,
+    commands: Mutex<Sender<Command>>,
+    /// Wakes the application up to carry out commands.
+    wake: Box<dyn Fn() + Send + Sync>,
+}
+
+impl Shared {
+    fn new(
+        commands: Sender<Command>,
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            snapshot: Mutex::default(),
+            metrics: Mutex::default(),
+            share: Mutex::default(),
+            tokens: Mutex::default(),
+            commands: Mutex::new(commands),
+            wake: Box::new(wake),
+        }
+    }
+
+    /// What the given API token allows, if it is known.
+    fn permission(&self, token: &str) -> Option<Permission> {
+        let tokens = self.tokens.lock().ok()?;
+        tokens
+            .iter()
+            .find(|t| !t.token.is_empty() && tokens_match(&t.token, token))
+            .map(|t| t.permission)
+    }
+}
+
+/// Serves the timer over HTTP until dropped.
+pub struct HttpServer {
+    shared: Arc<Shared>,
+    commands: Receiver<Command>,
+    address: String,
+    port: u16,
+    /// Announces the server on the local network.
+    #[cfg(feature = "mdns")]
+    advertisement: Option<Advertisement>,
+}
+
+impl HttpServer {
+    /// Start listening, calling `wake` whenever a command is received.
+    pub fn start(
+        address: &str,
+        port: u16,
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> crate::Result<Self> {
+        let listener = TcpListener::bind((address, port))?;
+        // poll, so that the thread notices when the server is dropped
+        listener.set_nonblocking(true)?;
+
+        let (sender, commands) = mpsc::channel();
+        let shared = Arc::new(Shared::new(sender, wake));
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || accept(listener, weak));
+
+        Ok(Self {
+            shared,
+            commands,
+            address: address.to_owned(),
+            port,
+            #[cfg(feature = "mdns")]
+            advertisement: None,
+        })
+    }
+
+    /// Announce the server on the local network with mDNS, unless it is
+    /// only listening on this machine.
+    #[cfg(feature = "mdns")]
+    pub fn advertise(&mut self) -> crate::Result<()> {
+        let ip = match self.address.parse::<IpAddr>() {
+            Ok(ip) if ip.is_loopback() => return Ok(()),
+            Ok(ip) if ip.is_unspecified() => match local_ip() {
+                Some(ip) => ip,
+                None => return Ok(()),
+            },
+            _ => self.address.clone(),
+        };
+
+        self.advertisement = Some(Advertisement::start(&ip, self.port)?);
+        Ok(())
+    }
+
+    /// Whether the server is listening on the given address and port.
+    pub fn is_listening_on(&self, address: &str, port: u16) -> bool {
+        self.address == address && self.port == port
+    }
+
+    /// Update the state shown to visitors.
+    pub fn update(&self, snapshot: Snapshot) {
+        if let Ok(mut shared) = self.shared.snapshot.lock() {
+            *shared = snapshot;
+        }
+    }
+
+    /// Update the totals given by the metrics endpoint.
+    pub fn update_metrics(&self, metrics: Metrics) {
+        if let Ok(mut shared) = self.shared.metrics.lock() {
+            *shared = metrics;
+        }
+    }
+
+    /// Replace the tokens accepted by the API.
+    pub fn set_tokens(&self, tokens: Vec<ApiToken>) {
+        if let Ok(mut shared) = self.shared.tokens.lock() {
+            *shared = tokens;
+        }
+    }
+
+    /// Take the commands received since the last call.
+    pub fn take_commands(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+
+    /// Create a new share link lasting for the given time, replacing any
+    /// previous one.
+    pub fn share(&self, duration: Duration) -> ShareLink {
+        let link = ShareLink {
+            token: new_token(),
+            expires_at: clock::now() + duration,
+        };
+        if let Ok(mut share) = self.shared.share.lock() {
+            *share = Some(link.clone());
+        }
+        link
+    }
+
+    /// The current share link, if it has not expired.
+    pub fn share_link(&self) -> Option<ShareLink> {
+        let share = self.shared.share.lock().ok()?;
+        share.clone().filter(|link| clock::now() < link.expires_at)
+    }
+
+    pub fn stop_sharing(&self) {
+        if let Ok(mut share) = self.shared.share.lock() {
+            *share = None;
+        }
+    }
+
+    /// The address other machines can reach the server at.
+    fn base_url(&self) -> String {
+        let host = match self.address.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => {
+                local_ip().unwrap_or_else(|| "localhost".to_owned())
+            }
+            _ => self.address.clone(),
+        };
+        format!("http://{}:{}", host, self.port)
+    }
+
+    /// The address to give out for the given share link.
+    pub fn share_url(&self, link: &ShareLink) -> String {
+        format!("{}/share/{}", self.base_url(), link.token)
+    }
+
+    /// The address of the remote control page, using the given token.
+    pub fn remote_url(&self, token: &ApiToken) -> String {
+        format!("{}/#token={}", self.base_url(), token.token)
+    }
+}
+
+/// A random token which is impractical to guess, from the operating system's
+/// random number generator.
+fn new_token() -> String {
+    let mut bytes = [0; 16];
+    getrandom::getrandom(&mut bytes).expect("no random number generator");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Compare tokens in a time which does not depend on where they differ, so
+/// that they cannot be guessed a character at a time.
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The address of this machine on the local network, as other machines
+/// would reach it. Nothing is actually sent.
+fn local_ip() -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
+
+fn accept(listener: TcpListener, shared: Weak<Shared>) {
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                if shared.strong_count() == 0 {
+                    return;
+                }
+                thread::sleep(ACCEPT_POLL);
+                continue;
+            }
+            Err(err) => {
+                warn!("Could not accept HTTP connection: {:?}", err);
+                continue;
+            }
+        };
+
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => return,
+        };
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream, &shared) {
+                warn!("Could not handle HTTP connection: {:?}", err);
+            }
+        });
+    }
+}
+
+/// The parts of an HTTP request which are looked at.
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    /// The bearer token from the `Authorization` header.
+    token: Option<String>,
+}
+
+/// A response to an HTTP request.
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn html(body: String) -> Self {
+        Self {
+            status: OK,
+            content_type: "text/html; charset=utf-8",
+            body,
+        }
+    }
+
+    fn json(body: String) -> Self {
+        Self {
+            status: OK,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", status),
+        }
+    }
+}
+
+/// Read a line, failing on ones longer than any request should have.
+fn read_line(
+    reader: &mut impl BufRead,
+    line: &mut String,
+) -> io::Result<usize> {
+    let read = reader
+        .by_ref()
+        .take(MAX_LINE_BYTES as u64)
+        .read_line(line)?;
+    if read == MAX_LINE_BYTES && !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request line too long",
+        ));
+    }
+    Ok(read)
+}
+
+fn handle_connection(stream: TcpStream, shared: &Shared) -> crate::Result<()> {
+    stream.set_nonblocking(false)?;
+    // clients which stall are dropped rather than holding on to a thread
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let mut request = Request {
+        method: parts.next().unwrap_or_default().to_owned(),
+        path: parts.next().unwrap_or_default().to_owned(),
+        token: None,
+    };
+
+    let mut header = String::new();
+    let mut headers = 0;
+    while read_line(&mut reader, &mut header)? > 0 && !header.trim().is_empty()
+    {
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many headers",
+            )
+            .into());
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                request.token = value
+                    .trim()
+                    .strip_prefix("Bearer ")
+                    .map(|token| token.trim().to_owned());
+            }
+        }
+        header.clear();
+    }
+
+    let response = if request.path.is_empty() {
+        Response::error(BAD_REQUEST)
+    } else {
+        respond(&request, shared)
+    };
+
+    let mut writer = stream;
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+
+    Ok(())
+}
+
+fn respond(request: &Request, shared: &Shared) -> Response {
+    let path = request.path.split('?').next().unwrap_or_default();
+
+    if let Some(endpoint) = path.strip_prefix("/api/") {
+        return api(request, endpoint, shared);
+    }
+
+    if request.method != "GET" {
+        return Response::error(NOT_FOUND);
+    }
+    if path == "/metrics" {
+        return metrics(request, shared);
+    }
+    if path == "/" {
+        return Response::html(REMOTE_PAGE.to_owned());
+    }
+    match path.strip_prefix("/share/") {
+        Some(token) => share(token, shared),
+        None => Response::error(NOT_FOUND),
+    }
+}
+
+/// The page for a share link.
+fn share(token: &str, shared: &Shared) -> Response {
+    let valid = match shared.share.lock() {
+        Ok(share) => share.as_ref().map_or(false, |s| s.is_valid(token)),
+        Err(_) => false,
+    };
+    // don't tell expired links apart from ones which never existed
+    if !valid {
+        return Response::error(NOT_FOUND);
+    }
+
+    match shared.snapshot.lock() {
+        Ok(snapshot) => Response::html(share_page(&snapshot.status())),
+        Err(_) => Response::error(SERVER_ERROR),
+    }
+}
+
+/// What the request's token allows, if it has a known one.
+fn authorize(request: &Request, shared: &Shared) -> Option<Permission> {
+    shared.permission(request.token.as_deref().unwrap_or_default())
+}
+
+/// Metrics for Prometheus, which needs a token like the API.
+fn metrics(request: &Request, shared: &Shared) -> Response {
+    if authorize(request, shared).is_none() {
+        return Response::error(UNAUTHORIZED);
+    }
+
+    let (metrics, status) =
+        match (shared.metrics.lock(), shared.snapshot.lock()) {
+            (Ok(metrics), Ok(snapshot)) => (metrics.clone(), snapshot.status()),
+            _ => return Response::error(SERVER_ERROR),
+        };
+    let metrics = Metrics {
+        state: status.state,
+        remaining_secs: status.remaining_secs,
+        paused: status.paused,
+        focus_score: status.focus_score,
+        ..metrics
+    };
+
+    Response {
+        status: OK,
+        content_type: "text/plain; version=0.0.4",
+        body: metrics.to_prometheus(),
+    }
+}
+
+/// The API, which answers with the same JSON as the IPC socket.
+fn api(request: &Request, endpoint: &str, shared: &Shared) -> Response {
+    let permission = match authorize(request, shared) {
+        Some(permission) => permission,
+        None => return Response::error(UNAUTHORIZED),
+    };
+
+    let command = match (request.method.as_str(), endpoint) {
+        ("GET", "status") => {
+            let status = match shared.snapshot.lock() {
+                Ok(snapshot) => snapshot.status(),
+                Err(_) => return Response::error(SERVER_ERROR),
+            };
+            return match serde_json::to_string(&status) {
+                Ok(body) => Response::json(body),
+                Err(_) => Response::error(SERVER_ERROR),
+            };
+        }
+        ("POST", "start") => Command::Start,
+        ("POST", "pause") => Command::Pause,
+        ("POST", "toggle") => Command::Toggle,
+        ("POST", "skip") => Command::Skip,
+        _ => return Response::error(NOT_FOUND),
+    };
+
+    if permission != Permission::Control {
+        return Response::error(FORBIDDEN);
+    }
+
+    let sent = match shared.commands.lock() {
+        Ok(commands) => commands.send(command).is_ok(),
+        Err(_) => false,
+    };
+    if !sent {
+        // the application has exited
+        return Response::error(SERVER_ERROR);
+    }
+    (shared.wake)();
+
+    Response::json(r#"{"ok":true}"#.to_owned())
+}
+
+/// A page showing whether the user is focusing, without their task.
+fn share_page(status: &Status) -> String {
+    let heading = match (status.state, status.paused) {
+        (State::Idle, _) => "Not started",
+        (State::Task, false) => "Focusing",
+        (State::Task, true) => "Paused",
+        (_, false) => "On a break",
+        (_, true) => "Break paused",
+    };
+    let remaining = format!(
+        "{} left",
+        Timer::from_duration(Duration::from_secs(status.remaining_secs))
+            .format_remaining(false)
+    );
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width\">\n\
+         <meta http-equiv=\"refresh\" content=\"{}\">\n\
+         <title>{} - TimeFlo</title>\n\
+         </head>\n\
+         <body style=\"font-family: sans-serif; text-align: center\">\n\
+         <h1>{}</h1>\n\
+         <p>{}</p>\n\
+         </body>\n\
+         </html>\n",
+        PAGE_REFRESH_SECS, heading, heading, remaining
+    )
+}
+
+/// Edit where the timer is served, its API tokens, and the share link of the
+/// running server, if any.
+pub fn settings_editor(
+    ui: &mut egui::Ui,
+    settings: &mut HttpSettings,
+    http: Option<&HttpServer>,
+) {
+    ui.checkbox(&mut settings.enabled, "Serve over HTTP");
+    ui.horizontal(|ui| {
+        ui.label("Address");
+        ui.text_edit_singleline(&mut settings.address);
+    });
+    ui.horizontal(|ui| {
+        ui.label("Port");
+        ui.add(egui::DragValue::new(&mut settings.port));
+    });
+    slider!(
+        ui,
+        settings.share_hours,
+        "Share links last",
+        0.5..=24.0,
+        "h"
+    );
+
+    api_token_editor(ui, &mut settings.api_tokens, http);
+
+    match http {
+        Some(http) => share_link_editor(ui, http, settings.share_hours),
+        None => {
+            ui.add(
+                egui::Label::new(
+                    "The server starts when preferences are closed",
+                )
+                .small(),
+            );
+        }
+    }
+}
+
+/// Add, copy, and remove tokens for the HTTP API.
+fn api_token_editor(
+    ui: &mut egui::Ui,
+    tokens: &mut Vec<ApiToken>,
+    http: Option<&HttpServer>,
+) {
+    ui.label("API tokens");
+
+    let mut removed = None;
+    for (i, token) in tokens.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut token.name)
+                    .hint_text("Name")
+                    .desired_width(80.),
+            );
+            egui::ComboBox::from_id_source(("api_token_permission", i))
+                .selected_text(token.permission.to_string())
+                .show_ui(ui, |ui| {
+                    for permission in Permission::ALL {
+                        ui.selectable_value(
+                            &mut token.permission,
+                            permission,
+                            permission.to_string(),
+                        );
+                    }
+                });
+            if ui.button("Copy").clicked() {
+                ui.output().copied_text = token.token.clone();
+            }
+            if let Some(http) = http {
+                if ui.button("Copy remote link").clicked() {
+                    ui.output().copied_text = http.remote_url(token);
+                }
+            }
+            if ui.button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        tokens.remove(i);
+    }
+
+    if ui.button("Add token").clicked() {
+        tokens.push(ApiToken::new(String::new()));
+    }
+}
+
+/// Create, copy, and revoke the link for following the timer.
+fn share_link_editor(ui: &mut egui::Ui, http: &HttpServer, hours: f32) {
+    match http.share_link() {
+        Some(link) => {
+            let url = http.share_url(&link);
+            let minutes_left = link
+                .expires_at
+                .saturating_duration_since(clock::now())
+                .as_secs()
+                / 60;
+            ui.hyperlink_to(&url, &url);
+            ui.add(
+                egui::Label::new(format!("Expires in {} min", minutes_left))
+                    .small(),
+            );
+            ui.horizontal(|ui| {
+                if ui.button("Copy link").clicked() {
+                    ui.output().copied_text = url;
+                }
+                if ui.button("Stop sharing").clicked() {
+                    http.stop_sharing();
+                }
+            });
+        }
+        None => {
+            if ui.button("Create share link").clicked() {
+                http.share(Duration::from_secs_f32(hours * 3600.));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::Timer;
+
+    fn get(path: &str, token: Option<&str>) -> Request {
+        Request {
+            method: "GET".to_owned(),
+            path: path.to_owned(),
+            token: token.map(str::to_owned),
+        }
+    }
+
+    fn shared() -> (Shared, Receiver<Command>) {
+        let (sender, commands) = mpsc::channel();
+        let shared = Shared::new(sender, || {});
+        *shared.snapshot.lock().unwrap() = Snapshot {
+            state: State::Task,
+            timer: Timer::from_duration(Duration::from_secs(754)),
+            task: Some("secret".to_owned()),
+            ..Default::default()
+        };
+        (shared, commands)
+    }
+
+    #[test]
+    fn test_share_link() {
+        let (shared, _) = shared();
+        let link = ShareLink {
+            token: new_token(),
+            expires_at: clock::now() + Duration::from_secs(60),
+        };
+        assert_eq!(32, link.token.len());
+        assert_ne!(link.token, new_token());
+        *shared.share.lock().unwrap() = Some(link.clone());
+
+        let path = format!("/share/{}", link.token);
+        let page = respond(&get(&path, None), &shared);
+        assert_eq!(OK, page.status);
+        assert!(page.body.contains("<h1>Focusing</h1>"));
+        assert!(page.body.contains("12:34 left"));
+        assert!(!page.body.contains("secret"));
+
+        let long = Status {
+            remaining_secs: 75 * 60,
+            ..shared.snapshot.lock().unwrap().status()
+        };
+        assert!(share_page(&long).contains("1:15:00 left"));
+
+        let not_found = Response::error(NOT_FOUND);
+        assert_eq!(not_found, respond(&get("/share/guess", None), &shared));
+        assert_eq!(not_found, respond(&get("/other", None), &shared));
+
+        clock::advance(Duration::from_secs(60));
+        assert_eq!(not_found, respond(&get(&path, None), &shared));
+    }
+
+    #[test]
+    fn test_read_line() {
+        let mut line = String::new();
+        let mut reader = "GET / HTTP/1.1\r\nHost: x\r\n".as_bytes();
+        assert_eq!(16, read_line(&mut reader, &mut line).unwrap());
+        assert_eq!("GET / HTTP/1.1\r\n", line);
+
+        let long = "a".repeat(MAX_LINE_BYTES + 1);
+        assert!(read_line(&mut long.as_bytes(), &mut String::new()).is_err());
+    }
+
+    #[test]
+    fn test_api_tokens() {
+        let (shared, commands) = shared();
+        let reader = ApiToken::new("Reader".to_owned());
+        let controller = ApiToken {
+            permission: Permission::Control,
+            ..ApiToken::new("Phone".to_owned())
+        };
+        *shared.tokens.lock().unwrap() =
+            vec![reader.clone(), controller.clone()];
+
+        let status = |token| respond(&get("/api/status", token), &shared);
+        assert_eq!(UNAUTHORIZED, status(None).status);
+        assert_eq!(UNAUTHORIZED, status(Some("guess")).status);
+        assert_eq!(UNAUTHORIZED, status(Some("")).status);
+        let response = status(Some(reader.token.as_str()));
+        assert_eq!(OK, response.status);
+        assert!(response.body.contains(r#""remaining_secs":754"#));
+
+        let skip = |token: &ApiToken| {
+            let request = Request {
+                method: "POST".to_owned(),
+                ..get("/api/skip", Some(token.token.as_str()))
+            };
+            respond(&request, &shared).status
+        };
+        assert_eq!(FORBIDDEN, skip(&reader));
+        assert_eq!(OK, skip(&controller));
+        assert_eq!(
+            vec![Command::Skip],
+            commands.try_iter().collect::<Vec<_>>()
+        );
+
+        let metrics = |token| respond(&get("/metrics", token), &shared);
+        assert_eq!(UNAUTHORIZED, metrics(None).status);
+        let response = metrics(Some(reader.token.as_str()));
+        assert!(response.body.contains("time_flo_remaining_seconds 754\n"));
+
+        // the remote page itself holds nothing private
+        let page = respond(&get("/", None), &shared);
+        assert_eq!(OK, page.status);
+        assert!(page.body.contains("/api/"));
+        assert!(!page.body.contains("secret"));
+
+        // tokens only match in full
+        assert!(!tokens_match("abc", "abd"));
+        assert!(!tokens_match("abc", "ab"));
+        assert!(tokens_match("abc", "abc"));
+    }
+}
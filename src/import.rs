@@ -0,0 +1,253 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone};
+use eframe::egui;
+
+use crate::history::{Entry, EntryStatus, History, Interruptions};
+
+/// Formats accepted for timestamps, besides RFC 3339.
+const TIMESTAMP_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// Split CSV text into rows of fields, handling quoted fields.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            ('\r', false) => {}
+            (c, _) => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Local>> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Some(time.with_timezone(&Local));
+    }
+
+    TIMESTAMP_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .and_then(|time| Local.from_local_datetime(&time).single())
+}
+
+/// Which column holds each field of an entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnMapping {
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+    /// Used to find the end if there is no end column.
+    pub minutes: Option<usize>,
+    pub task: Option<usize>,
+}
+
+impl ColumnMapping {
+    /// Guess the mapping from the names of the columns.
+    pub fn guess(headers: &[String]) -> Self {
+        let find = |names: &[&str]| {
+            headers.iter().position(|header| {
+                let header = header.trim().to_lowercase();
+                names.iter().any(|name| header.contains(name))
+            })
+        };
+
+        Self {
+            start: find(&["start", "begin", "from"]),
+            end: find(&["end", "stop", "finish"]),
+            minutes: find(&["minutes", "duration", "length"]),
+            task: find(&["task", "description", "name", "title"]),
+        }
+    }
+
+    fn entry(&self, row: &[String]) -> Option<Entry> {
+        let field = |column: Option<usize>| column.and_then(|i| row.get(i));
+
+        let start = parse_timestamp(field(self.start)?)?;
+        let end = match field(self.end) {
+            Some(end) => parse_timestamp(end)?,
+            None => {
+                let minutes: f64 = field(self.minutes)?.trim().parse().ok()?;
+                start + Duration::seconds((minutes * 60.) as i64)
+            }
+        };
+
+        if end <= start {
+            return None;
+        }
+
+        Some(Entry {
+            start,
+            end,
+            task: field(self.task).map_or("", |s| s.trim()).to_owned(),
+            manual: true,
+            interruptions: Interruptions::default(),
+            status: EntryStatus::default(),
+            applications: Vec::new(),
+            snoozed_minutes: 0,
+            note: String::new(),
+            context: String::new(),
+            pauses: Vec::new(),
+        })
+    }
+}
+
+/// What happened to the rows of an import.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+}
+
+/// A CSV file being imported into the history.
+#[derive(Clone, Debug, Default)]
+pub struct CsvImport {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub mapping: ColumnMapping,
+}
+
+impl CsvImport {
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        Ok(Self::from_text(&fs::read_to_string(path)?))
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        let mut rows = parse_csv(text);
+        let headers = if rows.is_empty() {
+            Vec::new()
+        } else {
+            rows.remove(0)
+        };
+        let mapping = ColumnMapping::guess(&headers);
+
+        Self {
+            headers,
+            rows,
+            mapping,
+        }
+    }
+
+    /// Merge the rows into the history, skipping any which are already in
+    /// it, to the minute.
+    pub fn merge_into(&self, history: &mut History) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+
+        for row in &self.rows {
+            let entry = match self.mapping.entry(row) {
+                Some(entry) => entry,
+                None => {
+                    summary.invalid += 1;
+                    continue;
+                }
+            };
+
+            let key = |entry: &Entry| {
+                (entry.start.timestamp() / 60, entry.end.timestamp() / 60)
+            };
+            if history.entries().iter().any(|e| key(e) == key(&entry)) {
+                summary.duplicates += 1;
+            } else {
+                history.add(entry);
+                summary.added += 1;
+            }
+        }
+
+        summary
+    }
+}
+
+/// Choose which column of a CSV file holds a field.
+pub fn column_selector(
+    ui: &mut egui::Ui,
+    id: &str,
+    headers: &[String],
+    column: &mut Option<usize>,
+) {
+    let selected = column
+        .and_then(|i| headers.get(i))
+        .map_or("None", |header| header.as_str());
+
+    egui::ComboBox::from_id_source(("import_column", id))
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(column, None, "None");
+            for (i, header) in headers.iter().enumerate() {
+                ui.selectable_value(column, Some(i), header.as_str());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import() {
+        assert_eq!(
+            vec![vec!["a", "b, \"c\""], vec!["", "d"]],
+            parse_csv("a,\"b, \"\"c\"\"\"\r\n,d\n\n")
+        );
+
+        let import = CsvImport::from_text(
+            "Start,Duration,Description\n\
+             2021-11-02 09:00,25,Emails\n\
+             2021-11-03T10:00:00+00:00,50,Report\n\
+             yesterday,25,Nonsense\n\
+             2021-11-02 09:00,25,Emails again\n",
+        );
+        assert_eq!(
+            ColumnMapping {
+                start: Some(0),
+                end: None,
+                minutes: Some(1),
+                task: Some(2),
+            },
+            import.mapping
+        );
+
+        let mut history = History::default();
+        assert_eq!(
+            ImportSummary {
+                added: 2,
+                duplicates: 1,
+                invalid: 1,
+            },
+            import.merge_into(&mut history)
+        );
+        assert_eq!("Emails", history.entries()[0].task);
+        assert_eq!(25, history.entries()[0].minutes());
+
+        // importing again adds nothing
+        assert_eq!(0, import.merge_into(&mut history).added);
+    }
+}
@@ -0,0 +1,255 @@
+//! Exporting intervals as points to InfluxDB, or anything else which accepts
+//! its line protocol, such as VictoriaMetrics.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{State, TimeFloError};
+
+/// How long to wait for the database before giving up on a batch.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where to export intervals to.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InfluxSettings {
+    pub enabled: bool,
+    /// The write endpoint, e.g.
+    /// `http://localhost:8086/api/v2/write?org=home&bucket=focus` for
+    /// InfluxDB 2 or `http://localhost:8428/write` for VictoriaMetrics.
+    pub url: String,
+    /// API token, if the endpoint needs one.
+    pub token: String,
+    /// How often to send the points collected since the last batch, in
+    /// seconds.
+    pub batch_secs: u32,
+}
+
+impl Default for InfluxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            token: String::new(),
+            batch_secs: 60,
+        }
+    }
+}
+
+/// An interval which has ended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub state: State,
+    pub task: Option<String>,
+    pub profile: String,
+    pub end: DateTime<Local>,
+    /// Time spent in the interval, not counting pauses.
+    pub elapsed: Duration,
+    /// Whether the interval ran to the end rather than being skipped.
+    pub completed: bool,
+    pub interruptions: u32,
+}
+
+/// Escape a tag value.
+fn escape_tag(value: &str) -> String {
+    value
+        .replace('\n', " ")
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+impl Point {
+    /// The point in line protocol, with a timestamp in nanoseconds.
+    pub fn to_line(&self) -> String {
+        let state = match self.state {
+            State::Idle => "idle",
+            State::Task => "task",
+            State::ShortBreak => "short_break",
+            State::LongBreak => "long_break",
+        };
+
+        // tags in order of their keys, which is quickest for the database,
+        // and left out if empty, which is not allowed
+        let mut line = "time_flo_interval".to_owned();
+        let tags = [
+            ("profile", self.profile.as_str()),
+            ("state", state),
+            ("task", self.task.as_deref().unwrap_or_default()),
+        ];
+        for (key, value) in tags {
+            if !value.is_empty() {
+                line.push_str(&format!(",{}={}", key, escape_tag(value)));
+            }
+        }
+
+        line.push_str(&format!(
+            " elapsed_secs={},completed={},interruptions={}i {}",
+            self.elapsed.as_secs_f64(),
+            self.completed,
+            self.interruptions,
+            self.end.timestamp_nanos()
+        ));
+        line
+    }
+}
+
+/// Collects points and sends them in batches.
+#[derive(Default)]
+pub struct InfluxExporter {
+    pending: Vec<String>,
+    last_sent: Option<Instant>,
+}
+
+impl InfluxExporter {
+    pub fn record(&mut self, point: &Point) {
+        self.pending.push(point.to_line());
+    }
+
+    /// Send the collected points if the batch interval has passed, returning
+    /// how long until the next batch is due if there are points waiting.
+    pub fn flush_if_due(
+        &mut self,
+        settings: &InfluxSettings,
+    ) -> Option<Duration> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let interval = Duration::from_secs(settings.batch_secs as u64);
+        let last_sent = *self.last_sent.get_or_insert_with(Instant::now);
+        let since = last_sent.elapsed();
+        if since < interval {
+            return Some(interval - since);
+        }
+
+        self.flush(settings);
+        None
+    }
+
+    /// The collected points, ready to send.
+    fn take_batch(&mut self) -> Option<String> {
+        self.last_sent = Some(Instant::now());
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let body = self.pending.join("\n");
+        self.pending.clear();
+        Some(body)
+    }
+
+    /// Send the collected points in the background.
+    pub fn flush(&mut self, settings: &InfluxSettings) {
+        if let Some(body) = self.take_batch() {
+            let settings = settings.clone();
+            thread::spawn(move || {
+                if let Err(err) = send(&settings, &body) {
+                    warn!("Could not export intervals: {:?}", err);
+                }
+            });
+        }
+    }
+
+    /// Send the collected points and wait for them to arrive, for when the
+    /// application is exiting.
+    pub fn finish(&mut self, settings: &InfluxSettings) {
+        if let Some(body) = self.take_batch() {
+            if let Err(err) = send(settings, &body) {
+                warn!("Could not export intervals: {:?}", err);
+            }
+        }
+    }
+}
+
+fn send(settings: &InfluxSettings, body: &str) -> crate::Result<()> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+
+    let mut request = agent.post(&settings.url);
+    if !settings.token.is_empty() {
+        request =
+            request.set("Authorization", &format!("Token {}", settings.token));
+    }
+
+    request
+        .set("Content-Type", "text/plain; charset=utf-8")
+        .send_string(body)
+        .map_err(|err| TimeFloError::integration(settings.url.as_str(), err))?;
+
+    Ok(())
+}
+
+/// Edit where intervals are exported to.
+pub fn settings_editor(ui: &mut egui::Ui, influx: &mut InfluxSettings) {
+    ui.checkbox(&mut influx.enabled, "Export to InfluxDB");
+    if influx.enabled {
+        egui::Grid::new("influx_settings").show(ui, |ui| {
+            ui.label("Write URL");
+            ui.add(
+                egui::TextEdit::singleline(&mut influx.url)
+                    .hint_text("http://localhost:8086/api/v2/write?..."),
+            );
+            ui.end_row();
+
+            ui.label("Token");
+            ui.add(
+                egui::TextEdit::singleline(&mut influx.token).password(true),
+            );
+            ui.end_row();
+
+            ui.label("Send every");
+            ui.add(
+                egui::Slider::new(&mut influx.batch_secs, 0..=3600)
+                    .suffix(" s"),
+            );
+            ui.end_row();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_influx_line() {
+        let mut point = Point {
+            state: State::Task,
+            task: Some("Write report, v2".to_owned()),
+            profile: "deep work".to_owned(),
+            end: Local.timestamp(1_635_760_800, 0),
+            elapsed: Duration::from_millis(1_500_500),
+            completed: true,
+            interruptions: 2,
+        };
+        assert_eq!(
+            "time_flo_interval,profile=deep\\ work,state=task,\
+             task=Write\\ report\\,\\ v2 \
+             elapsed_secs=1500.5,completed=true,interruptions=2i \
+             1635760800000000000",
+            point.to_line()
+        );
+
+        point.state = State::ShortBreak;
+        point.task = None;
+        point.profile = String::new();
+        point.completed = false;
+        assert!(point.to_line().starts_with(
+            "time_flo_interval,state=short_break elapsed_secs=1500.5,\
+             completed=false,"
+        ));
+
+        let mut exporter = InfluxExporter::default();
+        let settings = InfluxSettings::default();
+        assert_eq!(None, exporter.flush_if_due(&settings));
+        exporter.record(&point);
+        assert!(exporter.flush_if_due(&settings).is_some());
+    }
+}
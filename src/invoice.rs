@@ -0,0 +1,263 @@
+//! Time by project as CSV for invoicing tools, rounded the way clients are
+//! billed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chrono::{Datelike, NaiveDate};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::history::{data_dir, EntryStatus, History};
+use crate::report::next_month;
+
+/// Which way billed time is rounded to a whole number of increments.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum Rounding {
+    Up,
+    Nearest,
+    Down,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Rounding::Up
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rounding::Up => "Round up",
+            Rounding::Nearest => "Round to nearest",
+            Rounding::Down => "Round down",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl Rounding {
+    pub const ALL: [Rounding; 3] =
+        [Rounding::Up, Rounding::Nearest, Rounding::Down];
+
+    /// Round minutes to a multiple of the increment, leaving them as they
+    /// are for an increment of zero.
+    pub fn apply(self, minutes: i64, increment: u32) -> i64 {
+        let increment = increment as i64;
+        if increment == 0 {
+            return minutes;
+        }
+
+        let whole = match self {
+            Rounding::Up => (minutes + increment - 1) / increment,
+            // halfway rounds up
+            Rounding::Nearest => (minutes + increment / 2) / increment,
+            Rounding::Down => minutes / increment,
+        };
+        whole * increment
+    }
+}
+
+/// How focused time is billed.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InvoiceSettings {
+    pub rounding: Rounding,
+    /// The increment time is rounded to, in minutes, or zero for none.
+    pub increment_minutes: u32,
+    /// Whether each project is rounded and listed day by day, rather than
+    /// once for the month.
+    pub per_day: bool,
+}
+
+impl Default for InvoiceSettings {
+    fn default() -> Self {
+        Self {
+            rounding: Rounding::default(),
+            increment_minutes: 15,
+            per_day: true,
+        }
+    }
+}
+
+/// Time on one project over a day or a month.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceLine {
+    /// The day, or the first day of the month when billed by month.
+    pub date: NaiveDate,
+    /// The context the intervals were labelled with, or empty.
+    pub project: String,
+    pub minutes: i64,
+    /// The minutes after rounding.
+    pub billed_minutes: i64,
+}
+
+/// Group the task intervals which ended in the month of the given day by
+/// project, leaving out voided ones, in order of date and then project.
+pub fn invoice_lines(
+    history: &History,
+    settings: &InvoiceSettings,
+    date: NaiveDate,
+) -> Vec<InvoiceLine> {
+    let month = date.with_day(1).unwrap();
+    let next = next_month(month);
+
+    let mut minutes: BTreeMap<(NaiveDate, &str), i64> = BTreeMap::new();
+    for entry in history.entries() {
+        let day = entry.end.date().naive_local();
+        if day < month || day >= next || entry.status == EntryStatus::Voided {
+            continue;
+        }
+
+        let date = if settings.per_day { day } else { month };
+        *minutes.entry((date, entry.context.trim())).or_default() +=
+            entry.minutes();
+    }
+
+    minutes
+        .into_iter()
+        .map(|((date, project), minutes)| InvoiceLine {
+            date,
+            project: project.to_owned(),
+            minutes,
+            billed_minutes: settings
+                .rounding
+                .apply(minutes, settings.increment_minutes),
+        })
+        .collect()
+}
+
+/// Write the lines as CSV, with billed time in decimal hours as invoicing
+/// tools expect.
+pub fn write_csv(
+    lines: &[InvoiceLine],
+    settings: &InvoiceSettings,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let date_format = if settings.per_day {
+        "%Y-%m-%d"
+    } else {
+        "%Y-%m"
+    };
+    writeln!(writer, "date,project,minutes,billed_minutes,billed_hours")?;
+    for line in lines {
+        writeln!(
+            writer,
+            "{},\"{}\",{},{},{:.2}",
+            line.date.format(date_format),
+            line.project.replace('"', "\"\""),
+            line.minutes,
+            line.billed_minutes,
+            line.billed_minutes as f64 / 60.
+        )?;
+    }
+    Ok(())
+}
+
+/// Export the month of the given day to `invoices/YYYY-MM.csv` in the data
+/// directory.
+pub fn export_csv(
+    history: &History,
+    settings: &InvoiceSettings,
+    date: NaiveDate,
+) -> crate::Result<PathBuf> {
+    let path = data_dir()
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no data directory")
+        })?
+        .join("invoices")
+        .join(format!("{}.csv", date.format("%Y-%m")));
+
+    fs::create_dir_all(path.parent().unwrap())?;
+    let mut writer = io::BufWriter::new(fs::File::create(&path)?);
+    let lines = invoice_lines(history, settings, date);
+    write_csv(&lines, settings, &mut writer)?;
+    writer.flush()?;
+    Ok(path)
+}
+
+/// Edit how focused time is rounded for invoicing.
+pub fn settings_editor(ui: &mut egui::Ui, invoice: &mut InvoiceSettings) {
+    egui::ComboBox::from_label("Rounding")
+        .selected_text(invoice.rounding.to_string())
+        .show_ui(ui, |ui| {
+            for rounding in Rounding::ALL {
+                ui.selectable_value(
+                    &mut invoice.rounding,
+                    rounding,
+                    rounding.to_string(),
+                );
+            }
+        });
+    ui.add(
+        egui::Slider::new(&mut invoice.increment_minutes, 0..=60)
+            .text("To a multiple of")
+            .suffix(" min"),
+    );
+    ui.checkbox(&mut invoice.per_day, "Round each day separately");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EntryDraft;
+
+    #[test]
+    fn test_invoice_lines() {
+        assert_eq!(15, Rounding::Up.apply(1, 15));
+        assert_eq!(15, Rounding::Nearest.apply(8, 15));
+        assert_eq!(0, Rounding::Nearest.apply(7, 15));
+        assert_eq!(0, Rounding::Down.apply(14, 15));
+        assert_eq!(7, Rounding::Down.apply(7, 0));
+
+        let mut history = History::default();
+        for (date, start, end, context) in [
+            ("2021-10-31", "09:00", "09:25", "Acme"),
+            ("2021-11-01", "09:00", "09:25", "Acme"),
+            ("2021-11-01", "10:00", "10:25", "Acme"),
+            ("2021-11-01", "11:00", "11:10", "\"Big\" Co"),
+            ("2021-11-02", "09:00", "09:25", "Acme"),
+        ] {
+            let mut entry = EntryDraft {
+                date: date.to_owned(),
+                start: start.into(),
+                end: end.into(),
+                task: "Write report".into(),
+            }
+            .parse()
+            .unwrap();
+            entry.context = context.to_owned();
+            history.add(entry);
+        }
+
+        let mut settings = InvoiceSettings::default();
+        let date = NaiveDate::from_ymd(2021, 11, 20);
+        let lines = invoice_lines(&history, &settings, date);
+        assert_eq!(3, lines.len());
+        assert_eq!("\"Big\" Co", lines[0].project);
+        assert_eq!(10, lines[0].minutes);
+        assert_eq!(15, lines[0].billed_minutes);
+        assert_eq!(50, lines[1].minutes);
+        assert_eq!(60, lines[1].billed_minutes);
+
+        // rounding once over the month bills less
+        settings.per_day = false;
+        let lines = invoice_lines(&history, &settings, date);
+        assert_eq!(2, lines.len());
+        assert_eq!(75, lines[1].minutes);
+        assert_eq!(75, lines[1].billed_minutes);
+
+        let mut csv = Vec::new();
+        write_csv(&lines, &settings, &mut csv).unwrap();
+        assert_eq!(
+            "date,project,minutes,billed_minutes,billed_hours\n\
+             2021-11,\"\"\"Big\"\" Co\",10,15,0.25\n\
+             2021-11,\"Acme\",75,75,1.25\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+}
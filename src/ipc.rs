@@ -0,0 +1,427 @@
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::timer::Timer;
+use crate::{State, TimeFloError};
+
+/// How often subscriptions check whether there is anything new to send.
+const SUBSCRIPTION_POLL: Duration = Duration::from_millis(250);
+
+/// A request sent to a running TimeFlo instance, one JSON object per line.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Status,
+    /// Begin the task, or resume after a pause.
+    Start,
+    Pause,
+    /// Pause if running, or start otherwise.
+    Toggle,
+    /// Skip the current interval, as the skip button does.
+    Skip,
+    /// Receive the status whenever it changes, with the remaining time only
+    /// counting as a change every `interval_secs` seconds.
+    Subscribe {
+        #[serde(default = "default_interval")]
+        interval_secs: u64,
+    },
+}
+
+fn default_interval() -> u64 {
+    1
+}
+
+/// An action requested by another process, for the application to carry
+/// out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Start,
+    Pause,
+    Toggle,
+    Skip,
+}
+
+/// Sent in reply to a command, which is carried out shortly after.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct OkResponse {
+    ok: bool,
+}
+
+/// Sent in reply to a request which could not be handled.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// The status of a running TimeFlo instance.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Status {
+    pub state: State,
+    pub remaining_secs: u64,
+    pub short_break_counter: u32,
+    pub task: Option<String>,
+    pub paused: bool,
+    /// Today's focus score, from 0 to 100.
+    #[serde(default)]
+    pub focus_score: u32,
+    /// Whether the timer is counting down.
+    #[serde(default)]
+    pub running: bool,
+}
+
+/// The parts of the application state needed to answer requests.
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    pub state: State,
+    pub timer: Timer,
+    pub short_break_counter: u32,
+    pub task: Option<String>,
+    pub focus_score: u32,
+}
+
+impl Status {
+    /// The status with the remaining time rounded down to the given number
+    /// of seconds, for telling whether it has changed at that granularity.
+    fn at_granularity(&self, interval_secs: u64) -> Self {
+        let interval_secs = interval_secs.max(1);
+        Self {
+            remaining_secs: self.remaining_secs / interval_secs * interval_secs,
+            ..self.clone()
+        }
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn status(&self) -> Status {
+        Status {
+            state: self.state,
+            remaining_secs: self.timer.remaining_time().as_secs(),
+            short_break_counter: self.short_break_counter,
+            task: self.task.clone(),
+            paused: self.timer.is_paused(),
+            focus_score: self.focus_score,
+            running: self.timer.is_running(),
+        }
+    }
+}
+
+/// The location of the socket used to talk to a running instance: the
+/// runtime directory, or else a directory of its own in the data directory,
+/// never a temporary directory shared with other users.
+pub fn socket_path() -> crate::Result<PathBuf> {
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .or_else(|| crate::history::data_dir().map(|dir| dir.join("run")))
+        .ok_or_else(|| {
+            TimeFloError::Ipc("no directory to keep the socket in".to_owned())
+        })?;
+    Ok(dir.join("time-flo.sock"))
+}
+
+/// Make sure the directory the socket is in exists, and that only the user
+/// can get into it, so that nobody else can connect before the socket's own
+/// permissions are set.
+fn prepare_socket_dir(dir: &Path) -> crate::Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+
+    let permissions = fs::metadata(dir)?.permissions();
+    if permissions.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Answers requests from other processes about the running instance.
+pub struct IpcServer {
+    snapshot: Arc<Mutex<Snapshot>>,
+    commands: Receiver<Command>,
+}
+
+/// What a client connection needs to handle requests.
+struct Client {
+    snapshot: Arc<Mutex<Snapshot>>,
+    commands: Sender<Command>,
+    /// Wakes the application up to carry out commands.
+    wake: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl IpcServer {
+    /// Start listening, calling `wake` whenever a command is received.
+    pub fn start(
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> crate::Result<Self> {
+        let path = socket_path()?;
+        if let Some(dir) = path.parent() {
+            prepare_socket_dir(dir)?;
+        }
+
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(TimeFloError::Ipc(format!(
+                    "another instance is already listening on {}",
+                    path.display()
+                )));
+            }
+
+            // left over from an instance which did not exit cleanly
+            fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        // the socket accepts commands, so only the user may connect to it
+        if let Err(err) =
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+        {
+            let _ = fs::remove_file(&path);
+            return Err(err.into());
+        }
+        let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+        let (sender, commands) = mpsc::channel();
+
+        let shared = Arc::clone(&snapshot);
+        let wake: Arc<dyn Fn() + Send + Sync> = Arc::new(wake);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Could not accept IPC connection: {:?}", err);
+                        continue;
+                    }
+                };
+
+                let client = Client {
+                    snapshot: Arc::clone(&shared),
+                    commands: sender.clone(),
+                    wake: Arc::clone(&wake),
+                };
+                thread::spawn(move || {
+                    if let Err(err) = handle_client(stream, &client) {
+                        warn!("Could not handle IPC client: {:?}", err);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { snapshot, commands })
+    }
+
+    /// Update the state used to answer requests.
+    pub fn update(&self, snapshot: Snapshot) {
+        if let Ok(mut shared) = self.snapshot.lock() {
+            *shared = snapshot;
+        }
+    }
+
+    /// Take the commands received since the last call.
+    pub fn take_commands(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        if let Ok(path) = socket_path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn current_status(snapshot: &Mutex<Snapshot>) -> crate::Result<Status> {
+    Ok(snapshot
+        .lock()
+        .map_err(|err| TimeFloError::Ipc(err.to_string()))?
+        .status())
+}
+
+fn handle_client(stream: UnixStream, client: &Client) -> crate::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        let command = |command| {
+            // the application has exited if the receiver is gone
+            client
+                .commands
+                .send(command)
+                .map_err(|err| TimeFloError::Ipc(err.to_string()))?;
+            (client.wake)();
+            Ok::<_, TimeFloError>(serde_json::to_string(&OkResponse {
+                ok: true,
+            })?)
+        };
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Status) => {
+                serde_json::to_string(&current_status(&client.snapshot)?)?
+            }
+            Ok(Request::Start) => command(Command::Start)?,
+            Ok(Request::Pause) => command(Command::Pause)?,
+            Ok(Request::Toggle) => command(Command::Toggle)?,
+            Ok(Request::Skip) => command(Command::Skip)?,
+            Ok(Request::Subscribe { interval_secs }) => {
+                return stream_status(writer, &client.snapshot, interval_secs);
+            }
+            Err(err) => serde_json::to_string(&ErrorResponse {
+                error: err.to_string(),
+            })?,
+        };
+
+        writeln!(writer, "{}", response)?;
+    }
+
+    Ok(())
+}
+
+/// Send the status to a subscriber whenever it changes, until they hang up.
+fn stream_status(
+    mut writer: UnixStream,
+    snapshot: &Mutex<Snapshot>,
+    interval_secs: u64,
+) -> crate::Result<()> {
+    let mut last_sent = None;
+
+    loop {
+        let status = current_status(snapshot)?;
+        let key = status.at_granularity(interval_secs);
+
+        if last_sent.as_ref() != Some(&key) {
+            let line = serde_json::to_string(&status)?;
+            if writeln!(writer, "{}", line).is_err() {
+                // the subscriber has gone away
+                return Ok(());
+            }
+            last_sent = Some(key);
+        }
+
+        thread::sleep(SUBSCRIPTION_POLL);
+    }
+}
+
+fn connect() -> crate::Result<UnixStream> {
+    let path = socket_path()?;
+    UnixStream::connect(&path).map_err(|err| {
+        TimeFloError::Ipc(format!(
+            "could not connect to {} (is TimeFlo running?): {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+/// Send a request to the running instance, returning the raw JSON response.
+pub fn send(request: &Request) -> crate::Result<String> {
+    let stream = connect()?;
+
+    writeln!(&stream, "{}", serde_json::to_string(request)?)?;
+
+    let mut response = String::new();
+    BufReader::new(&stream).read_line(&mut response)?;
+
+    if let Ok(error) = serde_json::from_str::<ErrorResponse>(&response) {
+        return Err(TimeFloError::Ipc(error.error));
+    }
+
+    Ok(response.trim_end().to_owned())
+}
+
+/// Relay requests read from `input` to the running instance line by line,
+/// and its responses to `output`, until the input ends.
+pub fn relay(
+    input: impl BufRead,
+    mut output: impl Write + Send + 'static,
+) -> crate::Result<()> {
+    let stream = connect()?;
+
+    let reader = BufReader::new(stream.try_clone()?);
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let written = line.and_then(|line| {
+                writeln!(output, "{}", line)?;
+                output.flush()
+            });
+            if written.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut writer = stream;
+    for line in input.lines() {
+        writeln!(writer, "{}", line?)?;
+    }
+
+    Ok(())
+}
+
+/// Query the status of the running instance.
+pub fn status() -> crate::Result<Status> {
+    Ok(serde_json::from_str(&send(&Request::Status)?)?)
+}
+
+/// Subscribe to the status of the running instance, calling `f` with each
+/// raw JSON update until the instance exits or `f` returns `false`.
+pub fn subscribe(
+    interval_secs: u64,
+    mut f: impl FnMut(&str) -> bool,
+) -> crate::Result<()> {
+    let stream = connect()?;
+
+    let request = Request::Subscribe { interval_secs };
+    writeln!(&stream, "{}", serde_json::to_string(&request)?)?;
+
+    for line in BufReader::new(&stream).lines() {
+        if !f(&line?) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipc() {
+        assert_eq!(
+            Request::Subscribe { interval_secs: 1 },
+            serde_json::from_str(r#"{"command":"subscribe"}"#).unwrap()
+        );
+        assert_eq!(
+            Request::Toggle,
+            serde_json::from_str(r#"{"command":"toggle"}"#).unwrap()
+        );
+
+        let status = Status {
+            state: State::Task,
+            remaining_secs: 754,
+            short_break_counter: 0,
+            task: None,
+            paused: false,
+            focus_score: 0,
+            running: true,
+        };
+        assert_eq!(754, status.at_granularity(1).remaining_secs);
+        assert_eq!(750, status.at_granularity(10).remaining_secs);
+        assert_eq!(720, status.at_granularity(60).remaining_secs);
+        assert_eq!(754, status.at_granularity(0).remaining_secs);
+    }
+}
@@ -2,10 +2,82 @@
 #![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[macro_use]
+mod macros;
+
+pub mod achievements;
+pub mod adaptive;
+pub mod alarm;
+pub mod alert;
+pub mod announce;
+
 mod app;
 pub use app::TimeFloApp;
 
+#[cfg(feature = "sound")]
+mod audio;
+pub mod busylight;
+pub mod capacity;
+pub mod clock;
+mod completion;
+
+#[cfg(unix)]
+pub mod ctl;
+mod devices;
+#[cfg(unix)]
+pub mod display;
+
+pub mod enforcement;
+
 mod error;
 pub use error::*;
 
+pub mod experiment;
+
+pub mod flow;
+pub mod flowtime;
+#[cfg(feature = "sound")]
+mod focus_sound;
+
+pub mod history;
+pub mod hooks;
+#[cfg(unix)]
+mod http;
+pub mod import;
+#[cfg(feature = "push")]
+mod influx;
+pub mod invoice;
+
+#[cfg(unix)]
+pub mod ipc;
+#[cfg(feature = "push")]
+mod lights;
+pub mod locale;
+pub mod lock;
+#[cfg(all(unix, feature = "mdns"))]
+pub mod mdns;
+pub mod metrics;
+#[cfg(feature = "notifications")]
+mod notifier;
+pub mod plan;
+mod power;
+pub mod profile;
+#[cfg(feature = "push")]
+pub mod push;
+mod repaint;
+pub mod report;
+
+pub mod scheduler;
+pub use scheduler::State;
+
+pub mod score;
+mod status_file;
+pub mod store;
+mod suspend;
+pub mod theme;
+pub mod time_of_day;
 pub mod timer;
+mod toast;
+#[cfg(all(feature = "tray", target_os = "linux"))]
+mod tray;
+pub mod window_title;
@@ -0,0 +1,306 @@
+//! Changing the color of smart lights with the state, through a Philips Hue
+//! bridge or WLED's JSON API.
+
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{State, TimeFloError};
+
+/// How long WLED lights pulse for when an interval ends. Hue lights pulse for
+/// as long as the bridge decides, about 15 seconds.
+const WLED_PULSE: Duration = Duration::from_secs(10);
+
+/// WLED's "Solid" and "Breathe" effects.
+const WLED_SOLID: u32 = 0;
+const WLED_BREATHE: u32 = 2;
+
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum LightBackend {
+    Hue,
+    Wled,
+}
+
+impl LightBackend {
+    pub const ALL: [LightBackend; 2] = [LightBackend::Hue, LightBackend::Wled];
+}
+
+impl Default for LightBackend {
+    fn default() -> Self {
+        LightBackend::Hue
+    }
+}
+
+impl fmt::Display for LightBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LightBackend::Hue => "Philips Hue",
+            LightBackend::Wled => "WLED",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A group of lights, and the color to show in each state.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightGroup {
+    pub name: String,
+    pub enabled: bool,
+    pub backend: LightBackend,
+    /// Address of the Hue bridge or the WLED controller.
+    pub address: String,
+    /// Hue API username, from pairing with the bridge.
+    pub username: String,
+    /// Hue group (room or zone) ID, where 0 is every light, or WLED segment
+    /// ID.
+    pub group: u32,
+    pub task: [u8; 3],
+    pub short_break: [u8; 3],
+    pub long_break: [u8; 3],
+    /// Black turns the lights off.
+    pub idle: [u8; 3],
+    /// Whether to pulse the lights when an interval ends.
+    pub pulse: bool,
+}
+
+impl Default for LightGroup {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            backend: LightBackend::default(),
+            address: String::new(),
+            username: String::new(),
+            group: 0,
+            task: [255, 40, 20],
+            short_break: [40, 220, 60],
+            long_break: [40, 120, 255],
+            idle: [255, 200, 150],
+            pulse: true,
+        }
+    }
+}
+
+impl LightGroup {
+    pub fn color(&self, state: State) -> [u8; 3] {
+        match state {
+            State::Idle => self.idle,
+            State::Task => self.task,
+            State::ShortBreak => self.short_break,
+            State::LongBreak => self.long_break,
+        }
+    }
+
+    fn send(&self, color: [u8; 3], pulse: bool) -> crate::Result<()> {
+        let pulse = pulse && self.pulse;
+        match self.backend {
+            LightBackend::Hue => self.send_hue(color, pulse),
+            LightBackend::Wled => {
+                if pulse {
+                    self.send_wled(color, WLED_BREATHE)?;
+                    thread::sleep(WLED_PULSE);
+                }
+                self.send_wled(color, WLED_SOLID)
+            }
+        }
+    }
+
+    fn send_hue(&self, color: [u8; 3], pulse: bool) -> crate::Result<()> {
+        let url = format!(
+            "http://{}/api/{}/groups/{}/action",
+            self.address, self.username, self.group
+        );
+
+        let body = if color == [0, 0, 0] {
+            serde_json::json!({ "on": false })
+        } else {
+            let (xy, bri) = hue_color(color);
+            serde_json::json!({
+                "on": true,
+                "xy": xy,
+                "bri": bri,
+                "alert": if pulse { "lselect" } else { "none" },
+            })
+        };
+
+        ureq::put(&url)
+            .send_json(body)
+            // don't leak the username into error messages
+            .map_err(|err| {
+                TimeFloError::integration(
+                    format!("http://{}", self.address),
+                    err,
+                )
+            })?;
+
+        Ok(())
+    }
+
+    fn send_wled(&self, color: [u8; 3], effect: u32) -> crate::Result<()> {
+        let url = format!("http://{}/json/state", self.address);
+
+        ureq::post(&url)
+            .send_json(serde_json::json!({
+                "on": color != [0, 0, 0],
+                "seg": [{
+                    "id": self.group,
+                    "col": [color],
+                    "fx": effect,
+                }],
+            }))
+            .map_err(|err| TimeFloError::integration(url.as_str(), err))?;
+
+        Ok(())
+    }
+}
+
+/// The CIE xy coordinates of an sRGB color, which Hue lights expect, and its
+/// brightness from 1 to 254.
+fn hue_color(rgb: [u8; 3]) -> ([f64; 2], u8) {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.;
+        if c > 0.04045 {
+            ((c + 0.055) / 1.055).powf(2.4)
+        } else {
+            c / 12.92
+        }
+    };
+    let (r, g, b) = (linear(rgb[0]), linear(rgb[1]), linear(rgb[2]));
+
+    let x = r * 0.4124 + g * 0.3576 + b * 0.1805;
+    let y = r * 0.2126 + g * 0.7152 + b * 0.0722;
+    let z = r * 0.0193 + g * 0.1192 + b * 0.9505;
+    let sum = x + y + z;
+
+    let round = |v: f64| (v * 10000.).round() / 10000.;
+    let xy = if sum > 0. {
+        [round(x / sum), round(y / sum)]
+    } else {
+        // the white point
+        [0.3127, 0.329]
+    };
+
+    let max = rgb.iter().copied().max().unwrap_or_default();
+    let bri = (max as f64 / 255. * 254.).round().max(1.) as u8;
+
+    (xy, bri)
+}
+
+/// Show the color for the given state on every enabled group, pulsing it if
+/// an interval has just ended.
+pub fn show_state(groups: &[LightGroup], state: State, pulse: bool) {
+    for group in groups.iter().filter(|group| group.enabled) {
+        let group = group.clone();
+        let color = group.color(state);
+        thread::spawn(move || {
+            if let Err(err) = group.send(color, pulse) {
+                warn!("Could not set lights {:?}: {:?}", group.name, err);
+            }
+        });
+    }
+}
+
+/// Add and remove groups of smart lights, and pick their colors.
+pub fn editor(ui: &mut egui::Ui, groups: &mut Vec<LightGroup>) {
+    let mut removed = None;
+
+    for (i, group) in groups.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut group.enabled, "");
+                ui.add(
+                    egui::TextEdit::singleline(&mut group.name)
+                        .hint_text("Name"),
+                );
+
+                if ui.button("\u{1F5D9}").clicked() {
+                    removed = Some(i);
+                }
+            });
+
+            egui::Grid::new("light_group").show(ui, |ui| {
+                ui.label("Kind");
+                egui::ComboBox::from_id_source("light_backend")
+                    .selected_text(group.backend.to_string())
+                    .show_ui(ui, |ui| {
+                        for backend in LightBackend::ALL {
+                            ui.selectable_value(
+                                &mut group.backend,
+                                backend,
+                                backend.to_string(),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Address");
+                ui.add(
+                    egui::TextEdit::singleline(&mut group.address)
+                        .hint_text("192.168.1.2"),
+                );
+                ui.end_row();
+
+                if group.backend == LightBackend::Hue {
+                    ui.label("Username");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut group.username)
+                            .password(true),
+                    );
+                    ui.end_row();
+                }
+
+                ui.label(match group.backend {
+                    LightBackend::Hue => "Group",
+                    LightBackend::Wled => "Segment",
+                });
+                ui.add(egui::DragValue::new(&mut group.group));
+                ui.end_row();
+
+                for (label, color) in [
+                    ("Task period", &mut group.task),
+                    ("Short break", &mut group.short_break),
+                    ("Long break", &mut group.long_break),
+                    ("Idle", &mut group.idle),
+                ] {
+                    ui.label(label);
+                    ui.color_edit_button_srgb(color);
+                    ui.end_row();
+                }
+            });
+
+            ui.checkbox(&mut group.pulse, "Pulse when an interval ends");
+        });
+    }
+
+    if let Some(i) = removed {
+        groups.remove(i);
+    }
+
+    if ui.button("Add lights").clicked() {
+        groups.push(LightGroup::default());
+    }
+    ui.add(egui::Label::new("Black turns the lights off.").small());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hue_color() {
+        assert_eq!(([0.3127, 0.329], 254), hue_color([255, 255, 255]));
+        assert_eq!(([0.64, 0.33], 254), hue_color([255, 0, 0]));
+        assert_eq!(([0.3, 0.6], 128), hue_color([0, 128, 0]));
+        assert_eq!(([0.3127, 0.329], 1), hue_color([0, 0, 0]));
+
+        let group = LightGroup::default();
+        assert_eq!(group.task, group.color(State::Task));
+        assert_eq!(group.idle, group.color(State::Idle));
+    }
+}
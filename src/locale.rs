@@ -0,0 +1,167 @@
+use std::env;
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// The POSIX locale name set in the environment for times and dates, such as
+/// `en_US.UTF-8`, or an empty string if there is none.
+pub fn env_locale() -> String {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+/// Conventions for writing durations, dates, and numbers.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum Locale {
+    /// Follow the locale set in the environment.
+    System,
+    /// English words with ISO 8601 dates.
+    Iso,
+    EnglishUs,
+    EnglishUk,
+    German,
+    French,
+    Spanish,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 7] = [
+        Locale::System,
+        Locale::Iso,
+        Locale::EnglishUs,
+        Locale::EnglishUk,
+        Locale::German,
+        Locale::French,
+        Locale::Spanish,
+    ];
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::System
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Locale::System => "System default",
+            Locale::Iso => "ISO 8601",
+            Locale::EnglishUs => "English (US)",
+            Locale::EnglishUk => "English (UK)",
+            Locale::German => "Deutsch",
+            Locale::French => "Fran\u{e7}ais",
+            Locale::Spanish => "Espa\u{f1}ol",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl Locale {
+    /// Resolve the system default to a concrete locale.
+    pub fn resolve(self) -> Self {
+        match self {
+            Locale::System => Self::from_posix(&env_locale()),
+            locale => locale,
+        }
+    }
+
+    /// The locale for a POSIX locale name such as `de_DE.UTF-8`.
+    fn from_posix(name: &str) -> Self {
+        let language = name.split(&['_', '.'][..]).next();
+        match language {
+            Some("en") if name.starts_with("en_US") => Locale::EnglishUs,
+            Some("en") => Locale::EnglishUk,
+            Some("de") => Locale::German,
+            Some("fr") => Locale::French,
+            Some("es") => Locale::Spanish,
+            _ => Locale::Iso,
+        }
+    }
+
+    pub fn format_date(self, date: NaiveDate) -> String {
+        let format = match self.resolve() {
+            Locale::EnglishUs => "%m/%d/%Y",
+            Locale::German => "%d.%m.%Y",
+            Locale::EnglishUk | Locale::French | Locale::Spanish => "%d/%m/%Y",
+            _ => "%Y-%m-%d",
+        };
+
+        date.format(format).to_string()
+    }
+
+    /// Words for hours and minutes, singular and plural.
+    fn duration_words(self) -> [&'static str; 4] {
+        match self.resolve() {
+            Locale::German => ["Stunde", "Stunden", "Minute", "Minuten"],
+            Locale::French => ["heure", "heures", "minute", "minutes"],
+            Locale::Spanish => ["hora", "horas", "minuto", "minutos"],
+            _ => ["hour", "hours", "minute", "minutes"],
+        }
+    }
+
+    /// A number of minutes in words, such as "1 hour 5 minutes".
+    pub fn format_minutes(self, minutes: i64) -> String {
+        let [hour, hours, minute, minutes_word] = self.duration_words();
+        let word = |n: i64, one, many| {
+            format!("{} {}", n, if n == 1 { one } else { many })
+        };
+
+        let (h, m) = (minutes / 60, minutes % 60);
+        match (h, m) {
+            (0, m) => word(m, minute, minutes_word),
+            (h, 0) => word(h, hour, hours),
+            (h, m) => {
+                format!(
+                    "{} {}",
+                    word(h, hour, hours),
+                    word(m, minute, minutes_word)
+                )
+            }
+        }
+    }
+
+    /// A number with the given number of decimal places.
+    pub fn format_decimal(self, value: f64, places: usize) -> String {
+        let formatted = format!("{:.*}", places, value);
+        match self.resolve() {
+            Locale::German | Locale::French | Locale::Spanish => {
+                formatted.replace('.', ",")
+            }
+            _ => formatted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(Locale::EnglishUs, Locale::from_posix("en_US.UTF-8"));
+        assert_eq!(Locale::EnglishUk, Locale::from_posix("en_GB.UTF-8"));
+        assert_eq!(Locale::German, Locale::from_posix("de_AT"));
+        assert_eq!(Locale::Iso, Locale::from_posix("C"));
+        assert_eq!(Locale::Iso, Locale::from_posix(""));
+
+        let date = NaiveDate::from_ymd(2021, 11, 2);
+        assert_eq!("11/02/2021", Locale::EnglishUs.format_date(date));
+        assert_eq!("02.11.2021", Locale::German.format_date(date));
+        assert_eq!("2021-11-02", Locale::Iso.format_date(date));
+
+        assert_eq!("1 minute", Locale::EnglishUk.format_minutes(1));
+        assert_eq!("2 hours", Locale::EnglishUk.format_minutes(120));
+        assert_eq!("1 hour 5 minutes", Locale::Iso.format_minutes(65));
+        assert_eq!("1 Stunde 30 Minuten", Locale::German.format_minutes(90));
+        assert_eq!("0 minutos", Locale::Spanish.format_minutes(0));
+
+        assert_eq!("4.25", Locale::EnglishUs.format_decimal(4.25, 2));
+        assert_eq!("4,3", Locale::French.format_decimal(4.26, 1));
+    }
+}
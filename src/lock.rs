@@ -0,0 +1,208 @@
+use chrono::{DateTime, Duration, Local};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// How a preferences lock may be lifted before it expires.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum LockRelease {
+    /// The lock can only expire.
+    Never,
+    /// The lock is lifted some number of minutes after a release is
+    /// requested.
+    Delay(u32),
+    /// The lock is lifted by entering a passphrase.
+    ///
+    /// This is stored in plain text, since it is meant as a deterrent rather
+    /// than as a security measure.
+    Passphrase(String),
+}
+
+impl Default for LockRelease {
+    fn default() -> Self {
+        LockRelease::Delay(15)
+    }
+}
+
+/// Locks interval durations for the rest of the day ("commitment mode"), so
+/// they can't be quietly changed mid-afternoon.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PreferencesLock {
+    /// The time at which the lock expires, if locked.
+    pub locked_until: Option<DateTime<Local>>,
+    /// How the lock may be lifted early.
+    pub release: LockRelease,
+    /// The time at which an early release was requested, if any.
+    pub release_requested: Option<DateTime<Local>>,
+}
+
+impl PreferencesLock {
+    /// Whether the lock is ready to be turned on, which it isn't with an
+    /// empty passphrase to lift it with.
+    pub fn can_lock(&self) -> bool {
+        match &self.release {
+            LockRelease::Passphrase(passphrase) => !passphrase.is_empty(),
+            _ => true,
+        }
+    }
+
+    /// Lock until the end of the current day.
+    pub fn lock_for_today(&mut self, now: DateTime<Local>) {
+        self.locked_until = Some(now.date().succ().and_hms(0, 0, 0));
+        self.release_requested = None;
+    }
+
+    pub fn is_locked(&self, now: DateTime<Local>) -> bool {
+        let expired = self.locked_until.map_or(true, |until| now >= until);
+        let released = self.release_time().map_or(false, |time| now >= time);
+        !expired && !released
+    }
+
+    /// The time at which a requested delayed release will happen.
+    pub fn release_time(&self) -> Option<DateTime<Local>> {
+        match (&self.release, self.release_requested) {
+            (LockRelease::Delay(minutes), Some(requested)) => {
+                Some(requested + Duration::minutes(*minutes as i64))
+            }
+            _ => None,
+        }
+    }
+
+    /// Request a delayed release of the lock.
+    pub fn request_release(&mut self, now: DateTime<Local>) {
+        if self.release_requested.is_none() {
+            self.release_requested = Some(now);
+        }
+    }
+
+    /// Try to release the lock with a passphrase, returning whether it was
+    /// correct.
+    pub fn release_with_passphrase(&mut self, passphrase: &str) -> bool {
+        match &self.release {
+            LockRelease::Passphrase(expected) if expected == passphrase => {
+                self.locked_until = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Engage the preferences lock, or release it early if allowed.
+pub fn editor(
+    ui: &mut egui::Ui,
+    lock: &mut PreferencesLock,
+    passphrase_input: &mut String,
+) {
+    let now = Local::now();
+
+    if lock.is_locked(now) {
+        ui.label("Durations are locked for the rest of the day.");
+
+        match lock.release.clone() {
+            LockRelease::Never => {}
+            LockRelease::Delay(minutes) => match lock.release_time() {
+                Some(time) => {
+                    ui.label(format!("Unlocking at {}", time.format("%H:%M")));
+                }
+                None => {
+                    let label = format!("Unlock in {} min", minutes);
+                    if ui.button(label).clicked() {
+                        lock.request_release(now);
+                    }
+                }
+            },
+            LockRelease::Passphrase(_) => {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(passphrase_input)
+                            .password(true),
+                    );
+                    if ui.button("Unlock").clicked() {
+                        lock.release_with_passphrase(passphrase_input);
+                        passphrase_input.clear();
+                    }
+                });
+            }
+        }
+
+        return;
+    }
+
+    ui.label("Allow unlocking early:");
+
+    let never = matches!(lock.release, LockRelease::Never);
+    if ui.radio(never, "Never").clicked() {
+        lock.release = LockRelease::Never;
+    }
+
+    ui.horizontal(|ui| {
+        let delay = matches!(lock.release, LockRelease::Delay(_));
+        if ui.radio(delay, "After a delay").clicked() && !delay {
+            lock.release = LockRelease::default();
+        }
+        if let LockRelease::Delay(minutes) = &mut lock.release {
+            ui.add(
+                egui::DragValue::new(minutes)
+                    .clamp_range(1..=240)
+                    .suffix(" min"),
+            );
+        }
+    });
+
+    ui.horizontal(|ui| {
+        let passphrase = matches!(lock.release, LockRelease::Passphrase(_));
+        if ui.radio(passphrase, "With a passphrase").clicked() && !passphrase {
+            lock.release = LockRelease::Passphrase(String::new());
+        }
+        if let LockRelease::Passphrase(passphrase) = &mut lock.release {
+            ui.add(egui::TextEdit::singleline(passphrase).password(true));
+        }
+    });
+
+    ui.scope(|ui| {
+        ui.set_enabled(lock.can_lock());
+        if ui.button("Lock until end of day").clicked() {
+            lock.lock_for_today(now);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_lock() {
+        let afternoon = Local.ymd(2021, 10, 1).and_hms(14, 0, 0);
+        let evening = Local.ymd(2021, 10, 1).and_hms(23, 30, 0);
+        let tomorrow = Local.ymd(2021, 10, 2).and_hms(8, 0, 0);
+
+        let mut lock = PreferencesLock::default();
+        assert!(!lock.is_locked(afternoon));
+
+        // test expiry
+        lock.lock_for_today(afternoon);
+        assert!(lock.is_locked(afternoon));
+        assert!(lock.is_locked(evening));
+        assert!(!lock.is_locked(tomorrow));
+
+        // test delayed release
+        lock.release = LockRelease::Delay(15);
+        lock.request_release(afternoon);
+        assert!(lock.is_locked(afternoon + Duration::minutes(14)));
+        assert!(!lock.is_locked(afternoon + Duration::minutes(15)));
+
+        // test passphrase release
+        lock.release = LockRelease::Passphrase(String::new());
+        assert!(!lock.can_lock());
+        lock.release = LockRelease::Passphrase("let me out".to_owned());
+        assert!(lock.can_lock());
+        lock.lock_for_today(afternoon);
+        assert!(!lock.release_with_passphrase("please"));
+        assert!(lock.is_locked(afternoon));
+        assert!(lock.release_with_passphrase("let me out"));
+        assert!(!lock.is_locked(afternoon));
+    }
+}
@@ -0,0 +1,13 @@
+/// Add a labelled slider for a value to a `Ui`, optionally with a suffix.
+macro_rules! slider {
+    ($ui:ident, $val:expr, $name:expr, $range:expr) => {
+        $ui.add(::eframe::egui::Slider::new(&mut $val, $range).text($name));
+    };
+    ($ui:ident, $val:expr, $name:expr, $range:expr, $suffix:expr) => {
+        $ui.add(
+            ::eframe::egui::Slider::new(&mut $val, $range)
+                .text($name)
+                .suffix($suffix),
+        );
+    };
+}
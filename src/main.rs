@@ -7,6 +7,29 @@ use eframe::egui::vec2;
 fn main() {
     env_logger::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("ctl") {
+        #[cfg(unix)]
+        std::process::exit(time_flo::ctl::run(&args[1..]));
+
+        #[cfg(not(unix))]
+        {
+            eprintln!("time-flo: ctl is only supported on Unix");
+            std::process::exit(1);
+        }
+    }
+
+    if args.first().map(String::as_str) == Some("--display") {
+        #[cfg(unix)]
+        run_display(&args[1..]);
+
+        #[cfg(not(unix))]
+        {
+            eprintln!("time-flo: --display is only supported on Unix");
+            std::process::exit(1);
+        }
+    }
+
     let app = time_flo::TimeFloApp::default();
     let native_options = eframe::NativeOptions {
         initial_window_size: Some(vec2(300., 200.)),
@@ -15,3 +38,36 @@ fn main() {
     };
     eframe::run_native(Box::new(app), native_options);
 }
+
+/// Show another instance's timer full screen, as in `time-flo --display
+/// desk.local:7315 [--token TOKEN]`.
+#[cfg(unix)]
+fn run_display(args: &[String]) -> ! {
+    use time_flo::display::{DisplayApp, Source};
+
+    let token = std::env::var("TIME_FLO_TOKEN").unwrap_or_default();
+    let source = match args {
+        [address] => Source::parse(address, token),
+        [address, flag, token] if flag == "--token" => {
+            Source::parse(address, token.clone())
+        }
+        _ => None,
+    };
+    let source = match source {
+        Some(source) => source,
+        None => {
+            eprintln!(
+                "Usage: time-flo --display <host>[:port] [--token TOKEN]\n\n\
+                 The token may also be given in TIME_FLO_TOKEN."
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let native_options = eframe::NativeOptions {
+        maximized: true,
+        decorated: false,
+        ..Default::default()
+    };
+    eframe::run_native(Box::new(DisplayApp::new(source)), native_options);
+}
@@ -0,0 +1,143 @@
+//! Advertising the HTTP server on the local network with mDNS, so that
+//! companion clients can find it without being told its address.
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+
+/// The DNS-SD service type of the HTTP server.
+pub const SERVICE_TYPE: &str = "_time-flo._tcp.local.";
+
+/// The name of this machine, as advertised.
+fn hostname() -> String {
+    fs::read_to_string("/etc/hostname")
+        .ok()
+        .and_then(|name| {
+            let name = name.trim().split('.').next()?.to_owned();
+            Some(name).filter(|name| !name.is_empty())
+        })
+        .unwrap_or_else(|| "time-flo".to_owned())
+}
+
+/// Advertises the HTTP server until dropped.
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertisement {
+    pub fn start(ip: &str, port: u16) -> crate::Result<Self> {
+        let host = hostname();
+
+        let mut properties = HashMap::new();
+        properties.insert("api".to_owned(), "/api/".to_owned());
+        properties
+            .insert("version".to_owned(), env!("CARGO_PKG_VERSION").to_owned());
+
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &host,
+            &format!("{}.local.", host),
+            ip,
+            port,
+            Some(properties),
+        )?;
+        let fullname = info.get_fullname().to_owned();
+
+        let daemon = ServiceDaemon::new()?;
+        daemon.register(info)?;
+
+        Ok(Self { daemon, fullname })
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// A TimeFlo instance found on the local network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub addresses: Vec<IpAddr>,
+    pub port: u16,
+}
+
+impl Instance {
+    /// The address of the instance's HTTP server, if it has one.
+    pub fn url(&self) -> Option<String> {
+        let ip = self.addresses.first()?;
+        Some(format!("http://{}:{}/", ip, self.port))
+    }
+}
+
+/// The instance name from a full service name, such as `desk` from
+/// `desk._time-flo._tcp.local.`.
+fn instance_name(fullname: &str) -> &str {
+    fullname
+        .strip_suffix(SERVICE_TYPE)
+        .map(|name| name.trim_end_matches('.'))
+        .unwrap_or(fullname)
+}
+
+/// Look for instances on the local network for the given time.
+pub fn discover(timeout: Duration) -> crate::Result<Vec<Instance>> {
+    let daemon = ServiceDaemon::new()?;
+    let receiver = daemon.browse(SERVICE_TYPE)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut found: Vec<Instance> = Vec::new();
+    while let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+        let info = match receiver.recv_timeout(wait) {
+            Ok(ServiceEvent::ServiceResolved(info)) => info,
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+
+        let mut addresses: Vec<IpAddr> =
+            info.get_addresses().iter().map(|&ip| ip.into()).collect();
+        addresses.sort();
+        let instance = Instance {
+            name: instance_name(info.get_fullname()).to_owned(),
+            addresses,
+            port: info.get_port(),
+        };
+        // services are often announced more than once
+        if !found.contains(&instance) {
+            found.push(instance);
+        }
+    }
+
+    let _ = daemon.shutdown();
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance() {
+        assert_eq!("desk", instance_name("desk._time-flo._tcp.local."));
+        assert_eq!("other.local.", instance_name("other.local."));
+
+        let mut instance = Instance {
+            name: "desk".to_owned(),
+            addresses: vec!["192.168.1.20".parse().unwrap()],
+            port: 7315,
+        };
+        assert_eq!(
+            Some("http://192.168.1.20:7315/".to_owned()),
+            instance.url()
+        );
+
+        instance.addresses.clear();
+        assert_eq!(None, instance.url());
+    }
+}
@@ -0,0 +1,191 @@
+//! Totals from the history and the state of the timer, for graphing focus
+//! habits in monitoring systems.
+
+use std::fmt::{Display, Write};
+
+use crate::history::{EntryStatus, History};
+use crate::State;
+
+/// Counts over the whole history, and the state of the timer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Task intervals completed, including interrupted ones.
+    pub completed: u64,
+    pub voided: u64,
+    pub skipped: u64,
+    /// Pauses during recorded task intervals.
+    pub interruptions: u64,
+    /// Time spent in completed task intervals.
+    pub focus_seconds: u64,
+    pub state: State,
+    pub remaining_secs: u64,
+    pub paused: bool,
+    /// Today's focus score, from 0 to 100.
+    pub focus_score: u32,
+}
+
+impl Metrics {
+    /// The totals from the history, with the timer left idle.
+    pub fn from_history(history: &History) -> Self {
+        let mut metrics = Metrics {
+            skipped: history.skipped().len() as u64,
+            ..Default::default()
+        };
+
+        for entry in history.entries() {
+            metrics.interruptions += entry.interruptions.total as u64;
+            if entry.status == EntryStatus::Voided {
+                metrics.voided += 1;
+            } else {
+                metrics.completed += 1;
+                metrics.focus_seconds +=
+                    (entry.end - entry.start).num_seconds().max(0) as u64;
+            }
+        }
+
+        metrics
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name, kind, help, value: &dyn Display| {
+            let _ = write!(
+                out,
+                "# HELP time_flo_{name} {help}\n\
+                 # TYPE time_flo_{name} {kind}\n\
+                 time_flo_{name} {value}\n",
+                name = name,
+                kind = kind,
+                help = help,
+                value = value
+            );
+        };
+
+        metric(
+            "completed_total",
+            "counter",
+            "Task intervals completed.",
+            &self.completed,
+        );
+        metric(
+            "voided_total",
+            "counter",
+            "Task intervals voided.",
+            &self.voided,
+        );
+        metric(
+            "skipped_total",
+            "counter",
+            "Task intervals skipped before they ended.",
+            &self.skipped,
+        );
+        metric(
+            "interruptions_total",
+            "counter",
+            "Pauses during task intervals.",
+            &self.interruptions,
+        );
+        metric(
+            "focus_seconds_total",
+            "counter",
+            "Time spent in completed task intervals.",
+            &self.focus_seconds,
+        );
+        metric(
+            "remaining_seconds",
+            "gauge",
+            "Time left in the current interval.",
+            &self.remaining_secs,
+        );
+        metric(
+            "paused",
+            "gauge",
+            "Whether the timer is paused.",
+            &(self.paused as u8),
+        );
+        metric(
+            "focus_score",
+            "gauge",
+            "Today's focus score, from 0 to 100.",
+            &self.focus_score,
+        );
+
+        out.push_str(
+            "# HELP time_flo_state The current state of the timer.\n\
+             # TYPE time_flo_state gauge\n",
+        );
+        for (state, label) in [
+            (State::Idle, "idle"),
+            (State::Task, "task"),
+            (State::ShortBreak, "short_break"),
+            (State::LongBreak, "long_break"),
+        ] {
+            let _ = writeln!(
+                out,
+                "time_flo_state{{state=\"{}\"}} {}",
+                label,
+                (self.state == state) as u8
+            );
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{Entry, Interruptions};
+    use chrono::{Duration, Local, TimeZone};
+
+    #[test]
+    fn test_metrics() {
+        let start = Local.ymd(2021, 11, 1).and_hms(9, 0, 0);
+        let entry = |status| Entry {
+            start,
+            end: start + Duration::minutes(25),
+            task: String::new(),
+            manual: false,
+            interruptions: Interruptions {
+                total: 2,
+                ..Default::default()
+            },
+            status,
+            applications: Vec::new(),
+            snoozed_minutes: 0,
+            note: String::new(),
+            context: String::new(),
+            pauses: Vec::new(),
+        };
+        let history = History::from_parts(
+            vec![
+                entry(EntryStatus::Completed),
+                entry(EntryStatus::Interrupted),
+                entry(EntryStatus::Voided),
+            ],
+            vec![start],
+            Vec::new(),
+        );
+
+        let metrics = Metrics {
+            state: State::ShortBreak,
+            remaining_secs: 120,
+            ..Metrics::from_history(&history)
+        };
+        assert_eq!(2, metrics.completed);
+        assert_eq!(1, metrics.voided);
+        assert_eq!(1, metrics.skipped);
+        assert_eq!(6, metrics.interruptions);
+        assert_eq!(50 * 60, metrics.focus_seconds);
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains(
+            "# TYPE time_flo_completed_total counter\n\
+             time_flo_completed_total 2\n"
+        ));
+        assert!(text.contains("time_flo_remaining_seconds 120\n"));
+        assert!(text.contains("time_flo_state{state=\"short_break\"} 1\n"));
+        assert!(text.contains("time_flo_state{state=\"task\"} 0\n"));
+    }
+}
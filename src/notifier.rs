@@ -0,0 +1,97 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use notify_rust::Notification;
+
+/// Number of attempts to make at showing a notification.
+const MAX_ATTEMPTS: u32 = 4;
+/// Delay before the first retry, doubling for each one after.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Identical notifications sent within this window are dropped.
+const DEDUPE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Delivers desktop notifications, retrying with backoff in case the
+/// notification daemon is briefly unavailable, and dropping duplicates sent
+/// in quick succession.
+#[derive(Default)]
+pub struct Notifier {
+    /// The last notification sent, and when.
+    last_sent: Option<(String, Instant)>,
+}
+
+impl Notifier {
+    /// Send a notification in the background.
+    pub fn send(&mut self, body: &str) {
+        if !self.should_send(body, Instant::now()) {
+            return;
+        }
+
+        let body = body.to_owned();
+        thread::spawn(move || {
+            for attempt in 0..MAX_ATTEMPTS {
+                let result = Notification::new()
+                    .summary("TimeFlo")
+                    .body(&body)
+                    .timeout(10000)
+                    .show();
+
+                match result {
+                    Ok(_) => return,
+                    Err(err) => {
+                        warn!(
+                            "Could not show notification (attempt {}): {:?}",
+                            attempt + 1,
+                            err
+                        );
+                        if attempt + 1 < MAX_ATTEMPTS {
+                            thread::sleep(retry_delay(attempt));
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn should_send(&mut self, body: &str, now: Instant) -> bool {
+        let duplicate = match &self.last_sent {
+            Some((last_body, sent_at)) => {
+                last_body == body
+                    && now.duration_since(*sent_at) < DEDUPE_WINDOW
+            }
+            None => false,
+        };
+
+        if !duplicate {
+            self.last_sent = Some((body.to_owned(), now));
+        }
+
+        !duplicate
+    }
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_DELAY * 2u32.pow(attempt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifier() {
+        let mut notifier = Notifier::default();
+        let now = Instant::now();
+
+        // test deduplication
+        assert!(notifier.should_send("Break time", now));
+        assert!(!notifier.should_send("Break time", now));
+        assert!(notifier.should_send("Task time", now));
+        assert!(notifier.should_send("Break time", now));
+        assert!(notifier.should_send("Break time", now + DEDUPE_WINDOW));
+
+        // test backoff
+        assert_eq!(Duration::from_millis(500), retry_delay(0));
+        assert_eq!(Duration::from_millis(2000), retry_delay(2));
+    }
+}
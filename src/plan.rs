@@ -0,0 +1,567 @@
+//! Planning the day as a queue of tasks, which can be saved as templates.
+
+use std::iter;
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use eframe::egui::{self, Color32};
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::{CycleRules, Schedule, Scheduler, State};
+use crate::time_of_day::TimeOfDay;
+
+/// Color setting admin blocks apart from deep work.
+pub const ADMIN_COLOR: Color32 = Color32::from_rgb(0x9a, 0x7a, 0xd9);
+
+/// A task waiting to be worked on.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct QueuedTask {
+    pub name: String,
+    /// Number of task intervals the task is expected to take.
+    pub estimate: u32,
+    /// Number of task intervals spent on it so far.
+    #[serde(default)]
+    pub completed: u32,
+}
+
+impl QueuedTask {
+    pub fn new(name: impl Into<String>, estimate: u32) -> Self {
+        Self {
+            name: name.into(),
+            estimate,
+            completed: 0,
+        }
+    }
+
+    /// Number of task intervals still expected.
+    pub fn remaining(&self) -> u32 {
+        self.estimate.saturating_sub(self.completed)
+    }
+}
+
+/// The tasks planned for the day, worked on in order.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TaskQueue {
+    pub tasks: Vec<QueuedTask>,
+}
+
+impl TaskQueue {
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// The task currently being worked on.
+    pub fn current(&self) -> Option<&QueuedTask> {
+        self.tasks.first()
+    }
+
+    /// The task after the current one.
+    pub fn next(&self) -> Option<&QueuedTask> {
+        self.tasks.get(1)
+    }
+
+    /// Count a completed task interval towards the current task, moving on
+    /// to the next task once its estimate is used up. Returns the task which
+    /// was finished, if any.
+    pub fn complete_interval(&mut self) -> Option<QueuedTask> {
+        if self.count_interval() {
+            self.advance()
+        } else {
+            None
+        }
+    }
+
+    /// Count a completed task interval towards the current task, returning
+    /// whether its estimate is used up.
+    pub fn count_interval(&mut self) -> bool {
+        match self.tasks.first_mut() {
+            Some(current) => {
+                current.completed += 1;
+                current.remaining() == 0
+            }
+            None => false,
+        }
+    }
+
+    /// Move on from the current task, returning it.
+    pub fn advance(&mut self) -> Option<QueuedTask> {
+        if self.tasks.is_empty() {
+            None
+        } else {
+            Some(self.tasks.remove(0))
+        }
+    }
+
+    /// Number of task intervals expected for the whole queue.
+    pub fn remaining(&self) -> u32 {
+        self.tasks.iter().map(QueuedTask::remaining).sum()
+    }
+
+    /// When the queue is expected to be done, working through the task
+    /// intervals still expected from `now` with breaks in between, and only
+    /// starting tasks within working hours. `under_way` is the time left in
+    /// the current interval, if it has started.
+    pub fn finish_time(
+        &self,
+        mut scheduler: Scheduler,
+        rules: &CycleRules,
+        mut under_way: Option<Duration>,
+        (work_start, work_end): (TimeOfDay, TimeOfDay),
+        duration: impl Fn(&Scheduler, &str) -> Duration,
+        now: NaiveDateTime,
+    ) -> Option<NaiveDateTime> {
+        let add = |at: NaiveDateTime, duration: Duration| {
+            at + chrono::Duration::from_std(duration)
+                .unwrap_or_else(|_| chrono::Duration::zero())
+        };
+        // tasks only start within working hours, if there are any
+        let within_hours = |at: NaiveDateTime| {
+            let time = TimeOfDay::from(at.time());
+            let day = if work_start >= work_end || time >= work_start {
+                if work_start >= work_end || time < work_end {
+                    return at;
+                }
+                at.date().succ()
+            } else {
+                at.date()
+            };
+            day.and_hms(work_start.hour, work_start.minute, 0)
+        };
+
+        let mut intervals = self.tasks.iter().flat_map(|task| {
+            iter::repeat(task.name.as_str()).take(task.remaining() as usize)
+        });
+        if scheduler.current() == State::Idle {
+            scheduler.enter(rules, State::Task, Duration::ZERO);
+        }
+
+        let mut at = now;
+        let mut finish = None;
+        loop {
+            match scheduler.current() {
+                State::Task => {
+                    let task = match intervals.next() {
+                        Some(task) => task,
+                        None => break,
+                    };
+                    let task_duration = duration(&scheduler, task);
+                    at = match under_way.take() {
+                        Some(left) => add(at, left),
+                        None => add(within_hours(at), task_duration),
+                    };
+                    finish = Some(at);
+                    scheduler.advance(rules, task_duration);
+                }
+                _ => {
+                    let left = under_way.take();
+                    at = add(
+                        at,
+                        left.unwrap_or_else(|| duration(&scheduler, "")),
+                    );
+                    scheduler.advance(rules, Duration::ZERO);
+                }
+            }
+        }
+
+        finish
+    }
+}
+
+/// A task whose intervals last longer or shorter than usual, such as 15
+/// minutes for "Email triage".
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TaskDuration {
+    /// Name of the task, ignoring case.
+    pub task: String,
+    pub minutes: f32,
+}
+
+impl Default for TaskDuration {
+    fn default() -> Self {
+        Self {
+            task: String::new(),
+            minutes: 25.,
+        }
+    }
+}
+
+/// The task interval duration set for the given task, if any.
+pub fn duration_for(
+    durations: &[TaskDuration],
+    task: &str,
+) -> Option<Duration> {
+    let task = task.trim();
+    durations
+        .iter()
+        .find(|d| !task.is_empty() && d.task.trim().eq_ignore_ascii_case(task))
+        .map(|d| Duration::from_secs_f32(d.minutes.max(0.) * 60.))
+}
+
+/// Recurring time-boxed admin blocks, such as two 15-minute "Inbox zero"
+/// blocks a day, which the planner fits in between the tasks of the plan.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdminBlocks {
+    pub enabled: bool,
+    /// Name of the task the blocks are planned as, ignoring case.
+    pub task: String,
+    pub per_day: u32,
+    pub minutes: f32,
+}
+
+impl Default for AdminBlocks {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            task: "Inbox zero".to_owned(),
+            per_day: 2,
+            minutes: 15.,
+        }
+    }
+}
+
+impl AdminBlocks {
+    /// Whether the given task is an admin block.
+    pub fn is_admin(&self, task: &str) -> bool {
+        let task = task.trim();
+        self.enabled
+            && !task.is_empty()
+            && self.task.trim().eq_ignore_ascii_case(task)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f32(self.minutes.max(0.) * 60.)
+    }
+
+    /// Plan the blocks still due today, given the number already done: the
+    /// first at the front of the queue, and the rest spread evenly between
+    /// the task intervals planned. Blocks planned before are replaced.
+    pub fn schedule(&self, queue: &mut TaskQueue, done: u32) {
+        queue.tasks.retain(|task| !self.is_admin(&task.name));
+        let due = self.per_day.saturating_sub(done);
+        if !self.enabled || due == 0 {
+            return;
+        }
+
+        let planned = queue.remaining();
+        let mut tasks = Vec::with_capacity(queue.tasks.len() + due as usize);
+        let mut intervals = 0;
+        let mut block = 0;
+        for task in queue.tasks.drain(..) {
+            while block < due && block * planned <= intervals * due {
+                tasks.push(QueuedTask::new(self.task.trim(), 1));
+                block += 1;
+            }
+            intervals += task.remaining();
+            tasks.push(task);
+        }
+        for _ in block..due {
+            tasks.push(QueuedTask::new(self.task.trim(), 1));
+        }
+        queue.tasks = tasks;
+    }
+}
+
+/// Unfinished tasks set aside for another day.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct CarryOver {
+    /// The day the tasks are set aside for.
+    pub date: Option<NaiveDate>,
+    pub tasks: Vec<QueuedTask>,
+}
+
+impl CarryOver {
+    /// Set aside the given tasks for a later day, after any already set aside.
+    pub fn add(&mut self, date: NaiveDate, tasks: Vec<QueuedTask>) {
+        self.date = Some(self.date.map_or(date, |d| d.min(date)));
+        self.tasks.extend(tasks);
+    }
+
+    /// Take the tasks set aside, if their day has come.
+    pub fn take_due(&mut self, today: NaiveDate) -> Vec<QueuedTask> {
+        match self.date {
+            Some(date) if date <= today => std::mem::take(self).tasks,
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A saved day plan, e.g. "Deep Work Friday".
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Template {
+    pub name: String,
+    /// Profile to switch to, or `None` to keep the current selection.
+    pub profile: Option<String>,
+    /// Order of intervals to follow, or `None` to keep the current one.
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    pub tasks: Vec<QueuedTask>,
+}
+
+impl Template {
+    /// A fresh queue of the template's tasks.
+    pub fn queue(&self) -> TaskQueue {
+        TaskQueue {
+            tasks: self
+                .tasks
+                .iter()
+                .map(|task| QueuedTask::new(task.name.clone(), task.estimate))
+                .collect(),
+        }
+    }
+}
+
+/// Edit the tasks with their own task interval durations.
+pub fn task_durations_editor(
+    ui: &mut egui::Ui,
+    durations: &mut Vec<TaskDuration>,
+) {
+    let mut removed = None;
+
+    egui::Grid::new("task_durations").show(ui, |ui| {
+        for (i, duration) in durations.iter_mut().enumerate() {
+            ui.add(
+                egui::TextEdit::singleline(&mut duration.task)
+                    .hint_text("Task")
+                    .desired_width(120.),
+            );
+            ui.add(
+                egui::Slider::new(&mut duration.minutes, 0.5..=120.0)
+                    .suffix(" min"),
+            );
+            if ui.button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(i) = removed {
+        durations.remove(i);
+    }
+
+    if ui.button("Add task duration").clicked() {
+        durations.push(TaskDuration::default());
+    }
+}
+
+/// Edit the admin blocks planned every day, returning whether they changed.
+pub fn admin_blocks_editor(ui: &mut egui::Ui, admin: &mut AdminBlocks) -> bool {
+    let before = admin.clone();
+
+    ui.checkbox(&mut admin.enabled, "Plan time-boxed admin blocks every day");
+    if admin.enabled {
+        ui.horizontal(|ui| {
+            ui.label("Task");
+            ui.add(
+                egui::TextEdit::singleline(&mut admin.task)
+                    .hint_text("Inbox zero")
+                    .desired_width(120.),
+            );
+        });
+        slider!(ui, admin.per_day, "Blocks per day", 1..=8);
+        slider!(ui, admin.minutes, "Block length", 5.0..=60.0, " min");
+    }
+
+    *admin != before
+}
+
+/// Edit the queue of tasks, with their estimates and order.
+pub fn task_queue_editor(
+    ui: &mut egui::Ui,
+    tasks: &mut Vec<QueuedTask>,
+    admin: &AdminBlocks,
+) {
+    let mut removed = None;
+    let mut raised = None;
+
+    egui::Grid::new("task_queue").show(ui, |ui| {
+        for (i, task) in tasks.iter_mut().enumerate() {
+            if admin.is_admin(&task.name) {
+                ui.add(
+                    egui::Label::new(format!("\u{1F4E5} {}", task.name))
+                        .text_color(ADMIN_COLOR),
+                );
+            } else {
+                ui.label(&task.name);
+            }
+            ui.add(
+                egui::DragValue::new(&mut task.estimate)
+                    .clamp_range(1..=16)
+                    .suffix(" tasks"),
+            );
+            if i > 0 && ui.small_button("\u{2B06}").clicked() {
+                raised = Some(i);
+            }
+            if ui.small_button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(i) = raised {
+        tasks.swap(i - 1, i);
+    }
+    if let Some(i) = removed {
+        tasks.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let template = Template {
+            name: "Deep Work Friday".to_owned(),
+            profile: Some("Deep work".to_owned()),
+            schedule: None,
+            tasks: vec![
+                QueuedTask {
+                    completed: 1,
+                    ..QueuedTask::new("Write report", 2)
+                },
+                QueuedTask::new("Review", 1),
+            ],
+        };
+
+        // applying a template starts from scratch
+        let mut queue = template.queue();
+        assert_eq!(3, queue.remaining());
+        assert_eq!("Write report", queue.current().unwrap().name);
+
+        assert_eq!(None, queue.complete_interval());
+        assert_eq!("Review", queue.next().unwrap().name);
+        let finished = queue.complete_interval().unwrap();
+        assert_eq!("Write report", finished.name);
+        assert_eq!("Review", queue.current().unwrap().name);
+
+        queue.complete_interval();
+        assert!(queue.is_empty());
+        assert_eq!(None, queue.complete_interval());
+
+        // the queue is projected to be done after its breaks
+        let queue = TaskQueue {
+            tasks: vec![
+                QueuedTask::new("Write report", 2),
+                QueuedTask::new("Review", 1),
+            ],
+        };
+        let rules = CycleRules {
+            schedule: crate::scheduler::Schedule::Cycle,
+            long_break_trigger: crate::scheduler::LongBreakTrigger::ShortBreaks,
+            num_short_breaks: 2,
+            long_break_focus_minutes: 100.,
+            flow: None,
+        };
+        let hours = (TimeOfDay::new(9, 0), TimeOfDay::new(17, 0));
+        let minutes = |scheduler: &Scheduler, _: &str| {
+            let minutes = match scheduler.current() {
+                State::Task => 25,
+                State::ShortBreak => 5,
+                _ => 15,
+            };
+            Duration::from_secs(minutes * 60)
+        };
+        let day = NaiveDate::from_ymd(2021, 11, 5);
+        let finish = |scheduler, under_way, hour, minute| {
+            queue.finish_time(
+                scheduler,
+                &rules,
+                under_way,
+                hours,
+                minutes,
+                day.and_hms(hour, minute, 0),
+            )
+        };
+        assert_eq!(
+            Some(day.and_hms(10, 25, 0)),
+            finish(Scheduler::default(), None, 9, 0)
+        );
+        assert_eq!(
+            Some(day.and_hms(10, 10, 0)),
+            finish(
+                Scheduler::at(State::Task, 0),
+                Some(Duration::from_secs(10 * 60)),
+                9,
+                0
+            )
+        );
+        // and carries on the next working day
+        assert_eq!(
+            Some(day.succ().and_hms(9, 55, 0)),
+            finish(Scheduler::default(), None, 16, 50)
+        );
+        assert_eq!(
+            Some(day.and_hms(10, 25, 0)),
+            finish(Scheduler::default(), None, 7, 0)
+        );
+        assert_eq!(
+            None,
+            TaskQueue::default().finish_time(
+                Scheduler::default(),
+                &rules,
+                None,
+                hours,
+                minutes,
+                day.and_hms(9, 0, 0),
+            )
+        );
+
+        // tasks may have their own durations
+        let durations = vec![TaskDuration {
+            task: "Email triage".to_owned(),
+            minutes: 15.,
+        }];
+        assert_eq!(
+            Some(Duration::from_secs(15 * 60)),
+            duration_for(&durations, " email TRIAGE")
+        );
+        assert_eq!(None, duration_for(&durations, "Write report"));
+        assert_eq!(None, duration_for(&[TaskDuration::default()], ""));
+
+        // unfinished tasks come back the next day
+        let today = NaiveDate::from_ymd(2021, 11, 5);
+        let tomorrow = today.succ();
+        let mut carry_over = CarryOver::default();
+        carry_over.add(tomorrow, template.tasks.clone());
+        assert!(carry_over.take_due(today).is_empty());
+        assert_eq!(template.tasks, carry_over.take_due(tomorrow));
+        assert_eq!(CarryOver::default(), carry_over);
+
+        // admin blocks are spread through the day
+        let admin = AdminBlocks {
+            enabled: true,
+            ..AdminBlocks::default()
+        };
+        let mut queue = TaskQueue {
+            tasks: vec![
+                QueuedTask::new("Write report", 2),
+                QueuedTask::new("Review", 2),
+            ],
+        };
+        let names = |queue: &TaskQueue| {
+            queue
+                .tasks
+                .iter()
+                .map(|task| task.name.clone())
+                .collect::<Vec<_>>()
+        };
+        admin.schedule(&mut queue, 0);
+        assert_eq!(
+            vec!["Inbox zero", "Write report", "Inbox zero", "Review"],
+            names(&queue)
+        );
+        assert!(admin.is_admin("inbox ZERO "));
+        assert_eq!(Duration::from_secs(15 * 60), admin.duration());
+
+        // rescheduling replaces the blocks not yet started
+        admin.schedule(&mut queue, 1);
+        assert_eq!(vec!["Inbox zero", "Write report", "Review"], names(&queue));
+        admin.schedule(&mut queue, 2);
+        assert_eq!(vec!["Write report", "Review"], names(&queue));
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+/// How often the power source is re-checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether the machine is currently running on battery power.
+///
+/// This is only implemented on Linux, and always returns `false` elsewhere.
+#[cfg(target_os = "linux")]
+pub fn on_battery() -> bool {
+    use std::fs;
+
+    let entries = match fs::read_dir("/sys/class/power_supply") {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut has_battery = false;
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+
+        match kind.trim() {
+            "Battery" => has_battery = true,
+            "Mains" | "USB" => {
+                let online =
+                    fs::read_to_string(path.join("online")).unwrap_or_default();
+                if online.trim() == "1" {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    has_battery
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn on_battery() -> bool {
+    false
+}
+
+/// Caches the power source so that it is not queried every frame.
+#[derive(Default)]
+pub struct PowerMonitor {
+    on_battery: bool,
+    last_check: Option<Instant>,
+}
+
+impl PowerMonitor {
+    pub fn on_battery(&mut self) -> bool {
+        let should_check = self
+            .last_check
+            .map_or(true, |last| last.elapsed() >= CHECK_INTERVAL);
+
+        if should_check {
+            self.last_check = Some(Instant::now());
+            self.on_battery = on_battery();
+        }
+
+        self.on_battery
+    }
+}
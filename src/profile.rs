@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::State;
+use crate::time_of_day::{self, TimeOfDay};
+
+/// A named set of interval durations.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// Duration of a task interval in minutes.
+    pub task_minutes: f32,
+    /// Duration of a short break in minutes.
+    pub short_break_minutes: f32,
+    /// Duration of a long break in minutes.
+    pub long_break_minutes: f32,
+    /// Number of short breaks before a long break.
+    pub num_short_breaks: u32,
+}
+
+impl Profile {
+    pub fn preferred_duration(&self, state: State) -> Duration {
+        let minutes = match state {
+            State::Idle => 0.,
+            State::Task => self.task_minutes,
+            State::ShortBreak => self.short_break_minutes,
+            State::LongBreak => self.long_break_minutes,
+        };
+        Duration::from_secs_f32(minutes * 60.)
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_owned(),
+            task_minutes: 25.,
+            short_break_minutes: 5.,
+            long_break_minutes: 15.,
+            num_short_breaks: 3,
+        }
+    }
+}
+
+/// Which profile to use for upcoming intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProfileSelection {
+    /// Follow the profile rules.
+    Scheduled,
+    /// Use the default profile.
+    Default,
+    /// Use the profile with the given name.
+    Named(String),
+}
+
+impl Default for ProfileSelection {
+    fn default() -> Self {
+        ProfileSelection::Scheduled
+    }
+}
+
+impl ProfileSelection {
+    /// Resolve the name of the selected profile at the given time, or `None`
+    /// for the default profile.
+    pub fn resolve(
+        &self,
+        rules: &[ProfileRule],
+        now: &DateTime<Local>,
+    ) -> Option<String> {
+        match self {
+            ProfileSelection::Scheduled => {
+                scheduled_profile(rules, now).map(String::from)
+            }
+            ProfileSelection::Default => None,
+            ProfileSelection::Named(name) => Some(name.clone()),
+        }
+    }
+}
+
+/// Activates a profile on certain weekdays between certain times.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ProfileRule {
+    /// Name of the profile to activate.
+    pub profile: String,
+    /// Days on which the rule applies, starting from Monday.
+    pub weekdays: [bool; 7],
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl ProfileRule {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile,
+            weekdays: [true, true, true, true, true, false, false],
+            start: TimeOfDay::new(9, 0),
+            end: TimeOfDay::new(17, 0),
+        }
+    }
+
+    pub fn matches(&self, now: &DateTime<Local>) -> bool {
+        let weekday = now.weekday().num_days_from_monday() as usize;
+        let time = TimeOfDay::from(now.time());
+        self.weekdays[weekday] && self.start <= time && time < self.end
+    }
+}
+
+/// The profile scheduled for the given time by the first matching rule.
+pub fn scheduled_profile<'a>(
+    rules: &'a [ProfileRule],
+    now: &DateTime<Local>,
+) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(now))
+        .map(|rule| rule.profile.as_str())
+}
+
+/// Edit the named profiles.
+pub fn profiles_editor(ui: &mut egui::Ui, profiles: &mut Vec<Profile>) {
+    let mut removed = None;
+
+    for (i, profile) in profiles.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut profile.name);
+                if ui.button("\u{1F5D9}").clicked() {
+                    removed = Some(i);
+                }
+            });
+
+            slider!(ui, profile.task_minutes, "Task period", 0.5..=120.0);
+            slider!(
+                ui,
+                profile.short_break_minutes,
+                "Short break",
+                0.5..=120.0
+            );
+            slider!(ui, profile.long_break_minutes, "Long break", 0.5..=120.0);
+            slider!(ui, profile.num_short_breaks, "Short breaks", 1..=16);
+
+            ui.separator();
+        });
+    }
+
+    if let Some(i) = removed {
+        profiles.remove(i);
+    }
+
+    if ui.button("Add profile").clicked() {
+        profiles.push(Profile {
+            name: format!("Profile {}", profiles.len() + 1),
+            ..Default::default()
+        });
+    }
+}
+
+/// Edit the rules for activating profiles, one rule per group of rows.
+pub fn profile_rules_editor(
+    ui: &mut egui::Ui,
+    rules: &mut Vec<ProfileRule>,
+    profiles: &[Profile],
+) {
+    const WEEKDAYS: [&str; 7] = ["M", "T", "W", "T", "F", "S", "S"];
+
+    let mut removed = None;
+
+    for (i, rule) in rules.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("profile")
+                    .selected_text(rule.profile.clone())
+                    .show_ui(ui, |ui| {
+                        for profile in profiles {
+                            ui.selectable_value(
+                                &mut rule.profile,
+                                profile.name.clone(),
+                                profile.name.clone(),
+                            );
+                        }
+                    });
+
+                if ui.button("\u{1F5D9}").clicked() {
+                    removed = Some(i);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                for (day, enabled) in WEEKDAYS.iter().zip(&mut rule.weekdays) {
+                    if ui.selectable_label(*enabled, *day).clicked() {
+                        *enabled = !*enabled;
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                time_of_day::editor(ui, &mut rule.start);
+                ui.label("to");
+                time_of_day::editor(ui, &mut rule.end);
+            });
+        });
+    }
+
+    if let Some(i) = removed {
+        rules.remove(i);
+    }
+
+    if let Some(profile) = profiles.first() {
+        if ui.button("Add rule").clicked() {
+            rules.push(ProfileRule::new(profile.name.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_scheduled_profile() {
+        let rules = vec![
+            ProfileRule {
+                start: TimeOfDay::new(8, 0),
+                end: TimeOfDay::new(15, 0),
+                ..ProfileRule::new("Deep work".to_owned())
+            },
+            ProfileRule {
+                start: TimeOfDay::new(15, 0),
+                end: TimeOfDay::new(18, 30),
+                ..ProfileRule::new("Admin".to_owned())
+            },
+        ];
+
+        // 2021-10-01 was a Friday
+        let morning = Local.ymd(2021, 10, 1).and_hms(9, 15, 0);
+        let afternoon = Local.ymd(2021, 10, 1).and_hms(15, 0, 0);
+        let evening = Local.ymd(2021, 10, 1).and_hms(18, 30, 0);
+        let weekend = Local.ymd(2021, 10, 2).and_hms(9, 15, 0);
+
+        assert_eq!(Some("Deep work"), scheduled_profile(&rules, &morning));
+        assert_eq!(Some("Admin"), scheduled_profile(&rules, &afternoon));
+        assert_eq!(None, scheduled_profile(&rules, &evening));
+        assert_eq!(None, scheduled_profile(&rules, &weekend));
+
+        // test overriding the schedule
+        assert_eq!(None, ProfileSelection::Default.resolve(&rules, &morning));
+        assert_eq!(
+            Some("Admin".to_owned()),
+            ProfileSelection::Named("Admin".to_owned())
+                .resolve(&rules, &morning)
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::TimeFloError;
+
+/// Remote services to which alerts are pushed, for when the user has stepped
+/// away from their desk.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PushSettings {
+    pub matrix: MatrixSettings,
+    pub telegram: TelegramSettings,
+    pub ntfy: NtfySettings,
+}
+
+/// Sends messages to a Matrix room.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MatrixSettings {
+    pub enabled: bool,
+    /// Base URL of the homeserver, e.g. `https://matrix.org`.
+    pub homeserver: String,
+    /// Internal ID of the room, e.g. `!abcdef:matrix.org`.
+    pub room_id: String,
+    pub access_token: String,
+}
+
+/// Sends messages to a Telegram chat through a bot.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TelegramSettings {
+    pub enabled: bool,
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+/// Publishes messages to an ntfy topic.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NtfySettings {
+    pub enabled: bool,
+    /// Base URL of the ntfy server.
+    pub server: String,
+    pub topic: String,
+}
+
+impl Default for NtfySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server: "https://ntfy.sh".to_owned(),
+            topic: String::new(),
+        }
+    }
+}
+
+impl PushSettings {
+    /// Send a message to every enabled service in the background.
+    pub fn send(&self, message: &str) {
+        if self.matrix.enabled {
+            let matrix = self.matrix.clone();
+            let message = message.to_owned();
+            thread::spawn(move || {
+                if let Err(err) = matrix.send(&message) {
+                    warn!("Could not send Matrix message: {:?}", err);
+                }
+            });
+        }
+
+        if self.telegram.enabled {
+            let telegram = self.telegram.clone();
+            let message = message.to_owned();
+            thread::spawn(move || {
+                if let Err(err) = telegram.send(&message) {
+                    warn!("Could not send Telegram message: {:?}", err);
+                }
+            });
+        }
+
+        if self.ntfy.enabled {
+            let ntfy = self.ntfy.clone();
+            let message = message.to_owned();
+            thread::spawn(move || {
+                if let Err(err) = ntfy.send(&message) {
+                    warn!("Could not publish ntfy message: {:?}", err);
+                }
+            });
+        }
+    }
+}
+
+impl MatrixSettings {
+    fn send(&self, message: &str) -> crate::Result<()> {
+        // the transaction ID only needs to be unique for this access token
+        let txn_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let url = format!(
+            "{}/_matrix/client/r0/rooms/{}/send/m.room.message/timeflo-{}",
+            self.homeserver.trim_end_matches('/'),
+            encode_path_segment(self.room_id.trim()),
+            txn_id,
+        );
+
+        ureq::put(&url)
+            .set("Authorization", &format!("Bearer {}", self.access_token))
+            .send_json(serde_json::json!({
+                "msgtype": "m.text",
+                "body": message,
+            }))
+            .map_err(|err| TimeFloError::integration(url.as_str(), err))?;
+
+        Ok(())
+    }
+}
+
+impl TelegramSettings {
+    fn send(&self, message: &str) -> crate::Result<()> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            self.bot_token
+        );
+
+        ureq::post(&url)
+            .send_json(serde_json::json!({
+                "chat_id": self.chat_id,
+                "text": message,
+            }))
+            // don't leak the bot token into error messages
+            .map_err(|err| {
+                TimeFloError::integration("https://api.telegram.org", err)
+            })?;
+
+        Ok(())
+    }
+}
+
+impl NtfySettings {
+    fn send(&self, message: &str) -> crate::Result<()> {
+        let url = format!(
+            "{}/{}",
+            self.server.trim_end_matches('/'),
+            encode_path_segment(self.topic.trim())
+        );
+
+        ureq::post(&url)
+            .set("Title", "TimeFlo")
+            .set("Tags", "tomato")
+            .send_string(message)
+            .map_err(|err| TimeFloError::integration(url.as_str(), err))?;
+
+        Ok(())
+    }
+}
+
+/// Percent-encode text for use as a single segment of a URL path, such as a
+/// Matrix room ID, which starts with `!` and contains a `:`, or an ntfy
+/// topic.
+fn encode_path_segment(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Edit the settings for each remote push service.
+pub fn settings_editor(ui: &mut egui::Ui, push: &mut PushSettings) {
+    ui.checkbox(&mut push.matrix.enabled, "Matrix");
+    if push.matrix.enabled {
+        egui::Grid::new("matrix_settings").show(ui, |ui| {
+            ui.label("Homeserver");
+            ui.text_edit_singleline(&mut push.matrix.homeserver);
+            ui.end_row();
+
+            ui.label("Room ID");
+            ui.text_edit_singleline(&mut push.matrix.room_id);
+            ui.end_row();
+
+            ui.label("Access token");
+            ui.add(
+                egui::TextEdit::singleline(&mut push.matrix.access_token)
+                    .password(true),
+            );
+            ui.end_row();
+        });
+    }
+
+    ui.checkbox(&mut push.telegram.enabled, "Telegram");
+    if push.telegram.enabled {
+        egui::Grid::new("telegram_settings").show(ui, |ui| {
+            ui.label("Bot token");
+            ui.add(
+                egui::TextEdit::singleline(&mut push.telegram.bot_token)
+                    .password(true),
+            );
+            ui.end_row();
+
+            ui.label("Chat ID");
+            ui.text_edit_singleline(&mut push.telegram.chat_id);
+            ui.end_row();
+        });
+    }
+
+    ui.checkbox(&mut push.ntfy.enabled, "ntfy");
+    if push.ntfy.enabled {
+        egui::Grid::new("ntfy_settings").show(ui, |ui| {
+            ui.label("Server");
+            ui.text_edit_singleline(&mut push.ntfy.server);
+            ui.end_row();
+
+            ui.label("Topic");
+            ui.text_edit_singleline(&mut push.ntfy.topic);
+            ui.end_row();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(
+            "%21abc123%3Aexample.org",
+            encode_path_segment("!abc123:example.org")
+        );
+        assert_eq!("a%2Fb%20c%C3%A9", encode_path_segment("a/b cé"));
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::epi::RepaintSignal;
+
+/// Requests repaints at scheduled times from a background thread, so that the
+/// UI does not need to repaint every frame while waiting.
+pub struct RepaintScheduler {
+    sender: Sender<Instant>,
+}
+
+impl RepaintScheduler {
+    pub fn new(signal: Arc<dyn RepaintSignal>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Instant>();
+
+        thread::spawn(move || {
+            let mut deadline: Option<Instant> = None;
+
+            loop {
+                let message = match deadline {
+                    Some(deadline) => receiver.recv_timeout(
+                        deadline.saturating_duration_since(Instant::now()),
+                    ),
+                    None => receiver
+                        .recv()
+                        .map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match message {
+                    // keep the earliest requested repaint
+                    Ok(requested) => {
+                        deadline = Some(
+                            deadline.map_or(requested, |d| d.min(requested)),
+                        );
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        deadline = None;
+                        signal.request_repaint();
+                    }
+                    // the scheduler has been dropped
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Request a repaint after the given amount of time.
+    pub fn schedule(&self, wait: Duration) {
+        // the thread only stops once the scheduler is dropped
+        let _ = self.sender.send(Instant::now() + wait);
+    }
+}
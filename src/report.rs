@@ -0,0 +1,274 @@
+//! Monthly reports as standalone HTML pages, for showing where a month went.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::history::{data_dir, EntryStatus, History};
+
+/// Height of the bars of the daily chart at their tallest, in pixels.
+const CHART_HEIGHT: i64 = 120;
+/// Width taken up by each day of the daily chart, in pixels.
+const CHART_DAY_WIDTH: i64 = 20;
+
+/// Time spent on one context over a month, which stands in for a project.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectTime {
+    /// The context, or empty for intervals without one.
+    pub name: String,
+    pub intervals: u32,
+    pub minutes: i64,
+}
+
+/// Where the focus of a month went.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonthReport {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    /// Minutes of focus on each day of the month, from the first.
+    pub daily_minutes: Vec<i64>,
+    /// Time by project, most first.
+    pub projects: Vec<ProjectTime>,
+}
+
+impl MonthReport {
+    /// Gather the task intervals which ended in the month of the given day,
+    /// leaving out voided ones.
+    pub fn new(history: &History, date: NaiveDate) -> Self {
+        let month = date.with_day(1).unwrap();
+        let next = next_month(month);
+
+        let mut daily_minutes = vec![0; (next - month).num_days() as usize];
+        let mut projects: BTreeMap<&str, ProjectTime> = BTreeMap::new();
+        for entry in history.entries() {
+            let day = entry.end.date().naive_local();
+            if day < month || day >= next || entry.status == EntryStatus::Voided
+            {
+                continue;
+            }
+
+            daily_minutes[(day - month).num_days() as usize] += entry.minutes();
+            let name = entry.context.trim();
+            let project = projects.entry(name).or_insert_with(|| ProjectTime {
+                name: name.to_owned(),
+                ..Default::default()
+            });
+            project.intervals += 1;
+            project.minutes += entry.minutes();
+        }
+
+        // ties stay in order of name
+        let mut projects: Vec<_> =
+            projects.into_iter().map(|(_, project)| project).collect();
+        projects.sort_by(|a, b| b.minutes.cmp(&a.minutes));
+
+        Self {
+            month,
+            daily_minutes,
+            projects,
+        }
+    }
+
+    /// Minutes of focus over the whole month.
+    pub fn total_minutes(&self) -> i64 {
+        self.daily_minutes.iter().sum()
+    }
+
+    /// Write the report as a standalone HTML page, with its chart inline.
+    pub fn write_html(&self, mut writer: impl Write) -> io::Result<()> {
+        let title = self.month.format("%B %Y");
+        let intervals: u32 = self.projects.iter().map(|p| p.intervals).sum();
+        let days = self.daily_minutes.iter().filter(|&&m| m > 0).count();
+
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(writer, "<title>TimeFlo report for {}</title>", title)?;
+        writeln!(writer, "<style>{}</style>", STYLE)?;
+        writeln!(writer, "</head>\n<body>")?;
+        writeln!(writer, "<h1>{}</h1>", title)?;
+        writeln!(
+            writer,
+            "<p>{} of focus over {} intervals on {} days.</p>",
+            hours(self.total_minutes()),
+            intervals,
+            days
+        )?;
+
+        writeln!(writer, "<h2>Focus by day</h2>")?;
+        self.write_chart(&mut writer)?;
+
+        writeln!(writer, "<h2>Time by project</h2>")?;
+        writeln!(writer, "<table>")?;
+        writeln!(
+            writer,
+            "<tr><th>Project</th><th>Intervals</th><th>Hours</th>\
+             <th>Share</th></tr>"
+        )?;
+        let total = self.total_minutes().max(1);
+        for project in &self.projects {
+            let name = match project.name.as_str() {
+                "" => "No context".to_owned(),
+                name => escape(name),
+            };
+            let share = project.minutes * 100 / total;
+            writeln!(
+                writer,
+                "<tr><td>{}</td><td>{}</td><td>{}</td>\
+                 <td><div class=\"bar\" style=\"width: {}%\"></div>{}%</td>\
+                 </tr>",
+                name,
+                project.intervals,
+                hours(project.minutes),
+                share,
+                share
+            )?;
+        }
+        writeln!(writer, "</table>")?;
+        writeln!(writer, "</body>\n</html>")?;
+        Ok(())
+    }
+
+    /// Write a bar chart of the minutes of focus on each day as inline SVG.
+    fn write_chart(&self, mut writer: impl Write) -> io::Result<()> {
+        let most = self.daily_minutes.iter().copied().max().unwrap_or(0);
+        let width = self.daily_minutes.len() as i64 * CHART_DAY_WIDTH;
+        writeln!(
+            writer,
+            "<svg width=\"{}\" height=\"{}\" role=\"img\">",
+            width,
+            CHART_HEIGHT + 16
+        )?;
+        for (i, &minutes) in self.daily_minutes.iter().enumerate() {
+            let x = i as i64 * CHART_DAY_WIDTH;
+            let height = minutes * CHART_HEIGHT / most.max(1);
+            writeln!(
+                writer,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\
+                 <title>{}: {}</title></rect>",
+                x + 2,
+                CHART_HEIGHT - height,
+                CHART_DAY_WIDTH - 4,
+                height,
+                (self.month + chrono::Duration::days(i as i64))
+                    .format("%Y-%m-%d"),
+                hours(minutes)
+            )?;
+            writeln!(
+                writer,
+                "<text x=\"{}\" y=\"{}\">{}</text>",
+                x + CHART_DAY_WIDTH / 2,
+                CHART_HEIGHT + 12,
+                i + 1
+            )?;
+        }
+        writeln!(writer, "</svg>")
+    }
+
+    /// Export the report to `reports/YYYY-MM.html` in the data directory.
+    pub fn export_html(&self) -> crate::Result<PathBuf> {
+        let path = data_dir()
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no data directory")
+            })?
+            .join("reports")
+            .join(format!("{}.html", self.month.format("%Y-%m")));
+
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut writer = io::BufWriter::new(fs::File::create(&path)?);
+        self.write_html(&mut writer)?;
+        writer.flush()?;
+        Ok(path)
+    }
+}
+
+/// Styles for the report, kept simple enough to print.
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+    table { border-collapse: collapse; } \
+    th, td { padding: 0.3em 1em; border-bottom: 1px solid #ccc; \
+    text-align: left; } \
+    rect { fill: #4a90c2; } \
+    text { font-size: 9px; text-anchor: middle; fill: #666; } \
+    .bar { display: inline-block; height: 0.8em; margin-right: 0.5em; \
+    background: #4a90c2; }";
+
+/// The first day of the month after the given one.
+pub(crate) fn next_month(month: NaiveDate) -> NaiveDate {
+    if month.month() == 12 {
+        NaiveDate::from_ymd(month.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(month.year(), month.month() + 1, 1)
+    }
+}
+
+/// Minutes as hours and minutes, such as `2:05`.
+fn hours(minutes: i64) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Escape text for use in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EntryDraft;
+
+    #[test]
+    fn test_month_report() {
+        let mut history = History::default();
+        for (date, context, status) in [
+            ("2021-10-31", "Client A", EntryStatus::Completed),
+            ("2021-11-01", "Client A", EntryStatus::Completed),
+            ("2021-11-01", "Client <B>", EntryStatus::Completed),
+            ("2021-11-02", "Client <B>", EntryStatus::Interrupted),
+            ("2021-11-30", "", EntryStatus::Completed),
+            ("2021-11-30", "Client A", EntryStatus::Voided),
+        ] {
+            let mut entry = EntryDraft {
+                date: date.to_owned(),
+                start: "09:00".into(),
+                end: "09:25".into(),
+                task: "Write report".into(),
+            }
+            .parse()
+            .unwrap();
+            entry.context = context.to_owned();
+            entry.status = status;
+            history.add(entry);
+        }
+
+        let report =
+            MonthReport::new(&history, NaiveDate::from_ymd(2021, 11, 15));
+        assert_eq!(NaiveDate::from_ymd(2021, 11, 1), report.month);
+        assert_eq!(30, report.daily_minutes.len());
+        assert_eq!(50, report.daily_minutes[0]);
+        assert_eq!(25, report.daily_minutes[29]);
+        assert_eq!(100, report.total_minutes());
+
+        let names: Vec<_> =
+            report.projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(vec!["Client <B>", "", "Client A"], names);
+        assert_eq!(2, report.projects[0].intervals);
+
+        let mut html = Vec::new();
+        report.write_html(&mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<h1>November 2021</h1>"));
+        assert!(html.contains("1:40 of focus over 4 intervals on 3 days"));
+        assert!(html.contains("Client &lt;B&gt;"));
+        assert!(html.contains("No context"));
+
+        let december =
+            MonthReport::new(&history, NaiveDate::from_ymd(2021, 12, 1));
+        assert_eq!(31, december.daily_minutes.len());
+        assert_eq!(0, december.total_minutes());
+    }
+}
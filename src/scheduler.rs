@@ -0,0 +1,505 @@
+//! The cycle of tasks and breaks, kept apart from any frontend so that it can
+//! be tested and reused on its own.
+
+use std::fmt;
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::flow::{self, Flow, FlowStep};
+
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum State {
+    Idle,
+    Task,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::Idle
+    }
+}
+
+impl State {
+    pub fn is_break(&self) -> bool {
+        matches!(self, State::ShortBreak | State::LongBreak)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Idle => "Idle",
+            State::Task => "Task period",
+            State::ShortBreak => "Short break",
+            State::LongBreak => "Long break",
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What decides when it is time for a long break.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum LongBreakTrigger {
+    /// After a number of short breaks.
+    ShortBreaks,
+    /// After a number of minutes spent on tasks, however they were split up.
+    FocusedMinutes,
+}
+
+impl Default for LongBreakTrigger {
+    fn default() -> Self {
+        LongBreakTrigger::ShortBreaks
+    }
+}
+
+impl fmt::Display for LongBreakTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LongBreakTrigger::ShortBreaks => "Short breaks",
+            LongBreakTrigger::FocusedMinutes => "Focused minutes",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The order intervals follow.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Schedule {
+    /// A short break after each task, and a long break once the long break
+    /// trigger is reached.
+    Cycle,
+    /// The given intervals repeated in order, such as Task, Short break,
+    /// Task, Long break.
+    Sequence(Vec<State>),
+    /// The steps of the flow file, falling back to the cycle if there is
+    /// none.
+    Flow,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule::Cycle
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Schedule::Cycle => "Short and long breaks",
+            Schedule::Sequence(_) => "Custom sequence",
+            Schedule::Flow => "Flow file",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// The rules deciding which interval comes next.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CycleRules {
+    pub schedule: Schedule,
+    pub long_break_trigger: LongBreakTrigger,
+    /// Short breaks before a long break, when triggered by short breaks.
+    pub num_short_breaks: u32,
+    /// Minutes spent on tasks before a long break, when triggered by
+    /// focused minutes.
+    pub long_break_focus_minutes: f32,
+    /// The flow followed with the flow schedule, if it could be loaded.
+    pub flow: Option<Flow>,
+}
+
+impl CycleRules {
+    /// The steps of the custom sequence or flow being followed, as each
+    /// step's state and the step following it, or nothing for the cycle.
+    fn steps(&self) -> Vec<(State, usize)> {
+        match (&self.schedule, &self.flow) {
+            (Schedule::Sequence(steps), _) => steps
+                .iter()
+                .enumerate()
+                .map(|(i, &state)| (state, (i + 1) % steps.len()))
+                .collect(),
+            (Schedule::Flow, Some(flow)) => flow
+                .steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| (step.state, flow.successor(i)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Where the program is in the cycle of tasks and breaks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Scheduler {
+    state: State,
+    /// Number of short breaks which have occurred since the last long break,
+    /// or the start of the program.
+    short_break_counter: u32,
+    /// Time spent on tasks since the last long break.
+    #[serde(default, with = "serde_millis")]
+    focused_since_long_break: Duration,
+    /// Position in a custom sequence or flow.
+    #[serde(default)]
+    step: usize,
+}
+
+impl Scheduler {
+    /// A scheduler in the given state, after the given number of short
+    /// breaks.
+    pub fn at(state: State, cycle_position: u32) -> Self {
+        Self {
+            state,
+            short_break_counter: cycle_position,
+            ..Default::default()
+        }
+    }
+
+    /// The current state.
+    pub fn current(&self) -> State {
+        self.state
+    }
+
+    /// Number of short breaks since the last long break.
+    pub fn cycle_position(&self) -> u32 {
+        self.short_break_counter
+    }
+
+    /// Time spent on tasks since the last long break, not counting the
+    /// current interval.
+    pub fn focused_since_long_break(&self) -> Duration {
+        self.focused_since_long_break
+    }
+
+    /// The step of the flow the current interval belongs to, if one is
+    /// being followed.
+    pub fn flow_step<'a>(&self, rules: &'a CycleRules) -> Option<&'a FlowStep> {
+        match (&rules.schedule, &rules.flow) {
+            (Schedule::Flow, Some(flow)) if self.state != State::Idle => flow
+                .steps
+                .get(self.step)
+                .filter(|step| step.state == self.state),
+            _ => None,
+        }
+    }
+
+    /// The state following the current one, after the given time spent in
+    /// it.
+    pub fn next(&self, rules: &CycleRules, elapsed: Duration) -> State {
+        let steps = rules.steps();
+        if !steps.is_empty() {
+            return if self.state == State::Idle {
+                steps[0].0
+            } else {
+                steps[steps[self.step % steps.len()].1].0
+            };
+        }
+
+        match self.state {
+            State::Task => {
+                // is it time for a long break?
+                let long_break_due = match rules.long_break_trigger {
+                    LongBreakTrigger::ShortBreaks => {
+                        self.short_break_counter >= rules.num_short_breaks
+                    }
+                    LongBreakTrigger::FocusedMinutes => {
+                        let focused = self.focused_since_long_break + elapsed;
+                        focused.as_secs_f32() / 60.
+                            >= rules.long_break_focus_minutes
+                    }
+                };
+
+                if long_break_due {
+                    State::LongBreak
+                } else {
+                    State::ShortBreak
+                }
+            }
+            _ => State::Task,
+        }
+    }
+
+    /// Move to the given state, after the given time spent in the current
+    /// one.
+    pub fn enter(
+        &mut self,
+        rules: &CycleRules,
+        state: State,
+        elapsed: Duration,
+    ) {
+        // follow the sequence to the next interval in that state, which is
+        // further along if intervals were skipped
+        let steps = rules.steps();
+        if !steps.is_empty() {
+            let mut step = if self.state == State::Idle {
+                0
+            } else {
+                steps[self.step % steps.len()].1
+            };
+            for _ in 0..steps.len() {
+                if steps[step].0 == state {
+                    self.step = step;
+                    break;
+                }
+                step = steps[step].1;
+            }
+        }
+
+        // keep track of time spent on tasks for the long break trigger
+        if self.state == State::Task {
+            self.focused_since_long_break += elapsed;
+        }
+
+        match state {
+            State::ShortBreak => self.short_break_counter += 1,
+            State::LongBreak => {
+                self.short_break_counter = 0;
+                self.focused_since_long_break = Duration::ZERO;
+            }
+            _ => {}
+        }
+
+        self.state = state;
+    }
+
+    /// Move to the given state out of turn, leaving the place in the cycle as
+    /// it was.
+    pub fn jump(&mut self, state: State) {
+        self.state = state;
+    }
+
+    /// Move on to the next state, after the given time spent in the current
+    /// one, returning it.
+    pub fn advance(&mut self, rules: &CycleRules, elapsed: Duration) -> State {
+        let state = self.next(rules, elapsed);
+        self.enter(rules, state, elapsed);
+        state
+    }
+}
+
+/// Choose the order intervals follow, editing the steps of a custom
+/// sequence.
+pub fn schedule_editor(ui: &mut egui::Ui, schedule: &mut Schedule) {
+    let custom = matches!(schedule, Schedule::Sequence(_));
+    egui::ComboBox::from_label("Schedule")
+        .selected_text(schedule.to_string())
+        .show_ui(ui, |ui| {
+            if ui
+                .selectable_label(
+                    *schedule == Schedule::Cycle,
+                    Schedule::Cycle.to_string(),
+                )
+                .clicked()
+            {
+                *schedule = Schedule::Cycle;
+            }
+            if ui.selectable_label(custom, "Custom sequence").clicked()
+                && !custom
+            {
+                *schedule = Schedule::Sequence(vec![
+                    State::Task,
+                    State::ShortBreak,
+                    State::Task,
+                    State::LongBreak,
+                ]);
+            }
+            ui.selectable_value(
+                schedule,
+                Schedule::Flow,
+                Schedule::Flow.to_string(),
+            );
+        });
+
+    let steps = match schedule {
+        Schedule::Sequence(steps) => steps,
+        Schedule::Flow => {
+            ui.label(match flow::flow_path() {
+                Some(path) => format!("Following {}", path.display()),
+                None => "Define the flow in flow.toml in the config directory"
+                    .to_owned(),
+            });
+            return;
+        }
+        Schedule::Cycle => return,
+    };
+    let mut removed = None;
+
+    egui::Grid::new("schedule").show(ui, |ui| {
+        for (i, step) in steps.iter_mut().enumerate() {
+            ui.label(format!("{}.", i + 1));
+            egui::ComboBox::from_id_source(("schedule_step", i))
+                .selected_text(step.name())
+                .show_ui(ui, |ui| {
+                    for state in
+                        [State::Task, State::ShortBreak, State::LongBreak]
+                    {
+                        ui.selectable_value(step, state, state.name());
+                    }
+                });
+            if ui.button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(i) = removed {
+        steps.remove(i);
+    }
+
+    if ui.button("Add interval").clicked() {
+        steps.push(State::Task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    /// A long break after every two short breaks.
+    fn cycle_rules() -> CycleRules {
+        CycleRules {
+            schedule: Schedule::Cycle,
+            long_break_trigger: LongBreakTrigger::ShortBreaks,
+            num_short_breaks: 2,
+            long_break_focus_minutes: 60.,
+            flow: None,
+        }
+    }
+
+    /// Task, short break, task, task, long break, repeated.
+    fn sequence_rules() -> CycleRules {
+        CycleRules {
+            schedule: Schedule::Sequence(vec![
+                State::Task,
+                State::ShortBreak,
+                State::Task,
+                State::Task,
+                State::LongBreak,
+            ]),
+            ..cycle_rules()
+        }
+    }
+
+    #[test]
+    fn test_advance() {
+        let rules = cycle_rules();
+        let mut scheduler = Scheduler::at(State::Task, 0);
+        let mut states = Vec::new();
+        for _ in 0..6 {
+            states.push(scheduler.advance(&rules, minutes(25)));
+        }
+        assert_eq!(
+            vec![
+                State::ShortBreak,
+                State::Task,
+                State::ShortBreak,
+                State::Task,
+                State::LongBreak,
+                State::Task,
+            ],
+            states
+        );
+        assert_eq!(0, scheduler.cycle_position());
+    }
+
+    #[test]
+    fn test_focused_minutes() {
+        // long breaks may follow from the time focused instead
+        let rules = CycleRules {
+            long_break_trigger: LongBreakTrigger::FocusedMinutes,
+            ..cycle_rules()
+        };
+        let mut scheduler = Scheduler::at(State::Task, 0);
+        assert_eq!(State::ShortBreak, scheduler.advance(&rules, minutes(40)));
+        assert_eq!(1, scheduler.cycle_position());
+        assert_eq!(minutes(40), scheduler.focused_since_long_break());
+        assert_eq!(State::Task, scheduler.advance(&rules, minutes(5)));
+        assert_eq!(State::ShortBreak, scheduler.next(&rules, minutes(10)));
+        assert_eq!(State::LongBreak, scheduler.advance(&rules, minutes(20)));
+        assert_eq!(Duration::ZERO, scheduler.focused_since_long_break());
+    }
+
+    #[test]
+    fn test_sequence() {
+        // custom sequences repeat in order
+        let rules = sequence_rules();
+        let mut scheduler = Scheduler::default();
+        let mut states = Vec::new();
+        for _ in 0..6 {
+            states.push(scheduler.advance(&rules, minutes(25)));
+        }
+        assert_eq!(
+            vec![
+                State::Task,
+                State::ShortBreak,
+                State::Task,
+                State::Task,
+                State::LongBreak,
+                State::Task,
+            ],
+            states
+        );
+    }
+
+    #[test]
+    fn test_skip_ahead() {
+        // skipping ahead carries on from the interval entered
+        let rules = sequence_rules();
+        let mut scheduler = Scheduler::default();
+        scheduler.advance(&rules, Duration::ZERO);
+        scheduler.enter(&rules, State::LongBreak, minutes(5));
+        assert_eq!(State::Task, scheduler.next(&rules, Duration::ZERO));
+        assert_eq!(0, scheduler.cycle_position());
+    }
+
+    #[test]
+    fn test_jump() {
+        // jumps out of turn leave the cycle alone
+        let mut scheduler = Scheduler::at(State::Task, 1);
+        scheduler.jump(State::LongBreak);
+        assert_eq!(State::LongBreak, scheduler.current());
+        assert_eq!(1, scheduler.cycle_position());
+    }
+
+    #[test]
+    fn test_flow() {
+        // flows follow their transitions
+        let step = |name: &str, state, next: Option<&str>| FlowStep {
+            name: name.to_owned(),
+            state,
+            next: next.map(str::to_owned),
+            ..Default::default()
+        };
+        let rules = CycleRules {
+            schedule: Schedule::Flow,
+            flow: Some(Flow {
+                steps: vec![
+                    step("warm-up", State::Task, None),
+                    step("deep", State::Task, None),
+                    step("rest", State::LongBreak, Some("deep")),
+                ],
+            }),
+            ..cycle_rules()
+        };
+        let mut scheduler = Scheduler::default();
+        let mut names = Vec::new();
+        for _ in 0..5 {
+            scheduler.advance(&rules, minutes(25));
+            names.push(scheduler.flow_step(&rules).unwrap().name.clone());
+        }
+        assert_eq!(vec!["warm-up", "deep", "rest", "deep", "rest"], names);
+    }
+}
@@ -0,0 +1,70 @@
+/// How much of the focus score each component contributes.
+const GOAL_WEIGHT: f32 = 0.4;
+const SKIP_WEIGHT: f32 = 0.3;
+const INTERRUPTION_WEIGHT: f32 = 0.3;
+
+/// What happened to the task intervals of a single day.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DayStats {
+    /// Task intervals which ran to completion.
+    pub completed: u32,
+    /// Minutes spent in completed task intervals.
+    pub minutes: i64,
+    /// Task intervals which were skipped before they ended.
+    pub skipped: u32,
+    /// Task intervals which were voided.
+    pub voided: u32,
+    /// Times a task interval was paused.
+    pub interruptions: u32,
+    /// Pauses classified as internal distractions.
+    pub internal_interruptions: u32,
+    /// Pauses classified as external interruptions.
+    pub external_interruptions: u32,
+}
+
+impl DayStats {
+    /// A score from 0 to 100 summarizing how focused the day was, given a
+    /// daily goal of completed task intervals.
+    pub fn focus_score(&self, goal: u32) -> u32 {
+        if self.completed == 0 {
+            return 0;
+        }
+
+        let completed = self.completed as f32;
+        let goal_attainment = (completed / goal.max(1) as f32).min(1.);
+        let abandoned = (self.skipped + self.voided) as f32;
+        let completion_rate = completed / (completed + abandoned);
+        let uninterrupted = 1. / (1. + self.interruptions as f32 / completed);
+
+        let score = GOAL_WEIGHT * goal_attainment
+            + SKIP_WEIGHT * completion_rate
+            + INTERRUPTION_WEIGHT * uninterrupted;
+        (score * 100.).round() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_score() {
+        assert_eq!(0, DayStats::default().focus_score(8));
+
+        let perfect = DayStats {
+            completed: 8,
+            ..Default::default()
+        };
+        assert_eq!(100, perfect.focus_score(8));
+        assert_eq!(100, perfect.focus_score(4));
+        assert_eq!(80, perfect.focus_score(16));
+
+        let distracted = DayStats {
+            completed: 4,
+            skipped: 4,
+            interruptions: 4,
+            ..Default::default()
+        };
+        assert_eq!(50, distracted.focus_score(8));
+    }
+}
@@ -0,0 +1,99 @@
+//! A small file kept up to date with the state of the timer, which shell
+//! prompts, status lines, and editors can read cheaply.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::timer::Timer;
+use crate::State;
+
+/// Where the status file is written.
+pub fn path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("time-flo").join("status"))
+}
+
+/// The contents of the status file, such as `Task period 12:34` or
+/// `Short break 04:10 (paused)`.
+pub fn contents(state: State, timer: &Timer) -> String {
+    let mut line = format!("{} {}", state, timer);
+    if timer.is_paused() {
+        line.push_str(" (paused)");
+    }
+    line.push('\n');
+    line
+}
+
+/// Writes the status file whenever its contents change, and removes it when
+/// dropped so that a stale status is not left behind.
+pub struct StatusFile {
+    path: PathBuf,
+    written: Option<String>,
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            written: None,
+        }
+    }
+
+    pub fn update(&mut self, contents: String) -> io::Result<()> {
+        if self.written.as_ref() == Some(&contents) {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // write then rename, so readers never see a partial file
+        let partial = self.path.with_extension("partial");
+        fs::write(&partial, &contents)?;
+        fs::rename(&partial, &self.path)?;
+
+        self.written = Some(contents);
+        Ok(())
+    }
+}
+
+impl Drop for StatusFile {
+    fn drop(&mut self) {
+        if self.written.is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_status_file() {
+        let mut timer = Timer::from_duration(Duration::from_secs(754));
+        assert_eq!("Task period 12:34\n", contents(State::Task, &timer));
+
+        timer.start();
+        crate::clock::advance(Duration::from_secs(4));
+        timer.pause();
+        assert_eq!(
+            "Short break 12:30 (paused)\n",
+            contents(State::ShortBreak, &timer)
+        );
+
+        let path = std::env::temp_dir()
+            .join(format!("time-flo-status-{}", std::process::id()));
+        let mut file = StatusFile::new(path.clone());
+        file.update(contents(State::Task, &timer)).unwrap();
+        assert_eq!(
+            "Task period 12:30 (paused)\n",
+            fs::read_to_string(&path).unwrap()
+        );
+
+        drop(file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,722 @@
+//! Where the history is kept, behind a common interface so that users and
+//! packagers can choose a backend.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{
+    self, AppTime, Entry, EntryStatus, History, InterruptionKind,
+    Interruptions, LoggedInterruption, Pause,
+};
+use crate::TimeFloError;
+
+/// Loads and saves the history.
+pub trait HistoryStore {
+    /// Load the history, starting a new one if there is none yet.
+    fn load(&self) -> crate::Result<History>;
+    fn save(&self, history: &History) -> crate::Result<()>;
+}
+
+/// The available history backends.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum StoreBackend {
+    Json,
+    PlainText,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// JSON encrypted with a passphrase.
+    #[cfg(feature = "encryption")]
+    Encrypted,
+}
+
+impl Default for StoreBackend {
+    fn default() -> Self {
+        StoreBackend::Json
+    }
+}
+
+impl fmt::Display for StoreBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StoreBackend::Json => "JSON",
+            StoreBackend::PlainText => "Plain text",
+            #[cfg(feature = "sqlite")]
+            StoreBackend::Sqlite => "SQLite",
+            #[cfg(feature = "encryption")]
+            StoreBackend::Encrypted => "Encrypted JSON",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl StoreBackend {
+    pub const ALL: &'static [StoreBackend] = &[
+        StoreBackend::Json,
+        StoreBackend::PlainText,
+        #[cfg(feature = "sqlite")]
+        StoreBackend::Sqlite,
+        #[cfg(feature = "encryption")]
+        StoreBackend::Encrypted,
+    ];
+
+    /// Whether the backend needs a passphrase to be opened.
+    pub fn is_encrypted(self) -> bool {
+        #[cfg(feature = "encryption")]
+        if self == StoreBackend::Encrypted {
+            return true;
+        }
+
+        false
+    }
+
+    /// Open the store for this backend in the data directory, or `None` if
+    /// there is no data directory. The passphrase is only used by encrypted
+    /// backends.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    pub fn open(self, passphrase: &str) -> Option<Box<dyn HistoryStore>> {
+        let dir = history::data_dir()?;
+
+        Some(match self {
+            StoreBackend::Json => Box::new(JsonStore(dir.join("history.json"))),
+            StoreBackend::PlainText => {
+                Box::new(PlainTextStore(dir.join("history.txt")))
+            }
+            #[cfg(feature = "sqlite")]
+            StoreBackend::Sqlite => {
+                Box::new(SqliteStore(dir.join("history.sqlite3")))
+            }
+            #[cfg(feature = "encryption")]
+            StoreBackend::Encrypted => Box::new(EncryptedStore {
+                path: dir.join("history.json.age"),
+                passphrase: passphrase.to_owned(),
+            }),
+        })
+    }
+}
+
+/// Read a file, or `None` if it does not exist yet.
+fn read_if_exists(path: &Path) -> crate::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_creating_dirs(path: &Path, contents: &str) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// The whole history as a single JSON document.
+pub struct JsonStore(pub PathBuf);
+
+impl HistoryStore for JsonStore {
+    fn load(&self) -> crate::Result<History> {
+        match read_if_exists(&self.0)? {
+            Some(contents) => serde_json::from_str(&contents)
+                .map_err(|err| TimeFloError::data_corrupt(&self.0, err)),
+            None => Ok(History::default()),
+        }
+    }
+
+    fn save(&self, history: &History) -> crate::Result<()> {
+        write_creating_dirs(&self.0, &serde_json::to_string_pretty(history)?)
+    }
+}
+
+/// The history as a JSON document, encrypted with a passphrase using age.
+#[cfg(feature = "encryption")]
+pub struct EncryptedStore {
+    pub path: PathBuf,
+    pub passphrase: String,
+}
+
+#[cfg(feature = "encryption")]
+fn encryption_error(err: impl fmt::Display) -> TimeFloError {
+    TimeFloError::Encryption(err.to_string())
+}
+
+#[cfg(feature = "encryption")]
+impl HistoryStore for EncryptedStore {
+    fn load(&self) -> crate::Result<History> {
+        use age::secrecy::Secret;
+        use std::io::Read;
+
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(History::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let decryptor = match age::Decryptor::new(&contents[..])
+            .map_err(|err| TimeFloError::data_corrupt(&self.path, err))?
+        {
+            age::Decryptor::Passphrase(decryptor) => decryptor,
+            _ => {
+                return Err(TimeFloError::data_corrupt(
+                    &self.path,
+                    "not encrypted with a passphrase",
+                ))
+            }
+        };
+
+        let mut json = String::new();
+        decryptor
+            .decrypt(&Secret::new(self.passphrase.clone()), None)
+            .map_err(encryption_error)?
+            .read_to_string(&mut json)?;
+
+        serde_json::from_str(&json)
+            .map_err(|err| TimeFloError::data_corrupt(&self.path, err))
+    }
+
+    fn save(&self, history: &History) -> crate::Result<()> {
+        use age::secrecy::Secret;
+        use std::io::Write;
+
+        let encryptor = age::Encryptor::with_user_passphrase(Secret::new(
+            self.passphrase.clone(),
+        ));
+
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(&mut encrypted)
+            .map_err(encryption_error)?;
+        writer.write_all(&serde_json::to_vec(history)?)?;
+        writer.finish()?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, encrypted)?;
+        Ok(())
+    }
+}
+
+/// One tab-separated line per entry or skip, easily read and edited by hand:
+///
+/// ```text
+/// entry <start> <end> <manual> <pauses> <internal> <external> <status> <snoozed> <apps> <task>
+/// note  <text>
+/// context <label>
+/// pause <start> <end>
+/// skip  <time>
+/// interruption <time> <kind> <abandoned> <note>
+/// ```
+///
+/// where `<snoozed>` is in minutes, `<apps>` is `-` or the focused
+/// applications as `name:samples` separated by commas, a note, context, or
+/// pause belongs to the entry before it, and `<kind>` is `internal` or `external`.
+pub struct PlainTextStore(pub PathBuf);
+
+/// Applications in the form `name:samples,name:samples`, or `-` if there are
+/// none.
+fn format_applications(applications: &[AppTime]) -> String {
+    if applications.is_empty() {
+        return "-".to_owned();
+    }
+
+    applications
+        .iter()
+        .map(|app| {
+            let name = app.name.replace(&['\t', '\n', ','][..], " ");
+            format!("{}:{}", name, app.samples)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_applications(s: &str) -> Option<Vec<AppTime>> {
+    if s == "-" {
+        return Some(Vec::new());
+    }
+
+    s.split(',')
+        .map(|app| {
+            let (name, samples) = app.rsplit_once(':')?;
+            Some(AppTime {
+                name: name.to_owned(),
+                samples: samples.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+impl PlainTextStore {
+    fn parse_line(
+        line: &str,
+        entries: &mut Vec<Entry>,
+        skipped: &mut Vec<DateTime<Local>>,
+        interruptions: &mut Vec<LoggedInterruption>,
+    ) -> Option<()> {
+        let time = |s: &str| {
+            DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|time| time.with_timezone(&Local))
+        };
+
+        let fields: Vec<&str> = line.splitn(11, '\t').collect();
+        match fields.as_slice() {
+            ["entry", rest @ ..] if rest.len() >= 7 => {
+                // lines written before statuses, applications, or snoozes
+                // were recorded have none
+                let (status, snoozed_minutes, applications, task) = match rest {
+                    [.., status, snoozed, applications, task]
+                        if rest.len() == 10 =>
+                    {
+                        (
+                            EntryStatus::parse(status)?,
+                            snoozed.parse().ok()?,
+                            parse_applications(applications)?,
+                            task,
+                        )
+                    }
+                    [.., status, applications, task] if rest.len() == 9 => (
+                        EntryStatus::parse(status)?,
+                        0,
+                        parse_applications(applications)?,
+                        task,
+                    ),
+                    [.., status, task] if rest.len() == 8 => {
+                        (EntryStatus::parse(status)?, 0, Vec::new(), task)
+                    }
+                    _ => (EntryStatus::Completed, 0, Vec::new(), &rest[6]),
+                };
+
+                entries.push(Entry {
+                    start: time(rest[0])?,
+                    end: time(rest[1])?,
+                    task: task.to_string(),
+                    manual: rest[2].parse().ok()?,
+                    interruptions: Interruptions {
+                        total: rest[3].parse().ok()?,
+                        internal: rest[4].parse().ok()?,
+                        external: rest[5].parse().ok()?,
+                    },
+                    status,
+                    applications,
+                    snoozed_minutes,
+                    note: String::new(),
+                    context: String::new(),
+                    pauses: Vec::new(),
+                });
+            }
+            ["note", note] => entries.last_mut()?.note = note.to_string(),
+            ["context", context] => {
+                entries.last_mut()?.context = context.to_string()
+            }
+            ["pause", start, end] => entries.last_mut()?.pauses.push(Pause {
+                start: time(*start)?,
+                end: time(*end)?,
+            }),
+            ["skip", at] => skipped.push(time(*at)?),
+            ["interruption", at, kind, abandoned, note] => {
+                interruptions.push(LoggedInterruption {
+                    at: time(*at)?,
+                    kind: InterruptionKind::parse(kind)?,
+                    abandoned: abandoned.parse().ok()?,
+                    note: note.to_string(),
+                })
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+impl HistoryStore for PlainTextStore {
+    fn load(&self) -> crate::Result<History> {
+        let contents = match read_if_exists(&self.0)? {
+            Some(contents) => contents,
+            None => return Ok(History::default()),
+        };
+
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+        let mut interruptions = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            Self::parse_line(
+                line,
+                &mut entries,
+                &mut skipped,
+                &mut interruptions,
+            )
+            .ok_or_else(|| {
+                TimeFloError::data_corrupt(
+                    &self.0,
+                    format!("invalid line {}", i + 1),
+                )
+            })?;
+        }
+
+        Ok(History::from_parts(entries, skipped, interruptions))
+    }
+
+    fn save(&self, history: &History) -> crate::Result<()> {
+        let mut contents = String::new();
+        for entry in history.entries() {
+            let i = entry.interruptions;
+            contents.push_str(&format!(
+                "entry\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                entry.start.to_rfc3339(),
+                entry.end.to_rfc3339(),
+                entry.manual,
+                i.total,
+                i.internal,
+                i.external,
+                entry.status.as_str(),
+                entry.snoozed_minutes,
+                format_applications(&entry.applications),
+                // tabs and newlines would break the format
+                entry.task.replace(&['\t', '\n'][..], " ")
+            ));
+            if !entry.note.is_empty() {
+                contents.push_str(&format!(
+                    "note\t{}\n",
+                    entry.note.replace(&['\t', '\n'][..], " ")
+                ));
+            }
+            if !entry.context.is_empty() {
+                contents.push_str(&format!(
+                    "context\t{}\n",
+                    entry.context.replace(&['\t', '\n'][..], " ")
+                ));
+            }
+            for pause in &entry.pauses {
+                contents.push_str(&format!(
+                    "pause\t{}\t{}\n",
+                    pause.start.to_rfc3339(),
+                    pause.end.to_rfc3339()
+                ));
+            }
+        }
+        for at in history.skipped() {
+            contents.push_str(&format!("skip\t{}\n", at.to_rfc3339()));
+        }
+        for interruption in history.interruptions() {
+            contents.push_str(&format!(
+                "interruption\t{}\t{}\t{}\t{}\n",
+                interruption.at.to_rfc3339(),
+                interruption.kind.as_str(),
+                interruption.abandoned,
+                interruption.note.replace(&['\t', '\n'][..], " ")
+            ));
+        }
+
+        write_creating_dirs(&self.0, &contents)
+    }
+}
+
+/// A SQLite database with a table each for entries, skips, and logged
+/// interruptions.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore(pub PathBuf);
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    fn connect(&self) -> crate::Result<rusqlite::Connection> {
+        if let Some(parent) = self.0.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let connection = rusqlite::Connection::open(&self.0)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS entries (
+                start TEXT NOT NULL,
+                end TEXT NOT NULL,
+                task TEXT NOT NULL,
+                manual INTEGER NOT NULL,
+                interruptions INTEGER NOT NULL,
+                internal_interruptions INTEGER NOT NULL,
+                external_interruptions INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS skips (at TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS interruptions (
+                at TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                abandoned INTEGER NOT NULL,
+                note TEXT NOT NULL
+            );",
+        )?;
+
+        let version: u32 =
+            connection
+                .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < 1 {
+            connection.execute_batch(
+                "ALTER TABLE entries
+                    ADD COLUMN status TEXT NOT NULL DEFAULT 'completed';
+                PRAGMA user_version = 1;",
+            )?;
+        }
+        if version < 2 {
+            connection.execute_batch(
+                "ALTER TABLE entries
+                    ADD COLUMN applications TEXT NOT NULL DEFAULT '[]';
+                PRAGMA user_version = 2;",
+            )?;
+        }
+        if version < 3 {
+            connection.execute_batch(
+                "ALTER TABLE entries
+                    ADD COLUMN snoozed_minutes INTEGER NOT NULL DEFAULT 0;
+                PRAGMA user_version = 3;",
+            )?;
+        }
+        if version < 4 {
+            connection.execute_batch(
+                "ALTER TABLE entries
+                    ADD COLUMN note TEXT NOT NULL DEFAULT '';
+                PRAGMA user_version = 4;",
+            )?;
+        }
+        if version < 5 {
+            connection.execute_batch(
+                "ALTER TABLE entries
+                    ADD COLUMN context TEXT NOT NULL DEFAULT '';
+                PRAGMA user_version = 5;",
+            )?;
+        }
+        if version < 6 {
+            connection.execute_batch(
+                "ALTER TABLE entries
+                    ADD COLUMN pauses TEXT NOT NULL DEFAULT '[]';
+                PRAGMA user_version = 6;",
+            )?;
+        }
+
+        Ok(connection)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl HistoryStore for SqliteStore {
+    fn load(&self) -> crate::Result<History> {
+        let connection = self.connect()?;
+        let time = |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|time| time.with_timezone(&Local))
+                .map_err(|err| TimeFloError::data_corrupt(&self.0, err))
+        };
+
+        let mut entries = Vec::new();
+        let mut statement = connection.prepare(
+            "SELECT start, end, task, manual, interruptions,
+                internal_interruptions, external_interruptions, status,
+                applications, snoozed_minutes, note, context, pauses
+            FROM entries",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let applications: String = row.get(8)?;
+            let pauses: String = row.get(12)?;
+            entries.push(Entry {
+                start: time(row.get(0)?)?,
+                end: time(row.get(1)?)?,
+                task: row.get(2)?,
+                manual: row.get(3)?,
+                interruptions: Interruptions {
+                    total: row.get(4)?,
+                    internal: row.get(5)?,
+                    external: row.get(6)?,
+                },
+                status: EntryStatus::parse(&row.get::<_, String>(7)?)
+                    .ok_or_else(|| {
+                        TimeFloError::data_corrupt(&self.0, "unknown status")
+                    })?,
+                applications: serde_json::from_str(&applications)
+                    .map_err(|err| TimeFloError::data_corrupt(&self.0, err))?,
+                snoozed_minutes: row.get(9)?,
+                note: row.get(10)?,
+                context: row.get(11)?,
+                pauses: serde_json::from_str(&pauses)
+                    .map_err(|err| TimeFloError::data_corrupt(&self.0, err))?,
+            });
+        }
+
+        let mut skipped = Vec::new();
+        let mut statement = connection.prepare("SELECT at FROM skips")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            skipped.push(time(row.get(0)?)?);
+        }
+
+        let mut interruptions = Vec::new();
+        let mut statement = connection
+            .prepare("SELECT at, kind, abandoned, note FROM interruptions")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            interruptions.push(LoggedInterruption {
+                at: time(row.get(0)?)?,
+                kind: InterruptionKind::parse(&row.get::<_, String>(1)?)
+                    .ok_or_else(|| {
+                        TimeFloError::data_corrupt(
+                            &self.0,
+                            "unknown interruption kind",
+                        )
+                    })?,
+                abandoned: row.get(2)?,
+                note: row.get(3)?,
+            });
+        }
+
+        Ok(History::from_parts(entries, skipped, interruptions))
+    }
+
+    fn save(&self, history: &History) -> crate::Result<()> {
+        let mut connection = self.connect()?;
+        let transaction = connection.transaction()?;
+
+        transaction.execute("DELETE FROM entries", [])?;
+        transaction.execute("DELETE FROM skips", [])?;
+        transaction.execute("DELETE FROM interruptions", [])?;
+        for entry in history.entries() {
+            transaction.execute(
+                "INSERT INTO entries
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11,
+                        ?12, ?13)",
+                rusqlite::params![
+                    entry.start.to_rfc3339(),
+                    entry.end.to_rfc3339(),
+                    entry.task,
+                    entry.manual,
+                    entry.interruptions.total,
+                    entry.interruptions.internal,
+                    entry.interruptions.external,
+                    entry.status.as_str(),
+                    serde_json::to_string(&entry.applications)?,
+                    entry.snoozed_minutes,
+                    entry.note,
+                    entry.context,
+                    serde_json::to_string(&entry.pauses)?,
+                ],
+            )?;
+        }
+        for at in history.skipped() {
+            transaction
+                .execute("INSERT INTO skips VALUES (?1)", [at.to_rfc3339()])?;
+        }
+        for interruption in history.interruptions() {
+            transaction.execute(
+                "INSERT INTO interruptions VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![
+                    interruption.at.to_rfc3339(),
+                    interruption.kind.as_str(),
+                    interruption.abandoned,
+                    interruption.note,
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::EntryDraft;
+
+    #[test]
+    fn test_plain_text_store() {
+        let path = std::env::temp_dir()
+            .join(format!("time-flo-test-{}.txt", std::process::id()));
+        let store = PlainTextStore(path.clone());
+
+        assert!(store.load().unwrap().entries().is_empty());
+
+        let mut entry = EntryDraft {
+            date: "2021-11-02".into(),
+            start: "14:00".into(),
+            end: "14:50".into(),
+            task: "Tabs\tand\nnewlines".into(),
+        }
+        .parse()
+        .unwrap();
+        entry.interruptions.total = 2;
+        entry.interruptions.external = 1;
+        entry.snoozed_minutes = 10;
+        entry.note = "Tidied\tup".into();
+        entry.context = "Writing".into();
+        entry.pauses = vec![Pause {
+            start: entry.start,
+            end: entry.start + chrono::Duration::minutes(5),
+        }];
+        entry.applications = vec![
+            AppTime {
+                name: "Visual Studio Code".into(),
+                samples: 40,
+            },
+            AppTime {
+                name: "a,b".into(),
+                samples: 2,
+            },
+        ];
+
+        let mut history = History::default();
+        history.add(entry);
+        history.record_skip(Local::now());
+        history.log_interruption(LoggedInterruption {
+            at: Local::now(),
+            kind: InterruptionKind::External,
+            abandoned: true,
+            note: "Fire\talarm".into(),
+        });
+        store.save(&history).unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(1, loaded.entries().len());
+        assert_eq!(1, loaded.skipped().len());
+        assert_eq!("Tabs and newlines", loaded.entries()[0].task);
+        assert_eq!(
+            history.entries()[0].interruptions,
+            loaded.entries()[0].interruptions
+        );
+        assert_eq!(
+            Some("Visual Studio Code:40,a b:2"),
+            fs::read_to_string(&path).unwrap().split('\t').nth(9)
+        );
+        assert_eq!(10, loaded.entries()[0].snoozed_minutes);
+        assert_eq!("a b", loaded.entries()[0].applications[1].name);
+        assert_eq!("Tidied up", loaded.entries()[0].note);
+        assert_eq!("Writing", loaded.entries()[0].context);
+        assert_eq!(history.entries()[0].pauses, loaded.entries()[0].pauses);
+        let interruption = &loaded.interruptions()[0];
+        assert_eq!(InterruptionKind::External, interruption.kind);
+        assert!(interruption.abandoned);
+        assert_eq!("Fire alarm", interruption.note);
+
+        // lines from before applications were recorded
+        fs::write(
+            &path,
+            "entry\t2021-11-02T14:00:00+00:00\t2021-11-02T14:50:00+00:00\t\
+             false\t0\t0\t0\tvoided\tOld task\n",
+        )
+        .unwrap();
+        let loaded = store.load().unwrap();
+        assert_eq!(EntryStatus::Voided, loaded.entries()[0].status);
+        assert_eq!("Old task", loaded.entries()[0].task);
+        assert!(loaded.entries()[0].applications.is_empty());
+
+        fs::write(&path, "nonsense\n").unwrap();
+        assert!(store.load().is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
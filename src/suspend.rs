@@ -0,0 +1,71 @@
+//! Noticing when the machine has been suspended, by watching for the wall
+//! clock running ahead of the monotonic clock, which stands still during
+//! suspend on Linux and macOS.
+
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::epi::RepaintSignal;
+
+/// How often the clocks are compared.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Gaps shorter than this are put down to scheduling delays and small
+/// adjustments of the wall clock.
+const THRESHOLD: Duration = Duration::from_secs(10);
+
+/// How long the machine was suspended for, given how much time passed on each
+/// clock, if the wall clock got far enough ahead.
+pub fn suspended_for(monotonic: Duration, wall: Duration) -> Option<Duration> {
+    wall.checked_sub(monotonic).filter(|gap| *gap >= THRESHOLD)
+}
+
+/// Compares the clocks in the background, waking the UI after a suspend.
+pub struct SuspendMonitor {
+    receiver: Receiver<Duration>,
+}
+
+impl SuspendMonitor {
+    pub fn start(signal: Arc<dyn RepaintSignal>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || loop {
+            let monotonic = Instant::now();
+            let wall = SystemTime::now();
+            thread::sleep(CHECK_INTERVAL);
+
+            let wall =
+                SystemTime::now().duration_since(wall).unwrap_or_default();
+            if let Some(gap) = suspended_for(monotonic.elapsed(), wall) {
+                // the monitor has been dropped
+                if sender.send(gap).is_err() {
+                    break;
+                }
+                signal.request_repaint();
+            }
+        });
+
+        Self { receiver }
+    }
+
+    /// Time spent suspended since this was last called.
+    pub fn take_suspended(&self) -> Duration {
+        self.receiver.try_iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suspended_for() {
+        let secs = Duration::from_secs;
+        assert_eq!(Some(secs(600)), suspended_for(secs(5), secs(605)));
+        assert_eq!(None, suspended_for(secs(5), secs(7)));
+        // the wall clock was set back
+        assert_eq!(None, suspended_for(secs(5), secs(0)));
+    }
+}
@@ -0,0 +1,359 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui::{self, Color32, FontFamily, TextStyle};
+use serde::{Deserialize, Deserializer};
+
+/// How often the active theme file is checked for modifications.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A user-defined theme, loaded from a TOML or JSON file.
+///
+/// Every field is optional, and anything left unspecified falls back to the
+/// default egui style.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub colors: ThemeColors,
+    pub spacing: ThemeSpacing,
+    pub fonts: ThemeFonts,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ThemeColors {
+    /// Whether to start from the dark or the light base style.
+    pub dark: bool,
+    pub background: Option<HexColor>,
+    pub text: Option<HexColor>,
+    /// Used for selections and highlighted buttons.
+    pub accent: Option<HexColor>,
+    /// Used for the countdown when the timer is about to go off.
+    pub warning: Option<HexColor>,
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self {
+            dark: true,
+            background: None,
+            text: None,
+            accent: None,
+            warning: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ThemeSpacing {
+    pub item_spacing: Option<[f32; 2]>,
+    pub button_padding: Option<[f32; 2]>,
+}
+
+/// Font sizes in points for each text style.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ThemeFonts {
+    pub body: Option<f32>,
+    pub heading: Option<f32>,
+    pub monospace: Option<f32>,
+    pub button: Option<f32>,
+    pub small: Option<f32>,
+}
+
+impl Theme {
+    /// Load a theme from a file, choosing the format by its extension.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let theme: Theme = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .map_err(|err| crate::TimeFloError::config_parse(path, err))?,
+            _ => toml::from_str(&contents)
+                .map_err(|err| crate::TimeFloError::config_parse(path, err))?,
+        };
+        Ok(theme)
+    }
+
+    /// Apply this theme to the given context.
+    pub fn apply(&self, ctx: &egui::CtxRef) {
+        let colors = &self.colors;
+
+        let mut visuals = if colors.dark {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+
+        if let Some(background) = colors.background {
+            visuals.widgets.noninteractive.bg_fill = background.0;
+        }
+        if let Some(text) = colors.text {
+            visuals.override_text_color = Some(text.0);
+        }
+        if let Some(accent) = colors.accent {
+            visuals.selection.bg_fill = accent.0;
+            visuals.hyperlink_color = accent.0;
+        }
+
+        let mut style = (*ctx.style()).clone();
+        style.visuals = visuals;
+
+        if let Some([x, y]) = self.spacing.item_spacing {
+            style.spacing.item_spacing = egui::vec2(x, y);
+        }
+        if let Some([x, y]) = self.spacing.button_padding {
+            style.spacing.button_padding = egui::vec2(x, y);
+        }
+
+        ctx.set_style(style);
+
+        let mut fonts = egui::FontDefinitions::default();
+        let sizes = [
+            (TextStyle::Body, FontFamily::Proportional, self.fonts.body),
+            (
+                TextStyle::Heading,
+                FontFamily::Proportional,
+                self.fonts.heading,
+            ),
+            (
+                TextStyle::Monospace,
+                FontFamily::Monospace,
+                self.fonts.monospace,
+            ),
+            (
+                TextStyle::Button,
+                FontFamily::Proportional,
+                self.fonts.button,
+            ),
+            (TextStyle::Small, FontFamily::Proportional, self.fonts.small),
+        ];
+        for (text_style, family, size) in sizes {
+            if let Some(size) = size {
+                fonts.family_and_size.insert(text_style, (family, size));
+            }
+        }
+        ctx.set_fonts(fonts);
+    }
+
+    /// The color to use for the countdown when the timer is about to go off.
+    pub fn warning_color(&self) -> Color32 {
+        self.colors.warning.map(|c| c.0).unwrap_or(Color32::RED)
+    }
+
+    /// The color to use for highlighted buttons.
+    pub fn accent_color(&self) -> Color32 {
+        self.colors.accent.map(|c| c.0).unwrap_or(Color32::BLUE)
+    }
+}
+
+/// A color which is deserialized from a `#rrggbb` or `#rrggbbaa` string.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HexColor(pub Color32);
+
+impl HexColor {
+    pub fn parse(s: &str) -> Option<Self> {
+        let hex = s.strip_prefix('#')?;
+        let channel =
+            |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+
+        match hex.len() {
+            6 => Some(HexColor(Color32::from_rgb(
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+            ))),
+            8 => Some(HexColor(Color32::from_rgba_unmultiplied(
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+                channel(6)?,
+            ))),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        HexColor::parse(&s).ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid color: {}", s))
+        })
+    }
+}
+
+/// The directory in which user themes are stored.
+pub fn themes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("time-flo").join("themes"))
+}
+
+/// List the names of all themes available in the themes directory.
+pub fn available_themes() -> Vec<String> {
+    let entries = match themes_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        Some(entries) => entries,
+        None => return Vec::new(),
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("toml") | Some("json")
+            )
+        })
+        .filter_map(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(String::from)
+        })
+        .collect();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Find the file backing the theme with the given name.
+fn theme_path(name: &str) -> Option<PathBuf> {
+    let dir = themes_dir()?;
+    ["toml", "json"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Keeps track of the active theme, reloading it when its file changes.
+#[derive(Default)]
+pub struct ThemeManager {
+    /// Name of the active theme, or `None` for the default theme.
+    name: Option<String>,
+    /// The active theme.
+    theme: Theme,
+    /// Modification time of the theme file when it was last loaded.
+    modified: Option<SystemTime>,
+    /// The last time the theme file was checked for modifications.
+    last_check: Option<Instant>,
+    /// Whether the theme needs to be (re)applied to the context.
+    dirty: bool,
+}
+
+impl ThemeManager {
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Switch to the theme with the given name.
+    pub fn select(&mut self, name: Option<String>) -> crate::Result<()> {
+        if self.name != name {
+            self.name = name;
+            self.modified = None;
+            self.reload()?;
+        }
+        Ok(())
+    }
+
+    /// Reload the active theme from disk.
+    fn reload(&mut self) -> crate::Result<()> {
+        self.dirty = true;
+
+        let path = match self.name.as_deref().and_then(theme_path) {
+            Some(path) => path,
+            None => {
+                self.theme = Theme::default();
+                return Ok(());
+            }
+        };
+
+        self.modified = fs::metadata(&path)?.modified().ok();
+        self.theme = Theme::load(&path)?;
+        Ok(())
+    }
+
+    /// Reload the theme if its file has changed, and apply it if needed.
+    pub fn update(&mut self, ctx: &egui::CtxRef) -> crate::Result<()> {
+        let should_check = self
+            .last_check
+            .map_or(true, |last| last.elapsed() >= RELOAD_INTERVAL);
+
+        if should_check {
+            self.last_check = Some(Instant::now());
+
+            let modified = self
+                .name
+                .as_deref()
+                .and_then(theme_path)
+                .and_then(|path| fs::metadata(path).ok())
+                .and_then(|metadata| metadata.modified().ok());
+
+            if modified.is_some() && modified != self.modified {
+                // apply whatever was loaded even if the new file is broken
+                let result = self.reload();
+                self.apply_if_dirty(ctx);
+                return result;
+            }
+        }
+
+        self.apply_if_dirty(ctx);
+        Ok(())
+    }
+
+    fn apply_if_dirty(&mut self, ctx: &egui::CtxRef) {
+        if self.dirty {
+            self.theme.apply(ctx);
+            self.dirty = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme() {
+        // test color parsing
+        assert_eq!(
+            Some(HexColor(Color32::from_rgb(0x12, 0x34, 0xab))),
+            HexColor::parse("#1234ab")
+        );
+        assert_eq!(
+            Some(HexColor(Color32::from_rgba_unmultiplied(1, 2, 3, 4))),
+            HexColor::parse("#01020304")
+        );
+        assert_eq!(None, HexColor::parse("1234ab"));
+        assert_eq!(None, HexColor::parse("#12345"));
+        assert_eq!(None, HexColor::parse("#zz0000"));
+
+        // test both file formats
+        let from_toml: Theme = toml::from_str(
+            r##"
+            [colors]
+            dark = false
+            accent = "#ff0000"
+
+            [fonts]
+            heading = 30.0
+            "##,
+        )
+        .unwrap();
+        let from_json: Theme = serde_json::from_str(
+            r##"{
+                "colors": { "dark": false, "accent": "#ff0000" },
+                "fonts": { "heading": 30.0 }
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(from_toml, from_json);
+        assert!(!from_toml.colors.dark);
+        assert_eq!(Color32::RED, from_toml.accent_color());
+        assert_eq!(Some(30.), from_toml.fonts.heading);
+        assert_eq!(None, from_toml.fonts.body);
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use chrono::{NaiveTime, Timelike};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::locale;
+
+/// A time of day with minute precision.
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Deserialize, Serialize,
+)]
+pub struct TimeOfDay {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: u32, minute: u32) -> Self {
+        Self { hour, minute }
+    }
+}
+
+/// How hours are displayed in clock times.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum HourFormat {
+    /// Follow the locale set in the environment.
+    Locale,
+    TwelveHour,
+    TwentyFourHour,
+}
+
+impl Default for HourFormat {
+    fn default() -> Self {
+        HourFormat::Locale
+    }
+}
+
+impl fmt::Display for HourFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HourFormat::Locale => "Locale default",
+            HourFormat::TwelveHour => "12-hour",
+            HourFormat::TwentyFourHour => "24-hour",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl HourFormat {
+    /// Resolve the locale default to a concrete format.
+    pub fn resolve(self) -> Self {
+        match self {
+            HourFormat::Locale => {
+                if locale_uses_twelve_hour(&locale::env_locale()) {
+                    HourFormat::TwelveHour
+                } else {
+                    HourFormat::TwentyFourHour
+                }
+            }
+            format => format,
+        }
+    }
+
+    /// Format a time according to this format.
+    pub fn format(self, time: NaiveTime) -> String {
+        match self.resolve() {
+            HourFormat::TwelveHour => time.format("%-I:%M %p").to_string(),
+            _ => time.format("%H:%M").to_string(),
+        }
+    }
+}
+
+/// Whether a POSIX locale name such as `en_US.UTF-8` conventionally uses a
+/// 12-hour clock.
+fn locale_uses_twelve_hour(locale: &str) -> bool {
+    const TWELVE_HOUR_LOCALES: [&str; 11] = [
+        "en_US", "en_CA", "en_AU", "en_NZ", "en_IN", "en_PH", "hi_IN", "ar_EG",
+        "ar_SA", "ur_PK", "bn_BD",
+    ];
+
+    TWELVE_HOUR_LOCALES
+        .iter()
+        .any(|prefix| locale.starts_with(prefix))
+}
+
+impl From<NaiveTime> for TimeOfDay {
+    fn from(time: NaiveTime) -> Self {
+        Self::new(time.hour(), time.minute())
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+pub fn editor(ui: &mut egui::Ui, time: &mut TimeOfDay) {
+    ui.add(egui::DragValue::new(&mut time.hour).clamp_range(0..=23));
+    ui.label(":");
+    ui.add(egui::DragValue::new(&mut time.minute).clamp_range(0..=59));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hour_format() {
+        let time = NaiveTime::from_hms(15, 7, 0);
+
+        assert_eq!("3:07 PM", HourFormat::TwelveHour.format(time));
+        assert_eq!("15:07", HourFormat::TwentyFourHour.format(time));
+
+        assert!(locale_uses_twelve_hour("en_US.UTF-8"));
+        assert!(!locale_uses_twelve_hour("en_GB.UTF-8"));
+        assert!(!locale_uses_twelve_hour("de_DE"));
+        assert!(!locale_uses_twelve_hour(""));
+
+        assert_eq!("07:05", TimeOfDay::new(7, 5).to_string());
+    }
+}
@@ -1,21 +1,83 @@
-use std::fmt;
-use std::time::{Duration, Instant};
+use std::fmt::{self, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime};
 
 use serde::{Deserialize, Serialize};
 
+use crate::clock;
+
 /// A stateful timer implementation.
 #[derive(Default, Copy, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(from = "SavedTimer", into = "SavedTimer")]
 pub struct Timer {
     /// The total duration of this timer
-    #[serde(with = "serde_millis")]
     duration: Duration,
     /// The amount of time which has been accumulated.
     /// This only increases when the timer has been paused.
-    #[serde(with = "serde_millis")]
     accumulated_time: Duration,
     /// The time at which this timer was started.
-    #[serde(with = "serde_millis")]
     start_timestamp: Option<Instant>,
+    /// Whether this timer counts up with no end, ignoring the duration.
+    count_up: bool,
+}
+
+/// A timer as it is saved, with the start on the wall clock, since instants
+/// mean nothing after a restart.
+#[derive(Deserialize, Serialize)]
+struct SavedTimer {
+    #[serde(with = "serde_millis")]
+    duration: Duration,
+    #[serde(with = "serde_millis")]
+    accumulated_time: Duration,
+    /// Time since the Unix epoch at which the running timer was started.
+    #[serde(default, with = "serde_millis")]
+    started_at: Option<Duration>,
+    #[serde(default)]
+    count_up: bool,
+}
+
+impl From<Timer> for SavedTimer {
+    fn from(timer: Timer) -> Self {
+        let started_at = timer.start_timestamp.and_then(|start| {
+            let wall = SystemTime::now().checked_sub(clock::elapsed(start))?;
+            wall.duration_since(SystemTime::UNIX_EPOCH).ok()
+        });
+
+        Self {
+            duration: timer.duration,
+            accumulated_time: timer.accumulated_time,
+            started_at,
+            count_up: timer.count_up,
+        }
+    }
+}
+
+impl From<SavedTimer> for Timer {
+    fn from(saved: SavedTimer) -> Self {
+        let mut accumulated_time = saved.accumulated_time;
+        let start_timestamp = saved.started_at.map(|started_at| {
+            let since = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH + started_at)
+                .unwrap_or_default();
+            let now = clock::now();
+            // instants can't reach further back than the system's boot, so
+            // count whatever doesn't fit as accumulated time instead
+            match now.checked_sub(since) {
+                Some(start) => start,
+                None => {
+                    accumulated_time += since;
+                    now
+                }
+            }
+        });
+
+        Self {
+            duration: saved.duration,
+            accumulated_time,
+            start_timestamp,
+            count_up: saved.count_up,
+        }
+    }
 }
 
 impl Timer {
@@ -24,48 +86,161 @@ impl Timer {
             duration,
             accumulated_time: Duration::ZERO,
             start_timestamp: None,
+            count_up: false,
+        }
+    }
+
+    /// A stopwatch, which counts up with no end.
+    pub fn count_up() -> Self {
+        Self {
+            count_up: true,
+            ..Self::default()
         }
     }
 
+    pub fn is_counting_up(&self) -> bool {
+        self.count_up
+    }
+
     pub fn start(&mut self) {
         if self.start_timestamp.is_none() {
-            self.start_timestamp = Some(Instant::now());
+            self.start_timestamp = Some(clock::now());
         }
     }
 
     pub fn pause(&mut self) {
         if let Some(start_timestamp) = self.start_timestamp {
-            let elapsed = Instant::now().duration_since(start_timestamp);
+            let elapsed = clock::now().duration_since(start_timestamp);
             self.accumulated_time += elapsed;
         }
 
         self.start_timestamp = None;
     }
 
+    /// Count time which passed without the clock noticing, such as while
+    /// the machine was suspended.
+    pub fn add_elapsed(&mut self, duration: Duration) {
+        self.accumulated_time += duration;
+    }
+
     pub fn set_duration(&mut self, new_duration: Duration) {
         self.duration = new_duration;
     }
 
+    /// Lengthen the timer by the given amount.
+    pub fn extend(&mut self, duration: Duration) {
+        self.duration += duration;
+    }
+
     pub fn elapsed(&self) -> Duration {
         let current_elapsed = match self.start_timestamp {
             Some(start_timestamp) => {
-                Instant::now().duration_since(start_timestamp)
+                clock::now().duration_since(start_timestamp)
             }
             None => Duration::ZERO,
         };
         self.accumulated_time + current_elapsed
     }
 
+    /// Time left until the timer is over, which is always none when
+    /// counting up.
     pub fn remaining_time(&self) -> Duration {
+        if self.count_up {
+            return Duration::ZERO;
+        }
         self.duration.saturating_sub(self.elapsed())
     }
 
+    /// Time elapsed beyond the duration, once the timer is over.
+    pub fn overtime(&self) -> Duration {
+        if self.count_up {
+            return Duration::ZERO;
+        }
+        self.elapsed().saturating_sub(self.duration)
+    }
+
+    /// The fraction of the duration which has elapsed, from 0 to 1, or 0 when
+    /// counting up.
+    pub fn progress(&self) -> f32 {
+        if self.count_up {
+            return 0.;
+        } else if self.duration.is_zero() {
+            return 1.;
+        }
+
+        (self.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+    }
+
+    /// Time until the displayed remaining time next changes to a multiple of
+    /// `step` seconds.
+    pub fn until_display_change(&self, step: u64) -> Duration {
+        if self.count_up {
+            let step = Duration::from_secs(step.max(1));
+            let into = self.elapsed().as_nanos() % step.as_nanos();
+            return step - Duration::from_nanos(into as u64);
+        }
+
+        let remaining = self.remaining_time();
+        let displayed = remaining.as_secs();
+        if remaining.is_zero() && self.is_running() {
+            // counting up through overtime, one second at a time
+            let overtime = self.overtime();
+            return Duration::from_secs(overtime.as_secs() + 1) - overtime;
+        } else if displayed == 0 {
+            return remaining;
+        }
+
+        let sub_second = remaining - Duration::from_secs(displayed);
+        let next = (displayed - 1) - (displayed - 1) % step.max(1);
+        sub_second + Duration::from_secs(displayed - 1 - next)
+    }
+
+    /// Time until the displayed tenth of a second next changes.
+    pub fn until_tenth_change(&self) -> Duration {
+        let tenth = Duration::from_millis(100);
+        let remaining = self.remaining_time();
+        if self.count_up || remaining.is_zero() {
+            // counting up, or through overtime
+            let up = if self.count_up {
+                self.elapsed()
+            } else {
+                self.overtime()
+            };
+            let into = up.as_nanos() % tenth.as_nanos();
+            return tenth - Duration::from_nanos(into as u64);
+        }
+
+        match remaining.as_nanos() % tenth.as_nanos() {
+            0 => tenth,
+            into => Duration::from_nanos(into as u64),
+        }
+    }
+
+    /// The remaining time, or the overtime, optionally with tenths of a
+    /// second such as `04:59.3`. Hours are shown once there are any, such as
+    /// `1:30:00`.
+    pub fn format_remaining(&self, tenths: bool) -> String {
+        let mut text = String::new();
+        let _ = write_remaining(&mut text, display_units(self, tenths), tenths);
+        text
+    }
+
+    /// A paused copy of this timer, which remains meaningful after a restart.
+    pub fn checkpoint(&self) -> Self {
+        Self {
+            duration: self.duration,
+            accumulated_time: self.elapsed(),
+            start_timestamp: None,
+            count_up: self.count_up,
+        }
+    }
+
     pub fn has_started(&self) -> bool {
-        self.elapsed() > Duration::ZERO
+        self.is_running() || self.accumulated_time > Duration::ZERO
     }
 
     pub fn is_over(&self) -> bool {
-        self.elapsed() >= self.duration
+        !self.count_up && self.elapsed() >= self.duration
     }
 
     pub fn is_running(&self) -> bool {
@@ -77,16 +252,175 @@ impl Timer {
     }
 }
 
+/// The remaining time in whole seconds, or the overtime in whole seconds if
+/// there is any.
+fn display_seconds(timer: &Timer) -> (u64, bool) {
+    display_units(timer, false)
+}
+
+/// The remaining time, or the overtime if there is any, in whole seconds or
+/// tenths of a second. Timers counting up show the time elapsed.
+fn display_units(timer: &Timer, tenths: bool) -> (u64, bool) {
+    let units = |duration: Duration| {
+        if tenths {
+            (duration.as_millis() / 100) as u64
+        } else {
+            duration.as_secs()
+        }
+    };
+
+    if timer.count_up {
+        return (units(timer.elapsed()), false);
+    }
+
+    let overtime = units(timer.overtime());
+    if overtime > 0 {
+        (overtime, true)
+    } else {
+        (units(timer.remaining_time()), false)
+    }
+}
+
+fn write_remaining(
+    f: &mut impl Write,
+    (units, overtime): (u64, bool),
+    tenths: bool,
+) -> fmt::Result {
+    if overtime {
+        f.write_char('+')?;
+    }
+    let total_seconds = if tenths { units / 10 } else { units };
+    let (hours, minutes, seconds) = (
+        total_seconds / 3600,
+        total_seconds / 60 % 60,
+        total_seconds % 60,
+    );
+    if hours > 0 {
+        write!(f, "{}:{:02}:{:02}", hours, minutes, seconds)?;
+    } else {
+        write!(f, "{:02}:{:02}", minutes, seconds)?;
+    }
+    if tenths {
+        write!(f, ".{}", units % 10)?;
+    }
+    Ok(())
+}
+
 impl fmt::Display for Timer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let total_seconds = self.remaining_time().as_secs();
-        write!(f, "{:02}:{:02}", total_seconds / 60, total_seconds % 60)
+        write_remaining(f, display_seconds(self), false)
+    }
+}
+
+/// The formatted remaining time of a timer, which is only rebuilt when the
+/// displayed second, or tenth of a second, changes.
+#[derive(Default)]
+pub struct TimerLabel {
+    units: Option<((u64, bool), bool)>,
+    text: String,
+}
+
+impl TimerLabel {
+    pub fn get(&mut self, timer: &Timer, tenths: bool) -> &str {
+        let units = display_units(timer, tenths);
+        if self.units != Some((units, tenths)) {
+            self.units = Some((units, tenths));
+            self.text.clear();
+            let _ = write_remaining(&mut self.text, units, tenths);
+        }
+        &self.text
+    }
+}
+
+/// Something which happened to a timer.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TimerEvent {
+    /// The timer was started or resumed.
+    Started,
+    Paused,
+    /// The duration ran out.
+    Completed,
+    /// The displayed time changed, with the time now remaining.
+    Tick(Duration),
+}
+
+/// What was last seen of a timer.
+#[derive(PartialEq, Copy, Clone)]
+struct Observed {
+    running: bool,
+    over: bool,
+    seconds: (u64, bool),
+}
+
+impl Observed {
+    fn of(timer: &Timer) -> Self {
+        Self {
+            running: timer.is_running(),
+            over: timer.is_over(),
+            seconds: display_seconds(timer),
+        }
+    }
+}
+
+/// Sends the changes to a timer to each subscriber, so that they only have to
+/// react when something has happened.
+///
+/// Only the tray icon subscribes for now. Notifications, sounds, and the IPC
+/// status still check in on every update, as they also follow state the
+/// timer knows nothing about, such as acknowledged overtime and the task.
+#[derive(Default)]
+pub struct TimerEvents {
+    last: Option<Observed>,
+    subscribers: Vec<Sender<TimerEvent>>,
+}
+
+impl TimerEvents {
+    /// Receive the events from now on.
+    pub fn subscribe(&mut self) -> Receiver<TimerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Forget the timer, so that the next one observed is reported in full.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Compare the timer against what was last observed, and send out the
+    /// events which have happened since.
+    pub fn observe(&mut self, timer: &Timer) {
+        let now = Observed::of(timer);
+        let last = self.last.replace(now);
+        if last == Some(now) {
+            return;
+        }
+
+        let was = |f: fn(&Observed) -> bool| last.as_ref().map_or(false, f);
+        if now.running && !was(|o| o.running) {
+            self.send(TimerEvent::Started);
+        } else if !now.running && was(|o| o.running) {
+            self.send(TimerEvent::Paused);
+        }
+        if now.over && !was(|o| o.over) {
+            self.send(TimerEvent::Completed);
+        }
+        if last.map(|o| o.seconds) != Some(now.seconds) {
+            self.send(TimerEvent::Tick(timer.remaining_time()));
+        }
+    }
+
+    fn send(&mut self, event: TimerEvent) {
+        // subscribers which have gone away are dropped
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event).is_ok());
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::MockClock;
 
     #[test]
     fn test_timer() {
@@ -102,6 +436,7 @@ mod tests {
             duration: Duration::from_secs(20),
             accumulated_time: Duration::from_secs(12),
             start_timestamp: None,
+            count_up: false,
         };
 
         assert!(t2.is_paused());
@@ -109,12 +444,17 @@ mod tests {
         assert!(t2.has_started());
         assert_eq!(Duration::from_secs(12), t2.elapsed());
         assert_eq!(Duration::from_secs(8), t2.remaining_time());
+        assert_eq!(Duration::ZERO, t2.overtime());
 
         t2.start();
         assert!(!t2.is_paused());
         assert!(t2.is_running());
         assert!(t2.has_started());
 
+        let checkpoint = t2.checkpoint();
+        assert!(checkpoint.is_paused());
+        assert_eq!(t2.remaining_time(), checkpoint.remaining_time());
+
         // test formatting
         assert_eq!("00:00", format!("{}", Timer::default()));
         assert_eq!(
@@ -122,4 +462,170 @@ mod tests {
             format!("{}", Timer::from_duration(Duration::from_secs(754)))
         );
     }
+
+    #[test]
+    fn test_display_change() {
+        let timer = Timer {
+            duration: Duration::from_millis(12_300),
+            ..Default::default()
+        };
+        assert_eq!(Duration::from_millis(300), timer.until_display_change(1));
+        assert_eq!(Duration::from_millis(1_300), timer.until_display_change(5));
+        assert_eq!(Duration::from_millis(3_300), timer.until_display_change(4));
+        assert_eq!(Duration::from_millis(100), timer.until_tenth_change());
+    }
+
+    #[test]
+    fn test_overtime() {
+        let clock = MockClock::new();
+        clock::set(clock);
+
+        let mut timer = Timer {
+            duration: Duration::from_secs(20),
+            accumulated_time: Duration::from_millis(153_400),
+            start_timestamp: None,
+            count_up: false,
+        };
+        assert!(timer.is_over());
+        assert_eq!(Duration::from_millis(133_400), timer.overtime());
+        assert_eq!("+02:13", format!("{}", timer));
+        assert_eq!("+02:13.4", timer.format_remaining(true));
+
+        timer.start();
+        assert_eq!(Duration::from_millis(600), timer.until_display_change(1));
+        assert_eq!(Duration::from_millis(100), timer.until_tenth_change());
+    }
+
+    #[test]
+    fn test_label() {
+        let mut label = TimerLabel::default();
+        let timer = Timer::from_duration(Duration::from_millis(12_300));
+        assert_eq!("00:12", label.get(&timer, false));
+        assert_eq!("00:12.3", label.get(&timer, true));
+        assert_eq!(
+            "12:34",
+            label.get(&Timer::from_duration(Duration::from_secs(754)), false)
+        );
+
+        let overtime = Timer {
+            duration: Duration::from_secs(20),
+            accumulated_time: Duration::from_millis(153_400),
+            start_timestamp: None,
+            count_up: false,
+        };
+        assert_eq!("+02:13", label.get(&overtime, false));
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new();
+        clock::set(clock.clone());
+
+        let mut timer = Timer::from_duration(Duration::from_secs(60));
+        timer.start();
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(Duration::from_secs(45), timer.remaining_time());
+        timer.pause();
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(Duration::from_secs(15), timer.elapsed());
+        timer.start();
+        clock.advance(Duration::from_secs(30));
+        timer.add_elapsed(Duration::from_secs(15));
+        assert!(timer.is_over());
+        timer.extend(Duration::from_secs(60));
+        assert_eq!(Duration::from_secs(60), timer.remaining_time());
+    }
+
+    #[test]
+    fn test_saved_timer() {
+        let clock = MockClock::new();
+        clock::set(clock);
+
+        // a running timer keeps counting across a restart
+        let mut timer = Timer::from_duration(Duration::from_secs(60));
+        timer.start();
+        let saved = SavedTimer::from(timer);
+        let started_at = saved.started_at.unwrap();
+        let saved = SavedTimer {
+            started_at: Some(started_at - Duration::from_secs(20)),
+            ..saved
+        };
+        let restored = Timer::from(saved);
+        assert!(restored.is_running());
+        let remaining = restored.remaining_time().as_secs_f32();
+        assert!((remaining - 40.).abs() < 1., "{} remaining", remaining);
+    }
+
+    #[test]
+    fn test_count_up() {
+        let clock = MockClock::new();
+        clock::set(clock.clone());
+
+        let mut timer = Timer::count_up();
+        timer.start();
+        clock.advance(Duration::from_millis(90_500));
+        assert!(!timer.is_over());
+        assert_eq!(Duration::ZERO, timer.remaining_time());
+        assert_eq!("01:30", TimerLabel::default().get(&timer, false));
+        assert_eq!(Duration::from_millis(500), timer.until_display_change(1));
+        assert_eq!(Duration::from_millis(100), timer.until_tenth_change());
+        assert!(Timer::from(SavedTimer::from(timer)).is_counting_up());
+    }
+
+    #[test]
+    fn test_events() {
+        let clock = MockClock::new();
+        clock::set(clock.clone());
+
+        let mut events = TimerEvents::default();
+        let receiver = events.subscribe();
+        let mut t6 = Timer::from_duration(Duration::from_secs(2));
+        events.observe(&t6);
+        t6.start();
+        events.observe(&t6);
+        clock.advance(Duration::from_millis(500));
+        events.observe(&t6);
+        clock.advance(Duration::from_millis(1_500));
+        events.observe(&t6);
+        t6.pause();
+        events.observe(&t6);
+        events.reset();
+        events.observe(&t6);
+        assert_eq!(
+            vec![
+                TimerEvent::Tick(Duration::from_secs(2)),
+                TimerEvent::Started,
+                TimerEvent::Tick(Duration::from_millis(1_500)),
+                TimerEvent::Completed,
+                TimerEvent::Tick(Duration::ZERO),
+                TimerEvent::Paused,
+                TimerEvent::Completed,
+                TimerEvent::Tick(Duration::ZERO),
+            ],
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_format_hours() {
+        let format = |secs: u64| {
+            format!("{}", Timer::from_duration(Duration::from_secs(secs)))
+        };
+        assert_eq!("59:59", format(3_599));
+        assert_eq!("1:00:00", format(3_600));
+        assert_eq!("1:00:01", format(3_601));
+        assert_eq!("1:30:00", format(90 * 60));
+        assert_eq!("1:59:59", format(7_199));
+        assert_eq!("2:00:00", format(7_200));
+        assert_eq!("10:00:00", format(36_000));
+
+        let overtime = Timer {
+            duration: Duration::from_secs(60),
+            accumulated_time: Duration::from_millis(3_660_500),
+            start_timestamp: None,
+            count_up: false,
+        };
+        assert_eq!("+1:00:00", format!("{}", overtime));
+        assert_eq!("+1:00:00.5", overtime.format_remaining(true));
+    }
 }
@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Color32};
+
+/// How long each toast is shown for.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+/// Short-lived error messages shown in the corner of the window.
+#[derive(Default)]
+pub struct Toasts {
+    /// Messages currently being shown, with the time they were created.
+    toasts: Vec<(String, Instant)>,
+    /// Messages which are only ever shown once.
+    shown_once: HashSet<String>,
+}
+
+impl Toasts {
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.toasts.push((message.into(), Instant::now()));
+    }
+
+    /// Show an error, unless the same message has already been shown once.
+    pub fn error_once(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        if self.shown_once.insert(message.clone()) {
+            self.error(message);
+        }
+    }
+
+    /// Show the current toasts, returning the time until the next one
+    /// expires.
+    pub fn show(&mut self, ctx: &egui::CtxRef) -> Option<Duration> {
+        self.toasts
+            .retain(|(_, created)| created.elapsed() < TOAST_DURATION);
+
+        if self.toasts.is_empty() {
+            return None;
+        }
+
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8., -8.))
+            .show(ctx, |ui| {
+                for (message, _) in &self.toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(Color32::LIGHT_RED, message);
+                    });
+                }
+            });
+
+        self.toasts
+            .iter()
+            .map(|(_, created)| {
+                TOAST_DURATION.saturating_sub(created.elapsed())
+            })
+            .min()
+    }
+}
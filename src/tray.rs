@@ -0,0 +1,192 @@
+use ksni::{Handle, Icon, ToolTip, Tray, TrayService};
+
+use crate::State;
+
+/// Size of the tray icon in pixels.
+const ICON_SIZE: usize = 22;
+/// Each pixel of a glyph is drawn as a square of this many pixels.
+const GLYPH_SCALE: usize = 2;
+
+/// 3x5 pixel digits, one bit per pixel in row-major order.
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+
+/// What is shown in the tray, which only changes once per minute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrayStatus {
+    pub state: State,
+    /// Remaining time in whole minutes, rounded up.
+    pub minutes: u64,
+    pub paused: bool,
+    /// The time at which the current interval will end, if running.
+    pub ends_at: Option<String>,
+}
+
+struct TimeFloTray {
+    status: Option<TrayStatus>,
+}
+
+impl Tray for TimeFloTray {
+    fn id(&self) -> String {
+        "time-flo".to_owned()
+    }
+
+    fn title(&self) -> String {
+        "TimeFlo".to_owned()
+    }
+
+    fn icon_pixmap(&self) -> Vec<Icon> {
+        match &self.status {
+            Some(status) => vec![Icon {
+                width: ICON_SIZE as i32,
+                height: ICON_SIZE as i32,
+                data: render_icon(status.minutes, state_color(status.state)),
+            }],
+            None => Vec::new(),
+        }
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        let description = match &self.status {
+            Some(status) => {
+                let mut description = format!(
+                    "{}: {} min remaining",
+                    status.state, status.minutes
+                );
+                if status.paused {
+                    description.push_str(" (paused)");
+                } else if let Some(ends_at) = &status.ends_at {
+                    description.push_str(&format!(" (until {})", ends_at));
+                }
+                description
+            }
+            None => String::new(),
+        };
+
+        ToolTip {
+            title: "TimeFlo".to_owned(),
+            description,
+            ..Default::default()
+        }
+    }
+}
+
+/// A system tray icon showing the remaining minutes.
+pub struct TrayIcon {
+    handle: Handle<TimeFloTray>,
+    last_status: Option<TrayStatus>,
+}
+
+impl TrayIcon {
+    pub fn start() -> Self {
+        let service = TrayService::new(TimeFloTray { status: None });
+        let handle = service.handle();
+        service.spawn();
+
+        Self {
+            handle,
+            last_status: None,
+        }
+    }
+
+    /// Update the tray, if what it shows has changed.
+    pub fn update(&mut self, status: TrayStatus) {
+        if self.last_status.as_ref() == Some(&status) {
+            return;
+        }
+
+        let new_status = status.clone();
+        self.handle
+            .update(move |tray| tray.status = Some(new_status));
+        self.last_status = Some(status);
+    }
+}
+
+fn state_color(state: State) -> [u8; 3] {
+    match state {
+        State::Task => [0xc0, 0x30, 0x30],
+        State::ShortBreak | State::LongBreak => [0x30, 0x90, 0x40],
+        State::Idle => [0x60, 0x60, 0x60],
+    }
+}
+
+/// Render two digits of minutes onto a colored disc, as ARGB32 pixels in
+/// network byte order.
+fn render_icon(minutes: u64, [r, g, b]: [u8; 3]) -> Vec<u8> {
+    let mut data = vec![0; ICON_SIZE * ICON_SIZE * 4];
+
+    let mut set_pixel = |x: usize, y: usize, argb: [u8; 4]| {
+        let i = (y * ICON_SIZE + x) * 4;
+        data[i..i + 4].copy_from_slice(&argb);
+    };
+
+    // background disc
+    let center = (ICON_SIZE as f32 - 1.) / 2.;
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let dx = x as f32 - center;
+            let dy = y as f32 - center;
+            if dx * dx + dy * dy <= center * center {
+                set_pixel(x, y, [0xff, r, g, b]);
+            }
+        }
+    }
+
+    // digits, centered
+    let minutes = minutes.min(99) as usize;
+    let glyph_width = 3 * GLYPH_SCALE;
+    let glyph_height = 5 * GLYPH_SCALE;
+    let text_width = 2 * glyph_width + GLYPH_SCALE;
+    let x0 = (ICON_SIZE - text_width) / 2;
+    let y0 = (ICON_SIZE - glyph_height) / 2;
+
+    for (n, digit) in [minutes / 10, minutes % 10].iter().enumerate() {
+        let glyph = DIGITS[*digit];
+        let glyph_x = x0 + n * (glyph_width + GLYPH_SCALE);
+
+        for y in 0..glyph_height {
+            for x in 0..glyph_width {
+                let bit = 14 - ((y / GLYPH_SCALE) * 3 + x / GLYPH_SCALE);
+                if glyph & (1 << bit) != 0 {
+                    set_pixel(glyph_x + x, y0 + y, [0xff; 4]);
+                }
+            }
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_icon() {
+        let data = render_icon(10, [1, 2, 3]);
+        let pixel = |x: usize, y: usize| {
+            let i = (y * ICON_SIZE + x) * 4;
+            [data[i], data[i + 1], data[i + 2], data[i + 3]]
+        };
+
+        assert_eq!(ICON_SIZE * ICON_SIZE * 4, data.len());
+
+        // corners are transparent, the background is the state color
+        assert_eq!([0; 4], pixel(0, 0));
+        assert_eq!([0xff, 1, 2, 3], pixel(ICON_SIZE / 2, 2));
+
+        // top-left of the "1" is blank, top-middle is lit
+        assert_eq!([0xff, 1, 2, 3], pixel(4, 6));
+        assert_eq!([0xff; 4], pixel(6, 6));
+    }
+}
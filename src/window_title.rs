@@ -0,0 +1,290 @@
+//! Guessing the task from the title of the focused window, using rules set by
+//! the user, and keeping track of which applications are focused.
+
+use std::fmt;
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::history::AppTime;
+
+/// How often the focused window is sampled during a task interval.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What to do with the task guessed from window titles.
+#[derive(PartialEq, Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum TitleDetection {
+    Off,
+    /// Offer the guess when no task has been entered.
+    Suggest,
+    /// Fill in the guess when no task has been entered.
+    Assign,
+}
+
+impl TitleDetection {
+    pub const ALL: [TitleDetection; 3] = [
+        TitleDetection::Off,
+        TitleDetection::Suggest,
+        TitleDetection::Assign,
+    ];
+}
+
+impl Default for TitleDetection {
+    fn default() -> Self {
+        TitleDetection::Off
+    }
+}
+
+impl fmt::Display for TitleDetection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TitleDetection::Off => "Off",
+            TitleDetection::Suggest => "Suggest the task",
+            TitleDetection::Assign => "Fill in the task",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Maps window titles containing some text to a task.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TitleRule {
+    /// Text to look for in the title, ignoring case.
+    pub pattern: String,
+    pub task: String,
+}
+
+impl TitleRule {
+    pub fn matches(&self, title: &str) -> bool {
+        let pattern = self.pattern.trim();
+        !pattern.is_empty()
+            && title.to_lowercase().contains(&pattern.to_lowercase())
+    }
+}
+
+/// The task of the first rule which matches the title.
+pub fn task_for<'a>(rules: &'a [TitleRule], title: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(title) && !rule.task.trim().is_empty())
+        .map(|rule| rule.task.trim())
+}
+
+fn title_command() -> Option<Command> {
+    if cfg!(target_os = "linux") {
+        // only X11 exposes the focused window to other programs
+        let mut command = Command::new("xdotool");
+        command.args(&["getactivewindow", "getwindowname"]);
+        Some(command)
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args(&[
+            "-e",
+            "tell application \"System Events\" to get name of first \
+             application process whose frontmost is true",
+        ]);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// The trimmed output of a command, if it succeeded with any.
+fn output_of(mut command: Command) -> Option<String> {
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    Some(output).filter(|output| !output.is_empty())
+}
+
+/// The title of the focused window, or the name of the frontmost application
+/// on macOS.
+pub fn focused_title() -> Option<String> {
+    output_of(title_command()?)
+}
+
+/// The name of the focused window's program.
+pub fn focused_application() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        let mut command = Command::new("xdotool");
+        command.args(&["getactivewindow", "getwindowpid"]);
+        let pid: u32 = output_of(command)?.parse().ok()?;
+
+        let name = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+        Some(name.trim().to_owned()).filter(|name| !name.is_empty())
+    } else {
+        focused_title()
+    }
+}
+
+/// Samples the focused window over an interval, tallying the tasks it
+/// suggests and the applications it belongs to.
+#[derive(Default)]
+pub struct TitleSampler {
+    last_sample: Option<Instant>,
+    tally: Vec<(String, u32)>,
+    applications: Vec<AppTime>,
+}
+
+impl TitleSampler {
+    /// Sample the focused window if it is time to, returning how long until
+    /// the next sample. Titles are only read if there are rules to match
+    /// them against.
+    pub fn sample(
+        &mut self,
+        rules: &[TitleRule],
+        applications: bool,
+    ) -> Duration {
+        if let Some(last_sample) = self.last_sample {
+            let since = clock::elapsed(last_sample);
+            if since < SAMPLE_INTERVAL {
+                return SAMPLE_INTERVAL - since;
+            }
+        }
+
+        self.last_sample = Some(clock::now());
+        if !rules.is_empty() {
+            if let Some(title) = focused_title() {
+                self.record(rules, &title);
+            }
+        }
+        if applications {
+            if let Some(name) = focused_application() {
+                self.record_application(&name);
+            }
+        }
+        SAMPLE_INTERVAL
+    }
+
+    fn record_application(&mut self, name: &str) {
+        match self.applications.iter_mut().find(|app| app.name == name) {
+            Some(app) => app.samples += 1,
+            None => self.applications.push(AppTime {
+                name: name.to_owned(),
+                samples: 1,
+            }),
+        }
+    }
+
+    /// The applications sampled so far, most sampled first.
+    pub fn take_applications(&mut self) -> Vec<AppTime> {
+        let mut applications = std::mem::take(&mut self.applications);
+        applications.sort_by(|a, b| b.samples.cmp(&a.samples));
+        applications
+    }
+
+    fn record(&mut self, rules: &[TitleRule], title: &str) {
+        let task = match task_for(rules, title) {
+            Some(task) => task,
+            None => return,
+        };
+
+        match self.tally.iter_mut().find(|(t, _)| t == task) {
+            Some((_, count)) => *count += 1,
+            None => self.tally.push((task.to_owned(), 1)),
+        }
+    }
+
+    /// The task suggested most often, or first on a tie.
+    pub fn suggestion(&self) -> Option<&str> {
+        let mut best: Option<&(String, u32)> = None;
+        for entry in &self.tally {
+            if best.map_or(true, |best| entry.1 > best.1) {
+                best = Some(entry);
+            }
+        }
+        best.map(|(task, _)| task.as_str())
+    }
+
+    /// Start over for a new interval.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Edit which tasks window titles point to, first match first.
+pub fn rules_editor(ui: &mut egui::Ui, rules: &mut Vec<TitleRule>) {
+    let mut removed = None;
+
+    egui::Grid::new("title_rules").show(ui, |ui| {
+        for (i, rule) in rules.iter_mut().enumerate() {
+            ui.add(
+                egui::TextEdit::singleline(&mut rule.pattern)
+                    .hint_text("Title contains")
+                    .desired_width(120.),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut rule.task)
+                    .hint_text("Task")
+                    .desired_width(120.),
+            );
+            if ui.button("\u{1F5D9}").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(i) = removed {
+        rules.remove(i);
+    }
+
+    if ui.button("Add rule").clicked() {
+        rules.push(TitleRule::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_rules() {
+        let rule = |pattern: &str, task: &str| TitleRule {
+            pattern: pattern.to_owned(),
+            task: task.to_owned(),
+        };
+        let rules = vec![
+            rule("", "Everything"),
+            rule("time-flo", "TimeFlo"),
+            rule("Inbox", "Email"),
+            rule("mozilla firefox", ""),
+        ];
+        assert_eq!(
+            Some("TimeFlo"),
+            task_for(&rules, "app.rs - time-flo - Visual Studio Code")
+        );
+        assert_eq!(Some("Email"), task_for(&rules, "INBOX (3) - Mail"));
+        assert_eq!(None, task_for(&rules, "News - Mozilla Firefox"));
+
+        let mut sampler = TitleSampler::default();
+        assert_eq!(None, sampler.suggestion());
+        sampler.record(&rules, "Inbox");
+        sampler.record(&rules, "main.rs - time-flo");
+        sampler.record(&rules, "Terminal");
+        assert_eq!(Some("Email"), sampler.suggestion());
+        sampler.record(&rules, "lib.rs - time-flo");
+        assert_eq!(Some("TimeFlo"), sampler.suggestion());
+
+        for name in ["firefox", "code", "code"] {
+            sampler.record_application(name);
+        }
+        let applications = sampler.take_applications();
+        assert_eq!("code", applications[0].name);
+        assert_eq!(2, applications[0].samples);
+        assert_eq!(1, applications[1].samples);
+        assert!(sampler.take_applications().is_empty());
+
+        sampler.reset();
+        assert_eq!(None, sampler.suggestion());
+    }
+}